@@ -0,0 +1,106 @@
+//! Compares the size of instances encoded as JSON and with `jddf::binary`.
+//!
+//! Run with `cargo bench --bench binary_size`.
+
+use jddf::binary::Codec;
+use jddf::gen::Generator;
+use jddf::{Schema, SerdeSchema};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde_json::{json, Value};
+
+const INSTANCES: usize = 1000;
+
+fn main() {
+    let schemas = vec![
+        (
+            "user",
+            json!({
+                "properties": {
+                    "id": { "type": "uint32" },
+                    "name": { "type": "string" },
+                    "email": { "type": "string" },
+                    "role": { "enum": ["admin", "member", "guest"] },
+                    "createdAt": { "type": "timestamp" },
+                },
+                "optionalProperties": {
+                    "age": { "type": "uint8" },
+                    "verified": { "type": "boolean" },
+                },
+            }),
+        ),
+        (
+            "metrics",
+            json!({
+                "values": {
+                    "elements": {
+                        "properties": {
+                            "at": { "type": "timestamp" },
+                            "value": { "type": "float64" },
+                            "count": { "type": "int32" },
+                        },
+                    },
+                },
+            }),
+        ),
+        (
+            "events",
+            json!({
+                "elements": {
+                    "discriminator": {
+                        "tag": "type",
+                        "mapping": {
+                            "click": {
+                                "properties": {
+                                    "x": { "type": "int16" },
+                                    "y": { "type": "int16" },
+                                },
+                            },
+                            "key": {
+                                "properties": {
+                                    "code": { "type": "uint16" },
+                                    "shift": { "type": "boolean" },
+                                },
+                            },
+                        },
+                    },
+                },
+            }),
+        ),
+    ];
+
+    let codec = Codec::new();
+    let generator = Generator::new();
+
+    println!(
+        "{:<10} {:>12} {:>12} {:>8}",
+        "schema", "json", "binary", "ratio"
+    );
+    for (name, schema) in schemas {
+        let serde_schema: SerdeSchema = serde_json::from_value(schema).unwrap();
+        let schema = Schema::from_serde(serde_schema).unwrap();
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let instances: Vec<Value> = (0..INSTANCES)
+            .map(|_| generator.generate(&schema, &mut rng))
+            .collect();
+
+        let json: usize = instances
+            .iter()
+            .map(|instance| serde_json::to_vec(instance).unwrap().len())
+            .sum();
+
+        let binary: usize = instances
+            .iter()
+            .map(|instance| codec.encode(&schema, instance).unwrap().len())
+            .sum();
+
+        println!(
+            "{:<10} {:>12} {:>12} {:>7.1}%",
+            name,
+            json,
+            binary,
+            100.0 * binary as f64 / json as f64
+        );
+    }
+}
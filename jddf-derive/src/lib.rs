@@ -0,0 +1,498 @@
+//! `#[derive(JddfSchema)]`, for building JDDF schemas from Rust types.
+//!
+//! Don't depend on this crate directly. Instead, enable the `derive` feature
+//! of `jddf`, which re-exports the derive alongside the `JddfSchema` trait it
+//! implements. See the docs for `jddf::reflect` for more.
+//!
+//! The derive reads serde's attributes, so that the schema describes the JSON
+//! serde produces:
+//!
+//! * `rename`, `rename_all`, and `rename_all_fields` rename properties, enum
+//!   values, and discriminator tags.
+//! * `tag` turns an enum into a `discriminator`, and `tag` with `content`
+//!   puts each variant's data in a property alongside the tag.
+//! * `deny_unknown_fields` disallows additional properties.
+//! * `skip` and `skip_serializing` leave out a field or variant.
+//! * `default`, `skip_deserializing`, and `skip_serializing_if` make a
+//!   property optional. `Option` fields are always optional.
+//! * `flatten` merges in the properties of the flattened field.
+//! * Fields using `with`, `serialize_with`, or `deserialize_with` accept
+//!   anything, since their JSON representation is unknown.
+//!
+//! Untagged enums, externally tagged enums with data, tuples, and generic
+//! types have no JDDF equivalent, and are a compile-time error.
+
+extern crate proc_macro;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::meta::ParseNestedMeta;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, Attribute, Data, DataEnum, DeriveInput, Error, Fields, FieldsNamed,
+    GenericArgument, LitStr, PathArguments, Result, Token, Type,
+};
+
+#[proc_macro_derive(JddfSchema, attributes(serde))]
+pub fn derive_jddf_schema(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "JddfSchema can't be derived for generic types",
+        ));
+    }
+
+    let attrs = Attrs::parse(&input.attrs)?;
+    let ident = &input.ident;
+    let name = attrs
+        .rename
+        .clone()
+        .unwrap_or_else(|| ident.unraw().to_string());
+
+    let body = match &input.data {
+        Data::Struct(data) => expand_struct(&attrs, &data.fields)?,
+        Data::Enum(data) => expand_enum(&attrs, data)?,
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                ident,
+                "JddfSchema can't be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl ::jddf::reflect::JddfSchema for #ident {
+            fn jddf_schema(
+                defs: &mut ::jddf::reflect::__private::Definitions<
+                    ::std::string::String,
+                    ::jddf::Schema,
+                >,
+            ) -> ::jddf::Schema {
+                ::jddf::reflect::__private::define(defs, #name, |defs| #body)
+            }
+        }
+    })
+}
+
+fn expand_struct(attrs: &Attrs, fields: &Fields) -> Result<TokenStream> {
+    match fields {
+        Fields::Named(fields) if attrs.transparent => {
+            let field = fields
+                .named
+                .iter()
+                .find(|field| !Attrs::parse(&field.attrs).map(|a| a.skip).unwrap_or(false))
+                .ok_or_else(|| Error::new_spanned(fields, "transparent struct has no field"))?;
+
+            Ok(schema_of(&field.ty))
+        }
+        Fields::Named(fields) => {
+            expand_properties(attrs.rename_all, !attrs.deny_unknown_fields, fields)
+        }
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+            Ok(schema_of(&fields.unnamed[0].ty))
+        }
+        Fields::Unnamed(fields) => Err(Error::new_spanned(
+            fields,
+            "JDDF has no way to describe tuples",
+        )),
+        Fields::Unit => Ok(quote! { ::jddf::reflect::__private::empty() }),
+    }
+}
+
+fn expand_enum(attrs: &Attrs, data: &DataEnum) -> Result<TokenStream> {
+    if attrs.untagged {
+        return Err(Error::new_spanned(
+            &data.variants,
+            "JDDF has no way to describe untagged enums",
+        ));
+    }
+
+    let mut names = vec![];
+    let mut variants = vec![];
+    for variant in &data.variants {
+        let variant_attrs = Attrs::parse(&variant.attrs)?;
+        if variant_attrs.skip {
+            continue;
+        }
+
+        let name = match &variant_attrs.rename {
+            Some(rename) => rename.clone(),
+            None => {
+                let ident = variant.ident.unraw().to_string();
+                match attrs.rename_all {
+                    Some(rule) => rule.apply_to_variant(&ident),
+                    None => ident,
+                }
+            }
+        };
+
+        names.push(name);
+        variants.push((variant, variant_attrs));
+    }
+
+    let tag = match &attrs.tag {
+        Some(tag) => tag,
+        None => {
+            if let Some((variant, _)) = variants
+                .iter()
+                .find(|(variant, _)| !matches!(variant.fields, Fields::Unit))
+            {
+                return Err(Error::new_spanned(
+                    &variant.fields,
+                    "JDDF can only describe enums with data if they're tagged, using #[serde(tag = \"...\")]",
+                ));
+            }
+
+            return Ok(quote! {
+                ::jddf::reflect::__private::enumeration(&[#(#names),*])
+            });
+        }
+    };
+
+    let allow_additional = !attrs.deny_unknown_fields;
+    let mut schemas = vec![];
+    for (variant, variant_attrs) in &variants {
+        let rename_all = variant_attrs.rename_all.or(attrs.rename_all_fields);
+
+        let data = match &variant.fields {
+            Fields::Unit => None,
+            Fields::Named(fields) => Some(expand_properties(rename_all, allow_additional, fields)?),
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                Some(schema_of(&fields.unnamed[0].ty))
+            }
+            Fields::Unnamed(fields) => {
+                return Err(Error::new_spanned(
+                    fields,
+                    "JDDF has no way to describe tuples",
+                ))
+            }
+        };
+
+        let schema = match (&attrs.content, data) {
+            (_, None) => quote! {
+                ::jddf::reflect::__private::Properties::new(#allow_additional).into_schema()
+            },
+            (Some(content), Some(data)) => quote! {{
+                let mut properties = ::jddf::reflect::__private::Properties::new(#allow_additional);
+                let schema = #data;
+                properties.required(#content, schema);
+                properties.into_schema()
+            }},
+            (None, Some(data)) => match &variant.fields {
+                Fields::Named(_) => data,
+                _ => quote! {{
+                    let schema = #data;
+                    ::jddf::reflect::__private::properties_of(defs, schema)
+                }},
+            },
+        };
+
+        schemas.push(schema);
+    }
+
+    Ok(quote! {
+        ::jddf::reflect::__private::discriminator(#tag, vec![#((#names, #schemas)),*])
+    })
+}
+
+fn expand_properties(
+    rename_all: Option<RenameRule>,
+    allow_additional: bool,
+    fields: &FieldsNamed,
+) -> Result<TokenStream> {
+    let mut stmts = vec![];
+
+    for field in &fields.named {
+        let attrs = Attrs::parse(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+
+        let ty = &field.ty;
+        if attrs.flatten {
+            let schema = schema_of(ty);
+            stmts.push(quote! {
+                let schema = #schema;
+                properties.flatten(defs, schema);
+            });
+            continue;
+        }
+
+        let ident = field.ident.as_ref().unwrap().unraw().to_string();
+        let name = match (&attrs.rename, rename_all) {
+            (Some(rename), _) => rename.clone(),
+            (None, Some(rule)) => rule.apply_to_field(&ident),
+            (None, None) => ident,
+        };
+
+        let (schema, optional) = if attrs.with {
+            (
+                quote! { ::jddf::reflect::__private::empty() },
+                attrs.optional,
+            )
+        } else {
+            match option_inner(ty) {
+                Some(inner) if attrs.skip_serializing_if => (schema_of(inner), true),
+                Some(_) => (schema_of(ty), true),
+                None => (schema_of(ty), attrs.optional || attrs.skip_serializing_if),
+            }
+        };
+
+        let method = if optional {
+            quote! { optional }
+        } else {
+            quote! { required }
+        };
+
+        stmts.push(quote! {
+            let schema = #schema;
+            properties.#method(#name, schema);
+        });
+    }
+
+    Ok(quote! {{
+        let mut properties = ::jddf::reflect::__private::Properties::new(#allow_additional);
+        #(#stmts)*
+        properties.into_schema()
+    }})
+}
+
+fn schema_of(ty: &Type) -> TokenStream {
+    quote! { <#ty as ::jddf::reflect::JddfSchema>::jddf_schema(defs) }
+}
+
+/// The `T` in `Option<T>`, if `ty` looks like an `Option`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+
+    let segment = path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The subset of serde's attributes which affect the schema.
+#[derive(Default)]
+struct Attrs {
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+    rename_all_fields: Option<RenameRule>,
+    tag: Option<String>,
+    content: Option<String>,
+    untagged: bool,
+    deny_unknown_fields: bool,
+    transparent: bool,
+    skip: bool,
+    skip_serializing_if: bool,
+    optional: bool,
+    flatten: bool,
+    with: bool,
+}
+
+impl Attrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut out = Self::default();
+
+        for attr in attrs {
+            if !attr.path().is_ident("serde") {
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                let key = match meta.path.get_ident() {
+                    Some(ident) => ident.to_string(),
+                    None => return skip_value(&meta),
+                };
+
+                match key.as_str() {
+                    "rename" => out.rename = serialize_name(&meta)?.or(out.rename.take()),
+                    "rename_all" => {
+                        if let Some(rule) = serialize_name(&meta)? {
+                            out.rename_all = Some(RenameRule::parse(&rule, meta.path.span())?);
+                        }
+                    }
+                    "rename_all_fields" => {
+                        if let Some(rule) = serialize_name(&meta)? {
+                            out.rename_all_fields =
+                                Some(RenameRule::parse(&rule, meta.path.span())?);
+                        }
+                    }
+                    "tag" => out.tag = Some(meta.value()?.parse::<LitStr>()?.value()),
+                    "content" => out.content = Some(meta.value()?.parse::<LitStr>()?.value()),
+                    "untagged" => out.untagged = true,
+                    "deny_unknown_fields" => out.deny_unknown_fields = true,
+                    "transparent" => out.transparent = true,
+                    "skip" | "skip_serializing" => out.skip = true,
+                    "skip_deserializing" => out.optional = true,
+                    "skip_serializing_if" => {
+                        skip_value(&meta)?;
+                        out.skip_serializing_if = true;
+                    }
+                    "default" => {
+                        skip_value(&meta)?;
+                        out.optional = true;
+                    }
+                    "flatten" => out.flatten = true,
+                    "with" | "serialize_with" | "deserialize_with" => {
+                        skip_value(&meta)?;
+                        out.with = true;
+                    }
+                    _ => skip_value(&meta)?,
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(out)
+    }
+}
+
+/// Parse `key = "name"` or `key(serialize = "name", ...)`, returning the name
+/// used when serializing.
+fn serialize_name(meta: &ParseNestedMeta) -> Result<Option<String>> {
+    if meta.input.peek(Token![=]) {
+        return Ok(Some(meta.value()?.parse::<LitStr>()?.value()));
+    }
+
+    let mut out = None;
+    meta.parse_nested_meta(|meta| {
+        if meta.path.is_ident("serialize") {
+            out = Some(meta.value()?.parse::<LitStr>()?.value());
+            Ok(())
+        } else {
+            skip_value(&meta)
+        }
+    })?;
+
+    Ok(out)
+}
+
+/// Consume the value of an attribute the derive doesn't care about.
+fn skip_value(meta: &ParseNestedMeta) -> Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        let content;
+        syn::parenthesized!(content in meta.input);
+        content.parse::<TokenStream>()?;
+    }
+
+    Ok(())
+}
+
+/// The case conventions of `#[serde(rename_all = "...")]`.
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(rule: &str, span: proc_macro2::Span) -> Result<Self> {
+        Ok(match rule {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            _ => return Err(Error::new(span, format!("unknown rename rule: {:?}", rule))),
+        })
+    }
+
+    /// Rename a variant, which is presumed to be in `PascalCase`.
+    fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            RenameRule::Pascal => variant.to_owned(),
+            RenameRule::Lower => variant.to_ascii_lowercase(),
+            RenameRule::Upper => variant.to_ascii_uppercase(),
+            RenameRule::Camel => {
+                let mut chars = variant.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            }
+            RenameRule::Snake => {
+                let mut out = String::new();
+                for (i, c) in variant.char_indices() {
+                    if i > 0 && c.is_uppercase() {
+                        out.push('_');
+                    }
+                    out.push(c.to_ascii_lowercase());
+                }
+                out
+            }
+            RenameRule::ScreamingSnake => RenameRule::Snake
+                .apply_to_variant(variant)
+                .to_ascii_uppercase(),
+            RenameRule::Kebab => RenameRule::Snake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+            RenameRule::ScreamingKebab => RenameRule::ScreamingSnake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+        }
+    }
+
+    /// Rename a field, which is presumed to be in `snake_case`.
+    fn apply_to_field(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_owned(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => {
+                let mut out = String::new();
+                let mut capitalize = true;
+                for c in field.chars() {
+                    if c == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        out.push(c.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        out.push(c);
+                    }
+                }
+                out
+            }
+            RenameRule::Camel => {
+                let pascal = RenameRule::Pascal.apply_to_field(field);
+                let mut chars = pascal.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            }
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use jddf::reflect::schema_for;
+use jddf::{Schema, Validator};
+use jddf_derive::JddfSchema;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+
+fn to_json(schema: Schema) -> serde_json::Value {
+    serde_json::to_value(schema.into_serde()).unwrap()
+}
+
+/// Asserts that `value` serializes to an instance which is valid against the
+/// schema derived for its type.
+fn assert_valid<T: Serialize + jddf::JddfSchema>(value: &T) {
+    let schema = schema_for::<T>();
+    let instance = serde_json::to_value(value).unwrap();
+    let errors = Validator::new().validate(&schema, &instance).unwrap();
+    assert!(errors.is_empty(), "{} is invalid", instance);
+}
+
+#[derive(Serialize, JddfSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct User {
+    user_id: String,
+    #[serde(rename = "years")]
+    age: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nickname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    manager: Option<Box<User>>,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+    scores: Vec<f64>,
+    #[serde(skip)]
+    #[allow(dead_code)]
+    password: String,
+}
+
+#[test]
+fn derive_struct() {
+    assert_eq!(
+        to_json(schema_for::<User>()),
+        json!({
+            "definitions": {
+                "User": {
+                    "properties": {
+                        "userId": { "type": "string" },
+                        "years": { "type": "uint8" },
+                        "scores": { "elements": { "type": "float64" } },
+                    },
+                    "optionalProperties": {
+                        "nickname": { "type": "string" },
+                        "manager": { "ref": "User" },
+                        "labels": { "values": { "type": "string" } },
+                    },
+                },
+            },
+            "ref": "User",
+        })
+    );
+
+    assert_valid(&User {
+        user_id: "alice".to_owned(),
+        age: 42,
+        nickname: None,
+        manager: Some(Box::new(User {
+            user_id: "bob".to_owned(),
+            age: 50,
+            nickname: Some("bobby".to_owned()),
+            manager: None,
+            labels: HashMap::new(),
+            scores: vec![],
+            password: "hunter2".to_owned(),
+        })),
+        labels: HashMap::new(),
+        scores: vec![1.5],
+        password: "hunter2".to_owned(),
+    });
+}
+
+#[derive(Serialize, JddfSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum Status {
+    Active,
+    #[serde(rename = "gone")]
+    Deleted,
+    OnHold,
+}
+
+#[test]
+fn derive_unit_enum() {
+    assert_eq!(
+        to_json(schema_for::<Status>()),
+        json!({
+            "definitions": {},
+            "enum": ["ACTIVE", "gone", "ON_HOLD"],
+        })
+    );
+
+    assert_valid(&Status::Active);
+    assert_valid(&Status::Deleted);
+    assert_valid(&Status::OnHold);
+}
+
+#[derive(Serialize, JddfSchema)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Serialize, JddfSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Shape {
+    Circle { center: Point, radius: f32 },
+    Polygon(Polygon),
+    Empty,
+}
+
+#[derive(Serialize, JddfSchema)]
+struct Polygon {
+    points: Vec<Point>,
+}
+
+#[test]
+fn derive_internally_tagged_enum() {
+    assert_eq!(
+        to_json(schema_for::<Shape>()),
+        json!({
+            "definitions": {
+                "Point": {
+                    "properties": {
+                        "x": { "type": "int32" },
+                        "y": { "type": "int32" },
+                    },
+                    "additionalProperties": true,
+                },
+            },
+            "discriminator": {
+                "tag": "type",
+                "mapping": {
+                    "circle": {
+                        "properties": {
+                            "center": { "ref": "Point" },
+                            "radius": { "type": "float32" },
+                        },
+                        "additionalProperties": true,
+                    },
+                    "polygon": {
+                        "properties": {
+                            "points": { "elements": { "ref": "Point" } },
+                        },
+                        "additionalProperties": true,
+                    },
+                    "empty": {
+                        "properties": {},
+                        "additionalProperties": true,
+                    },
+                },
+            },
+        })
+    );
+
+    assert_valid(&Shape::Circle {
+        center: Point { x: 1, y: 2 },
+        radius: 3.0,
+    });
+    assert_valid(&Shape::Polygon(Polygon {
+        points: vec![Point { x: 0, y: 0 }],
+    }));
+    assert_valid(&Shape::Empty);
+}
+
+#[derive(Serialize, JddfSchema)]
+#[serde(tag = "kind", content = "data", deny_unknown_fields)]
+enum Event {
+    Login(String),
+    #[serde(rename_all = "camelCase")]
+    Purchase {
+        item_id: String,
+    },
+    Logout,
+}
+
+#[test]
+fn derive_adjacently_tagged_enum() {
+    assert_eq!(
+        to_json(schema_for::<Event>()),
+        json!({
+            "definitions": {},
+            "discriminator": {
+                "tag": "kind",
+                "mapping": {
+                    "Login": {
+                        "properties": {
+                            "data": { "type": "string" },
+                        },
+                    },
+                    "Purchase": {
+                        "properties": {
+                            "data": {
+                                "properties": {
+                                    "itemId": { "type": "string" },
+                                },
+                            },
+                        },
+                    },
+                    "Logout": {
+                        "properties": {},
+                    },
+                },
+            },
+        })
+    );
+
+    assert_valid(&Event::Login("alice".to_owned()));
+    assert_valid(&Event::Purchase {
+        item_id: "abc".to_owned(),
+    });
+    assert_valid(&Event::Logout);
+}
+
+#[derive(Serialize, JddfSchema)]
+#[serde(deny_unknown_fields)]
+struct Audit {
+    at: String,
+}
+
+#[derive(Serialize, JddfSchema)]
+struct Record {
+    id: u32,
+    #[serde(flatten)]
+    audit: Audit,
+    #[serde(serialize_with = "serialize_secret")]
+    secret: String,
+}
+
+fn serialize_secret<S: serde::Serializer>(_: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bool(true)
+}
+
+#[test]
+fn derive_flatten() {
+    assert_eq!(
+        to_json(schema_for::<Record>()),
+        json!({
+            "definitions": {},
+            "properties": {
+                "id": { "type": "uint32" },
+                "at": { "type": "string" },
+                "secret": {},
+            },
+            "additionalProperties": true,
+        })
+    );
+
+    assert_valid(&Record {
+        id: 1,
+        audit: Audit {
+            at: "now".to_owned(),
+        },
+        secret: "shh".to_owned(),
+    });
+}
@@ -0,0 +1,697 @@
+//! Convert schemas and instances to [Apache Arrow][arrow].
+//!
+//! This module is only available with the `arrow` feature enabled.
+//!
+//! A schema of the `properties` form describes a table: each property is a
+//! column, and each instance is a row. [`schema`](fn.schema.html) converts
+//! such a schema into an Arrow schema, and
+//! [`record_batch`](fn.record_batch.html) converts a batch of its instances
+//! into an Arrow `RecordBatch`:
+//!
+//! ```
+//! use jddf::{Schema, SerdeSchema};
+//! use serde_json::json;
+//!
+//! let serde_schema: SerdeSchema = serde_json::from_value(json!({
+//!     "properties": {
+//!         "name": { "type": "string" },
+//!         "tags": { "elements": { "type": "string" } },
+//!     },
+//! }))
+//! .unwrap();
+//! let schema = Schema::from_serde(serde_schema).unwrap();
+//!
+//! let batch = jddf::arrow::record_batch(
+//!     &schema,
+//!     &[
+//!         json!({ "name": "a", "tags": [] }),
+//!         json!({ "name": "b", "tags": ["x", "y"] }),
+//!     ],
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(2, batch.num_rows());
+//! assert_eq!(2, batch.num_columns());
+//! ```
+//!
+//! Schemas are converted as follows:
+//!
+//! * The empty form becomes a `Utf8`, holding the value as JSON.
+//! * Each `type` becomes the Arrow type of the same name, except for `string`,
+//!   which becomes `Utf8`, and `timestamp`, which becomes a `Timestamp` in
+//!   microseconds, in UTC.
+//! * `enum` becomes a `Dictionary` of `Utf8`, whose keys are the index of the
+//!   value in the `enum`.
+//! * `elements` becomes a `List`.
+//! * `values` becomes a `Map` from `Utf8`.
+//! * `properties` becomes a `Struct`. Optional properties are nullable, and
+//!   additional properties are left out.
+//! * `discriminator` becomes a dense `Union`, with a `Struct` for each mapping
+//!   value, named after the value of the tag.
+//! * `ref` becomes whatever its definition does. Arrow types can't refer to
+//!   themselves, so recursive definitions are not supported.
+//!
+//! [arrow]: https://arrow.apache.org
+
+use crate::errors::JddfError;
+use crate::schema::{Form, Schema, Type};
+use crate::validator::Validator;
+use arrow_array::builder::{
+    BooleanBuilder, Float32Builder, Float64Builder, Int16Builder, Int32Builder, Int8Builder,
+    StringBuilder, TimestampMicrosecondBuilder, UInt16Builder, UInt32Builder, UInt8Builder,
+};
+use arrow_array::types::Int32Type;
+use arrow_array::{
+    ArrayRef, DictionaryArray, ListArray, MapArray, RecordBatch, RecordBatchOptions, StringArray,
+    StructArray, UnionArray,
+};
+use arrow_buffer::{NullBuffer, OffsetBuffer, ScalarBuffer};
+use arrow_schema::{DataType, Field, Fields, UnionFields};
+use chrono::DateTime;
+use failure::{bail, Error};
+use indexmap::IndexMap;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Convert a root schema into an Arrow schema.
+///
+/// The schema must be of the `properties` form, or refer to a definition
+/// which is, and neither it nor any definition it uses may be recursive.
+/// Each discriminator must have between 1 and 128 mapping values, as that's
+/// how many members an Arrow union can have. Otherwise, this returns
+/// [`JddfError::UnsupportedSchema`][unsupported].
+///
+/// [unsupported]: ../errors/enum.JddfError.html#variant.UnsupportedSchema
+pub fn schema(schema: &Schema) -> Result<arrow_schema::Schema, Error> {
+    let (fields, _) = Converter::new(schema).columns(schema, &[])?;
+    Ok(arrow_schema::Schema::new(fields))
+}
+
+/// Convert a batch of instances of a root schema into an Arrow record batch.
+///
+/// The record batch uses the schema returned by [`schema`](fn.schema.html),
+/// and has a row for each instance.
+///
+/// Instances are validated before they are converted. If any is invalid, this
+/// returns [`JddfError::InvalidInstance`][invalid], with a path to the first
+/// problem. The path treats `instances` as a JSON array, so it starts with the
+/// index of the invalid instance.
+///
+/// [invalid]: ../errors/enum.JddfError.html#variant.InvalidInstance
+pub fn record_batch(schema: &Schema, instances: &[Value]) -> Result<RecordBatch, Error> {
+    let validator = Validator::new();
+    for (index, instance) in instances.iter().enumerate() {
+        if let Some(error) = validator.validate(schema, instance)?.first() {
+            bail!(JddfError::InvalidInstance {
+                path: format!("/{}{}", index, error.instance_path()),
+            });
+        }
+    }
+
+    let values: Vec<_> = instances.iter().map(Some).collect();
+    let (fields, columns) = Converter::new(schema).columns(schema, &values)?;
+
+    let options = RecordBatchOptions::new().with_row_count(Some(instances.len()));
+    Ok(RecordBatch::try_new_with_options(
+        Arc::new(arrow_schema::Schema::new(fields)),
+        columns,
+        &options,
+    )?)
+}
+
+struct Converter<'a> {
+    defs: Option<&'a IndexMap<String, Schema>>,
+
+    /// The definitions being converted, to detect recursion.
+    stack: Vec<&'a str>,
+}
+
+impl<'a> Converter<'a> {
+    fn new(root: &'a Schema) -> Self {
+        Self {
+            defs: root.definitions().as_ref(),
+            stack: vec![],
+        }
+    }
+
+    /// Convert the properties of a schema into columns.
+    fn columns(
+        &mut self,
+        schema: &'a Schema,
+        values: &[Option<&Value>],
+    ) -> Result<(Fields, Vec<ArrayRef>), Error> {
+        match schema.form() {
+            Form::Ref(name) => self.in_definition(name, |c, def| c.columns(def, values)),
+            Form::Properties { .. } => {
+                let array = self.array(schema, values)?;
+                let array = array
+                    .as_any()
+                    .downcast_ref::<StructArray>()
+                    .expect("unreachable: properties become structs");
+
+                Ok((array.fields().clone(), array.columns().to_vec()))
+            }
+            _ => bail!(JddfError::UnsupportedSchema {
+                reason: "root schema must be of the properties form".to_owned(),
+            }),
+        }
+    }
+
+    /// Follow a reference, failing if it's recursive.
+    fn in_definition<T>(
+        &mut self,
+        name: &'a str,
+        f: impl FnOnce(&mut Self, &'a Schema) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        if self.stack.contains(&name) {
+            bail!(JddfError::UnsupportedSchema {
+                reason: format!("recursive definition: {}", name),
+            });
+        }
+
+        let def = &self.defs.expect("unreachable: schema has definitions")[name];
+        self.stack.push(name);
+        let out = f(self, def);
+        self.stack.pop();
+        out
+    }
+
+    /// Convert the values at some position in a batch of instances into an
+    /// array. `None` stands for a value which is absent, either because it's
+    /// an optional property or because its parent is absent.
+    fn array(&mut self, schema: &'a Schema, values: &[Option<&Value>]) -> Result<ArrayRef, Error> {
+        Ok(match schema.form() {
+            Form::Empty => Arc::new(
+                values
+                    .iter()
+                    .map(|value| value.map(Value::to_string))
+                    .collect::<StringArray>(),
+            ),
+            Form::Ref(name) => self.in_definition(name, |c, def| c.array(def, values))?,
+            Form::Type(type_) => primitive(type_, values),
+            Form::Enum(enum_values) => {
+                let keys = values
+                    .iter()
+                    .map(|value| {
+                        value.map(|value| {
+                            enum_values
+                                .get_index_of(value.as_str().unwrap())
+                                .expect("unreachable: instance is valid")
+                                as i32
+                        })
+                    })
+                    .collect();
+
+                let dictionary: StringArray = enum_values.iter().map(Some).collect();
+                Arc::new(DictionaryArray::<Int32Type>::try_new(
+                    keys,
+                    Arc::new(dictionary),
+                )?)
+            }
+            Form::Elements(sub_schema) => {
+                let mut lengths = vec![];
+                let mut elements = vec![];
+                for value in values {
+                    let arr = value.map_or(&[][..], |value| value.as_array().unwrap());
+                    lengths.push(arr.len());
+                    elements.extend(arr.iter().map(Some));
+                }
+
+                let elements = self.array(sub_schema, &elements)?;
+                let field = Field::new("item", elements.data_type().clone(), false);
+                Arc::new(ListArray::try_new(
+                    Arc::new(field),
+                    OffsetBuffer::from_lengths(lengths),
+                    elements,
+                    nulls(values),
+                )?)
+            }
+            Form::Properties {
+                required, optional, ..
+            } => {
+                let mut fields = vec![];
+                let mut arrays = vec![];
+                for (nullable, properties) in &[(false, required), (true, optional)] {
+                    for (name, sub_schema) in properties.iter() {
+                        let sub_values: Vec<_> = values
+                            .iter()
+                            .map(|value| value.and_then(|value| value.get(name)))
+                            .collect();
+
+                        let array = self.array(sub_schema, &sub_values)?;
+                        fields.push(Field::new(name, array.data_type().clone(), *nullable));
+                        arrays.push(array);
+                    }
+                }
+
+                Arc::new(StructArray::try_new_with_length(
+                    fields.into(),
+                    arrays,
+                    nulls(values),
+                    values.len(),
+                )?)
+            }
+            Form::Values(sub_schema) => {
+                let mut lengths = vec![];
+                let mut keys = vec![];
+                let mut members = vec![];
+                for value in values {
+                    match value {
+                        Some(value) => {
+                            let obj = value.as_object().unwrap();
+                            lengths.push(obj.len());
+                            keys.extend(obj.keys().map(String::as_str));
+                            members.extend(obj.values().map(Some));
+                        }
+                        None => lengths.push(0),
+                    }
+                }
+
+                let keys: ArrayRef = Arc::new(keys.into_iter().map(Some).collect::<StringArray>());
+                let members = self.array(sub_schema, &members)?;
+                let entries = StructArray::try_new_with_length(
+                    Fields::from(vec![
+                        Field::new("key", DataType::Utf8, false),
+                        Field::new("value", members.data_type().clone(), false),
+                    ]),
+                    vec![keys, members],
+                    None,
+                    lengths.iter().sum(),
+                )?;
+
+                let field =
+                    Field::new("entries", DataType::Struct(entries.fields().clone()), false);
+                Arc::new(MapArray::try_new(
+                    Arc::new(field),
+                    OffsetBuffer::from_lengths(lengths),
+                    entries,
+                    nulls(values),
+                    false,
+                )?)
+            }
+            Form::Discriminator(tag, mapping) => {
+                if mapping.is_empty() {
+                    bail!(JddfError::UnsupportedSchema {
+                        reason: format!("discriminator {} has no mapping values", tag),
+                    });
+                }
+
+                if mapping.len() > i8::MAX as usize + 1 {
+                    bail!(JddfError::UnsupportedSchema {
+                        reason: format!("discriminator {} has too many mapping values", tag),
+                    });
+                }
+
+                // Arrow unions have no nulls of their own. Absent values are
+                // instead null members of the first mapping value.
+                let mut type_ids = vec![];
+                let mut offsets = vec![];
+                let mut members = vec![vec![]; mapping.len()];
+                for value in values {
+                    let type_id = match value {
+                        Some(value) => {
+                            let tag = value[tag].as_str().unwrap();
+                            mapping
+                                .get_index_of(tag)
+                                .expect("unreachable: instance is valid")
+                        }
+                        None => 0,
+                    };
+
+                    type_ids.push(type_id as i8);
+                    offsets.push(members[type_id].len() as i32);
+                    members[type_id].push(*value);
+                }
+
+                let mut fields = vec![];
+                let mut children = vec![];
+                for ((name, sub_schema), members) in mapping.iter().zip(&members) {
+                    let array = self.array(sub_schema, members)?;
+                    fields.push(Field::new(name, array.data_type().clone(), true));
+                    children.push(array);
+                }
+
+                Arc::new(UnionArray::try_new(
+                    UnionFields::try_new(0..mapping.len() as i8, fields)?,
+                    ScalarBuffer::from(type_ids),
+                    Some(ScalarBuffer::from(offsets)),
+                    children,
+                )?)
+            }
+        })
+    }
+}
+
+fn primitive(type_: &Type, values: &[Option<&Value>]) -> ArrayRef {
+    macro_rules! build {
+        ($builder:ty, $convert:expr) => {{
+            let mut builder = <$builder>::with_capacity(values.len());
+            for value in values {
+                builder.append_option(value.map($convert));
+            }
+
+            Arc::new(builder.finish())
+        }};
+    }
+
+    let int = |value: &Value| value.as_f64().unwrap();
+
+    match type_ {
+        Type::Boolean => build!(BooleanBuilder, |v: &Value| v.as_bool().unwrap()),
+        Type::Float32 => build!(Float32Builder, |v: &Value| v.as_f64().unwrap() as f32),
+        Type::Float64 => build!(Float64Builder, |v: &Value| v.as_f64().unwrap()),
+        Type::Int8 => build!(Int8Builder, |v: &Value| int(v) as i8),
+        Type::Uint8 => build!(UInt8Builder, |v: &Value| int(v) as u8),
+        Type::Int16 => build!(Int16Builder, |v: &Value| int(v) as i16),
+        Type::Uint16 => build!(UInt16Builder, |v: &Value| int(v) as u16),
+        Type::Int32 => build!(Int32Builder, |v: &Value| int(v) as i32),
+        Type::Uint32 => build!(UInt32Builder, |v: &Value| int(v) as u32),
+        Type::String => {
+            let mut builder = StringBuilder::new();
+            for value in values {
+                builder.append_option(value.map(|v| v.as_str().unwrap()));
+            }
+
+            Arc::new(builder.finish())
+        }
+        Type::Timestamp => {
+            let mut builder = TimestampMicrosecondBuilder::with_capacity(values.len());
+            for value in values {
+                builder.append_option(value.map(|v| {
+                    DateTime::parse_from_rfc3339(v.as_str().unwrap())
+                        .expect("unreachable: instance is valid")
+                        .timestamp_micros()
+                }));
+            }
+
+            Arc::new(builder.finish().with_timezone("UTC"))
+        }
+    }
+}
+
+/// The nulls of an array of `values`, or `None` if there are none.
+fn nulls(values: &[Option<&Value>]) -> Option<NullBuffer> {
+    if values.iter().all(Option::is_some) {
+        None
+    } else {
+        Some(values.iter().map(Option::is_some).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::Generator;
+    use crate::test_util::schema;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{TimestampMicrosecondType, UInt8Type};
+    use arrow_array::Array;
+    use arrow_schema::{TimeUnit, UnionMode};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use serde_json::json;
+
+    fn event_schema() -> Schema {
+        schema(json!({
+            "definitions": {
+                "point": {
+                    "properties": {
+                        "x": { "type": "float64" },
+                        "y": { "type": "float64" },
+                    },
+                },
+            },
+            "properties": {
+                "at": { "type": "timestamp" },
+                "level": { "enum": ["info", "error"] },
+                "tags": { "elements": { "type": "string" } },
+                "counts": { "values": { "type": "uint8" } },
+                "payload": {
+                    "discriminator": {
+                        "tag": "type",
+                        "mapping": {
+                            "click": {
+                                "properties": {
+                                    "point": { "ref": "point" },
+                                },
+                            },
+                            "key": {
+                                "properties": {
+                                    "code": { "type": "uint16" },
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+            "optionalProperties": {
+                "origin": { "ref": "point" },
+                "extra": {},
+            },
+        }))
+    }
+
+    #[test]
+    fn convert_schema() {
+        let point = DataType::Struct(Fields::from(vec![
+            Field::new("x", DataType::Float64, false),
+            Field::new("y", DataType::Float64, false),
+        ]));
+
+        let expected = arrow_schema::Schema::new(vec![
+            Field::new(
+                "at",
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+                false,
+            ),
+            Field::new(
+                "counts",
+                DataType::Map(
+                    Arc::new(Field::new(
+                        "entries",
+                        DataType::Struct(Fields::from(vec![
+                            Field::new("key", DataType::Utf8, false),
+                            Field::new("value", DataType::UInt8, false),
+                        ])),
+                        false,
+                    )),
+                    false,
+                ),
+                false,
+            ),
+            Field::new(
+                "level",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                false,
+            ),
+            Field::new(
+                "payload",
+                DataType::Union(
+                    UnionFields::try_new(
+                        vec![0, 1],
+                        vec![
+                            Field::new(
+                                "click",
+                                DataType::Struct(Fields::from(vec![Field::new(
+                                    "point",
+                                    point.clone(),
+                                    false,
+                                )])),
+                                true,
+                            ),
+                            Field::new(
+                                "key",
+                                DataType::Struct(Fields::from(vec![Field::new(
+                                    "code",
+                                    DataType::UInt16,
+                                    false,
+                                )])),
+                                true,
+                            ),
+                        ],
+                    )
+                    .unwrap(),
+                    UnionMode::Dense,
+                ),
+                false,
+            ),
+            Field::new(
+                "tags",
+                DataType::List(Arc::new(Field::new("item", DataType::Utf8, false))),
+                false,
+            ),
+            Field::new("extra", DataType::Utf8, true),
+            Field::new("origin", point, true),
+        ]);
+
+        assert_eq!(expected, super::schema(&event_schema()).unwrap());
+
+        let unsupported = vec![
+            (
+                json!({ "elements": {} }),
+                "root schema must be of the properties form",
+            ),
+            (
+                json!({
+                    "definitions": {
+                        "node": {
+                            "properties": {
+                                "children": { "elements": { "ref": "node" } },
+                            },
+                        },
+                    },
+                    "ref": "node",
+                }),
+                "recursive definition: node",
+            ),
+            (
+                json!({
+                    "optionalProperties": {
+                        "shape": {
+                            "discriminator": { "tag": "kind", "mapping": {} },
+                        },
+                    },
+                }),
+                "discriminator kind has no mapping values",
+            ),
+        ];
+
+        for (schema, reason) in unsupported {
+            assert_eq!(
+                JddfError::UnsupportedSchema {
+                    reason: reason.to_owned()
+                }
+                .to_string(),
+                super::schema(&self::schema(schema))
+                    .unwrap_err()
+                    .to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn convert_record_batch() {
+        let schema = event_schema();
+        let instances = vec![
+            json!({
+                "at": "2020-01-02T03:04:05.5+01:00",
+                "level": "error",
+                "tags": ["a", "b"],
+                "counts": { "x": 1, "y": 2 },
+                "payload": { "type": "key", "code": 13 },
+                "origin": { "x": 1.5, "y": 2.5 },
+            }),
+            json!({
+                "at": "1970-01-01T00:00:00Z",
+                "level": "info",
+                "tags": [],
+                "counts": {},
+                "payload": { "type": "click", "point": { "x": 0, "y": 1 } },
+                "extra": { "anything": [null] },
+            }),
+        ];
+
+        let batch = record_batch(&schema, &instances).unwrap();
+        assert_eq!(super::schema(&schema).unwrap(), *batch.schema());
+        assert_eq!(2, batch.num_rows());
+
+        let at = batch.column(0).as_primitive::<TimestampMicrosecondType>();
+        assert_eq!(vec![1_577_930_645_500_000, 0], at.values().to_vec());
+
+        let level = batch.column(2).as_dictionary::<Int32Type>();
+        assert_eq!(vec![1, 0], level.keys().values().to_vec());
+
+        let tags = batch.column(4).as_list::<i32>();
+        assert_eq!(vec![0, 2, 2], tags.offsets().to_vec());
+        assert_eq!(2, tags.values().len());
+
+        let counts = batch.column(1).as_map();
+        assert_eq!(vec![0, 2, 2], counts.offsets().to_vec());
+        assert_eq!(
+            vec![1, 2],
+            counts
+                .values()
+                .as_primitive::<UInt8Type>()
+                .values()
+                .to_vec()
+        );
+
+        let payload = batch.column(3).as_union();
+        assert_eq!(vec![1, 0], payload.type_ids().to_vec());
+        assert_eq!(1, payload.child(0).len());
+        assert_eq!(1, payload.child(1).len());
+
+        let origin = batch.column(6).as_struct();
+        assert!(origin.is_valid(0));
+        assert!(origin.is_null(1));
+
+        let extra = batch.column(5).as_string::<i32>();
+        assert!(extra.is_null(0));
+        assert_eq!(r#"{"anything":[null]}"#, extra.value(1));
+
+        let mut invalid = instances.clone();
+        invalid[1]["tags"] = json!([1]);
+        assert_eq!(
+            JddfError::InvalidInstance {
+                path: "/1/tags/0".to_owned()
+            }
+            .to_string(),
+            record_batch(&schema, &invalid).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn convert_generated() {
+        // Optional properties which contain unions, and unions whose mapping
+        // values contain optional properties, are the trickiest to get right,
+        // since unions have no nulls of their own.
+        let schema = schema(json!({
+            "definitions": {
+                "shape": {
+                    "discriminator": {
+                        "tag": "type",
+                        "mapping": {
+                            "circle": {
+                                "properties": { "r": { "type": "float32" } },
+                                "optionalProperties": {
+                                    "label": { "type": "string" },
+                                },
+                            },
+                            "group": {
+                                "properties": {
+                                    "children": {
+                                        "elements": {
+                                            "properties": {
+                                                "x": { "type": "int32" },
+                                            },
+                                            "optionalProperties": {
+                                                "kind": { "enum": ["a", "b"] },
+                                            },
+                                        },
+                                    },
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+            "properties": {
+                "id": { "type": "uint32" },
+            },
+            "optionalProperties": {
+                "shape": { "ref": "shape" },
+                "shapes": { "values": { "ref": "shape" } },
+                "created": { "type": "timestamp" },
+            },
+        }));
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let instances: Vec<_> = (0..200)
+            .map(|_| Generator::new().generate(&schema, &mut rng))
+            .collect();
+
+        let batch = record_batch(&schema, &instances).unwrap();
+        assert_eq!(200, batch.num_rows());
+        assert!(batch.column(2).logical_null_count() > 0);
+    }
+}
@@ -0,0 +1,1001 @@
+//! A compact binary encoding of instances, driven by their schema.
+//!
+//! A schema describes the shape of its instances so fully that most of what
+//! JSON spends bytes on, such as property names, quotes, and punctuation, can
+//! be left out. This module encodes instances into a compact binary format,
+//! similar in spirit to Avro, and decodes them back again. The same schema
+//! must be used for both.
+//!
+//! ```
+//! use jddf::binary::Codec;
+//! use jddf::{Schema, SerdeSchema};
+//! use serde_json::json;
+//!
+//! let serde_schema: SerdeSchema = serde_json::from_value(json!({
+//!     "properties": {
+//!         "age": { "type": "uint8" },
+//!         "name": { "type": "string" },
+//!     },
+//! }))
+//! .unwrap();
+//! let schema = Schema::from_serde(serde_schema).unwrap();
+//!
+//! let instance = json!({ "age": 42, "name": "Alice" });
+//! let codec = Codec::new();
+//! let bytes = codec.encode(&schema, &instance).unwrap();
+//! assert_eq!(vec![42, 5, b'A', b'l', b'i', b'c', b'e'], bytes);
+//! assert_eq!(instance, codec.decode(&schema, &bytes).unwrap());
+//! ```
+//!
+//! See the docs for [`Codec`](struct.Codec.html) for the details of the
+//! format.
+
+use crate::errors::JddfError;
+use crate::schema::{Form, Schema, Type};
+use chrono::{DateTime, FixedOffset, TimeZone};
+use failure::{bail, Error};
+use indexmap::IndexMap;
+use json_pointer::JsonPointer;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Encodes and decodes instances of schemas.
+///
+/// Each form is encoded as follows, where a *varint* is an unsigned LEB128
+/// integer, and signed varints are zigzag-encoded first:
+///
+/// * The empty form is encoded as a varint length, followed by the instance
+///   as JSON.
+/// * `boolean`, `int8`, and `uint8` are encoded as a single byte.
+/// * `int16` and `int32` are encoded as signed varints, and `uint16` and
+///   `uint32` as varints.
+/// * `float32` and `float64` are encoded as little-endian IEEE 754 numbers,
+///   four and eight bytes long respectively. `float32` values are rounded to
+///   the nearest `f32`, so numbers beyond the range of an `f32`, about
+///   ±3.4e38, can't be encoded.
+/// * `string` is encoded as a varint length, followed by UTF-8.
+/// * `timestamp` is encoded as the seconds since the Unix epoch as a signed
+///   varint, then nanoseconds as a varint, then the UTC offset in seconds as
+///   a signed varint.
+/// * `enum` is encoded as a varint index into its values, in the order they
+///   were declared.
+/// * `elements` is encoded as a varint count, followed by each element.
+/// * `values` is encoded as a varint count, followed by each member's name
+///   as a string and value.
+/// * `properties` encodes each required property in the order declared,
+///   without their names. Then comes a bitmap of which optional properties
+///   are present, one bit per optional property, with the first property in
+///   the least significant bit of the first byte. Then each optional property
+///   present, in the order declared. If additional properties are allowed,
+///   they follow as a varint count, and each property's name as a string and
+///   value as with the empty form.
+/// * `discriminator` is encoded as a varint index into its mapping, in the
+///   order declared, followed by the properties of the mapping value.
+/// * `ref` is encoded as its definition.
+///
+/// Decoding does not reproduce instances byte-for-byte as JSON: numbers
+/// without a fractional part are decoded as integers, and timestamps are
+/// decoded in the form produced by `DateTime::to_rfc3339`. Properties are
+/// decoded in the order the schema declares them.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Codec {
+    config: Config,
+}
+
+impl Codec {
+    /// Constructs a new codec using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new codec using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Encode an instance of a root schema.
+    ///
+    /// Returns [`JddfError::InvalidInstance`][invalid] if the instance isn't
+    /// valid against the schema. Not every invalid instance is caught, though:
+    /// if in doubt, validate instances before encoding them.
+    ///
+    /// Returns [`JddfError::NumberOutOfRange`][out-of-range] if the instance
+    /// is valid, but has a `float32` value too large for an `f32`.
+    ///
+    /// [invalid]: ../errors/enum.JddfError.html#variant.InvalidInstance
+    /// [out-of-range]: ../errors/enum.JddfError.html#variant.NumberOutOfRange
+    pub fn encode(&self, schema: &Schema, instance: &Value) -> Result<Vec<u8>, Error> {
+        let empty = IndexMap::new();
+        let mut encoder = Encoder {
+            max_depth: self.config.max_depth,
+            defs: schema.definitions().as_ref().unwrap_or(&empty),
+            tokens: vec![],
+            out: vec![],
+        };
+
+        encoder.encode(schema, instance, None, 0)?;
+        Ok(encoder.out)
+    }
+
+    /// Decode an instance of a root schema.
+    ///
+    /// Returns [`JddfError::InvalidEncoding`][invalid] if the data isn't an
+    /// encoding of an instance of the schema, and
+    /// [`JddfError::MaxElementsExceeded`][max-elements] if it has more
+    /// elements than [`Config::max_elements`][config] allows.
+    ///
+    /// [invalid]: ../errors/enum.JddfError.html#variant.InvalidEncoding
+    /// [max-elements]: ../errors/enum.JddfError.html#variant.MaxElementsExceeded
+    /// [config]: struct.Config.html#method.max_elements
+    pub fn decode(&self, schema: &Schema, data: &[u8]) -> Result<Value, Error> {
+        let empty = IndexMap::new();
+        let mut decoder = Decoder {
+            max_depth: self.config.max_depth,
+            max_elements: self.config.max_elements,
+            defs: schema.definitions().as_ref().unwrap_or(&empty),
+            min_sizes: HashMap::new(),
+            data,
+        };
+
+        let out = decoder.decode(schema, None, 0)?;
+        if !decoder.data.is_empty() {
+            bail!(JddfError::InvalidEncoding);
+        }
+
+        Ok(out)
+    }
+}
+
+/// Configuration for how instances are encoded and decoded.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    max_depth: usize,
+    max_elements: usize,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of references to follow before aborting. The
+    /// default value is 32, the same as for validation.
+    ///
+    /// When this depth is exceeded, encoding or decoding fails with
+    /// [`JddfError::MaxDepthExceeded`][max-depth]. This guards against
+    /// circularly-defined schemas, and against data crafted to nest too
+    /// deeply.
+    ///
+    /// [max-depth]: ../errors/enum.JddfError.html#variant.MaxDepthExceeded
+    pub fn max_depth(&mut self, max_depth: usize) -> &mut Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the maximum number of elements of an array, or members of an
+    /// object of the `values` form, to decode. The default value is 1,000,000.
+    ///
+    /// When this is exceeded, decoding fails with
+    /// [`JddfError::MaxElementsExceeded`][max-elements]. Data can't claim more
+    /// elements than it has bytes left for, but elements of some schemas, such
+    /// as `{ "properties": {} }`, take up no bytes at all. This guards against
+    /// data crafted to decode into enormous arrays of those.
+    ///
+    /// [max-elements]: ../errors/enum.JddfError.html#variant.MaxElementsExceeded
+    pub fn max_elements(&mut self, max_elements: usize) -> &mut Self {
+        self.max_elements = max_elements;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_depth: 32,
+            max_elements: 1_000_000,
+        }
+    }
+}
+
+struct Encoder<'a> {
+    max_depth: usize,
+    defs: &'a IndexMap<String, Schema>,
+    tokens: Vec<String>,
+    out: Vec<u8>,
+}
+
+impl<'a> Encoder<'a> {
+    fn encode(
+        &mut self,
+        schema: &Schema,
+        instance: &Value,
+        parent_tag: Option<&str>,
+        depth: usize,
+    ) -> Result<(), Error> {
+        match schema.form() {
+            Form::Empty => self.write_json(instance),
+            Form::Ref(name) => {
+                if depth == self.max_depth {
+                    bail!(JddfError::MaxDepthExceeded);
+                }
+
+                let defs = self.defs;
+                self.encode(&defs[name], instance, None, depth + 1)?;
+            }
+            Form::Type(Type::Boolean) => match instance {
+                Value::Bool(b) => self.out.push(*b as u8),
+                _ => return self.invalid(),
+            },
+            Form::Type(Type::Float32) => match instance.as_f64() {
+                Some(n) if (n as f32).is_finite() => {
+                    self.out.extend(&(n as f32).to_le_bytes());
+                }
+                Some(_) => bail!(JddfError::NumberOutOfRange {
+                    path: JsonPointer::new(self.tokens.clone()).to_string(),
+                }),
+                None => return self.invalid(),
+            },
+            Form::Type(Type::Float64) => match instance.as_f64() {
+                Some(n) => self.out.extend(&n.to_le_bytes()),
+                None => return self.invalid(),
+            },
+            Form::Type(Type::Int8) => {
+                let n = self.int(instance, i8::MIN.into(), i8::MAX.into())?;
+                self.out.push(n as i8 as u8);
+            }
+            Form::Type(Type::Uint8) => {
+                let n = self.int(instance, 0, u8::MAX.into())?;
+                self.out.push(n as u8);
+            }
+            Form::Type(Type::Int16) => {
+                let n = self.int(instance, i16::MIN.into(), i16::MAX.into())?;
+                self.write_signed(n);
+            }
+            Form::Type(Type::Uint16) => {
+                let n = self.int(instance, 0, u16::MAX.into())?;
+                self.write_varint(n as u64);
+            }
+            Form::Type(Type::Int32) => {
+                let n = self.int(instance, i32::MIN.into(), i32::MAX.into())?;
+                self.write_signed(n);
+            }
+            Form::Type(Type::Uint32) => {
+                let n = self.int(instance, 0, u32::MAX.into())?;
+                self.write_varint(n as u64);
+            }
+            Form::Type(Type::String) => match instance {
+                Value::String(s) => self.write_str(s),
+                _ => return self.invalid(),
+            },
+            Form::Type(Type::Timestamp) => {
+                let timestamp = match instance.as_str().map(DateTime::parse_from_rfc3339) {
+                    Some(Ok(timestamp)) => timestamp,
+                    _ => return self.invalid(),
+                };
+
+                self.write_signed(timestamp.timestamp());
+                self.write_varint(timestamp.timestamp_subsec_nanos().into());
+                self.write_signed(timestamp.offset().local_minus_utc().into());
+            }
+            Form::Enum(values) => match instance.as_str().and_then(|s| values.get_full(s)) {
+                Some((index, _)) => self.write_varint(index as u64),
+                None => return self.invalid(),
+            },
+            Form::Elements(sub_schema) => {
+                let arr = match instance {
+                    Value::Array(arr) => arr,
+                    _ => return self.invalid(),
+                };
+
+                self.write_varint(arr.len() as u64);
+                for (index, elem) in arr.iter().enumerate() {
+                    self.tokens.push(index.to_string());
+                    self.encode(sub_schema, elem, None, depth)?;
+                    self.tokens.pop();
+                }
+            }
+            Form::Properties {
+                required,
+                optional,
+                allow_additional,
+                ..
+            } => {
+                let obj = match instance {
+                    Value::Object(obj) => obj,
+                    _ => return self.invalid(),
+                };
+
+                for (name, sub_schema) in required {
+                    self.tokens.push(name.clone());
+                    match obj.get(name) {
+                        Some(sub_instance) => self.encode(sub_schema, sub_instance, None, depth)?,
+                        None => return self.invalid(),
+                    }
+                    self.tokens.pop();
+                }
+
+                let mut bitmap = vec![0; optional.len().div_ceil(8)];
+                for (index, name) in optional.keys().enumerate() {
+                    if obj.contains_key(name) {
+                        bitmap[index / 8] |= 1 << (index % 8);
+                    }
+                }
+                self.out.extend(bitmap);
+
+                for (name, sub_schema) in optional {
+                    if let Some(sub_instance) = obj.get(name) {
+                        self.tokens.push(name.clone());
+                        self.encode(sub_schema, sub_instance, None, depth)?;
+                        self.tokens.pop();
+                    }
+                }
+
+                let additional: Vec<_> = obj
+                    .iter()
+                    .filter(|(name, _)| {
+                        !required.contains_key(*name)
+                            && !optional.contains_key(*name)
+                            && parent_tag != Some(name.as_str())
+                    })
+                    .collect();
+
+                if *allow_additional {
+                    self.write_varint(additional.len() as u64);
+                    for (name, value) in additional {
+                        self.write_str(name);
+                        self.write_json(value);
+                    }
+                } else if let Some((name, _)) = additional.first() {
+                    self.tokens.push((*name).clone());
+                    return self.invalid();
+                }
+            }
+            Form::Values(sub_schema) => {
+                let obj = match instance {
+                    Value::Object(obj) => obj,
+                    _ => return self.invalid(),
+                };
+
+                self.write_varint(obj.len() as u64);
+                for (name, sub_instance) in obj {
+                    self.write_str(name);
+                    self.tokens.push(name.clone());
+                    self.encode(sub_schema, sub_instance, None, depth)?;
+                    self.tokens.pop();
+                }
+            }
+            Form::Discriminator(tag, mapping) => {
+                let value = match instance {
+                    Value::Object(obj) => obj.get(tag),
+                    _ => return self.invalid(),
+                };
+
+                self.tokens.push(tag.clone());
+                let (index, _, sub_schema) = match value
+                    .and_then(Value::as_str)
+                    .and_then(|v| mapping.get_full(v))
+                {
+                    Some(entry) => entry,
+                    None => return self.invalid(),
+                };
+                self.tokens.pop();
+
+                self.write_varint(index as u64);
+                self.encode(sub_schema, instance, Some(tag), depth)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The integer value of `instance`, if it's an integer between `min` and
+    /// `max` inclusive.
+    fn int(&self, instance: &Value, min: i64, max: i64) -> Result<i64, Error> {
+        match instance.as_f64() {
+            Some(n) if n.fract() == 0.0 && n >= min as f64 && n <= max as f64 => Ok(n as i64),
+            _ => self.invalid(),
+        }
+    }
+
+    fn invalid<T>(&self) -> Result<T, Error> {
+        bail!(JddfError::InvalidInstance {
+            path: JsonPointer::new(self.tokens.clone()).to_string(),
+        })
+    }
+
+    fn write_varint(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.out.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+
+        self.out.push(n as u8);
+    }
+
+    fn write_signed(&mut self, n: i64) {
+        self.write_varint(((n << 1) ^ (n >> 63)) as u64);
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write_varint(s.len() as u64);
+        self.out.extend(s.as_bytes());
+    }
+
+    fn write_json(&mut self, value: &Value) {
+        let json = serde_json::to_vec(value).expect("unreachable: values always serialize");
+        self.write_varint(json.len() as u64);
+        self.out.extend(json);
+    }
+}
+
+struct Decoder<'a, 'b> {
+    max_depth: usize,
+    max_elements: usize,
+    defs: &'a IndexMap<String, Schema>,
+    min_sizes: HashMap<&'a str, usize>,
+    data: &'b [u8],
+}
+
+impl<'a, 'b> Decoder<'a, 'b> {
+    fn decode(
+        &mut self,
+        schema: &'a Schema,
+        parent_tag: Option<&str>,
+        depth: usize,
+    ) -> Result<Value, Error> {
+        Ok(match schema.form() {
+            Form::Empty => self.read_json()?,
+            Form::Ref(name) => {
+                if depth == self.max_depth {
+                    bail!(JddfError::MaxDepthExceeded);
+                }
+
+                let defs = self.defs;
+                self.decode(&defs[name], None, depth + 1)?
+            }
+            Form::Type(Type::Boolean) => match self.read_byte()? {
+                0 => Value::Bool(false),
+                1 => Value::Bool(true),
+                _ => bail!(JddfError::InvalidEncoding),
+            },
+            Form::Type(Type::Float32) => {
+                let mut bytes = [0; 4];
+                bytes.copy_from_slice(self.read_bytes(4)?);
+                float(f32::from_le_bytes(bytes).into())?
+            }
+            Form::Type(Type::Float64) => {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(self.read_bytes(8)?);
+                float(f64::from_le_bytes(bytes))?
+            }
+            Form::Type(Type::Int8) => Value::from(self.read_byte()? as i8),
+            Form::Type(Type::Uint8) => Value::from(self.read_byte()?),
+            Form::Type(Type::Int16) => Value::from(self.read_int::<i16>()?),
+            Form::Type(Type::Uint16) => Value::from(self.read_int::<u16>()?),
+            Form::Type(Type::Int32) => Value::from(self.read_int::<i32>()?),
+            Form::Type(Type::Uint32) => Value::from(self.read_int::<u32>()?),
+            Form::Type(Type::String) => Value::String(self.read_str()?),
+            Form::Type(Type::Timestamp) => {
+                let secs = self.read_signed()?;
+                let nanos = self.read_int::<u32>()?;
+                let offset = self.read_int::<i32>()?;
+
+                let timestamp = FixedOffset::east_opt(offset)
+                    .and_then(|offset| offset.timestamp_opt(secs, nanos).single());
+                match timestamp {
+                    Some(timestamp) => Value::String(timestamp.to_rfc3339()),
+                    None => bail!(JddfError::InvalidEncoding),
+                }
+            }
+            Form::Enum(values) => match values.get_index(self.read_len()?) {
+                Some(value) => Value::String(value.clone()),
+                None => bail!(JddfError::InvalidEncoding),
+            },
+            Form::Elements(sub_schema) => {
+                let min_size = self.min_size(sub_schema, &mut vec![]);
+                let len = self.read_count(min_size)?;
+                let mut out = vec![];
+                for _ in 0..len {
+                    out.push(self.decode(sub_schema, None, depth)?);
+                }
+
+                Value::Array(out)
+            }
+            Form::Properties {
+                required,
+                optional,
+                allow_additional,
+                ..
+            } => {
+                let mut out = Map::new();
+                for (name, sub_schema) in required {
+                    out.insert(name.clone(), self.decode(sub_schema, None, depth)?);
+                }
+
+                let bitmap = self.read_bytes(optional.len().div_ceil(8))?.to_vec();
+                if optional.len() % 8 != 0 && bitmap[bitmap.len() - 1] >> (optional.len() % 8) != 0
+                {
+                    bail!(JddfError::InvalidEncoding);
+                }
+
+                for (index, (name, sub_schema)) in optional.iter().enumerate() {
+                    if bitmap[index / 8] & (1 << (index % 8)) != 0 {
+                        out.insert(name.clone(), self.decode(sub_schema, None, depth)?);
+                    }
+                }
+
+                if *allow_additional {
+                    for _ in 0..self.read_len()? {
+                        let name = self.read_str()?;
+                        if required.contains_key(&name)
+                            || optional.contains_key(&name)
+                            || parent_tag == Some(name.as_str())
+                            || out.contains_key(&name)
+                        {
+                            bail!(JddfError::InvalidEncoding);
+                        }
+
+                        let value = self.read_json()?;
+                        out.insert(name, value);
+                    }
+                }
+
+                Value::Object(out)
+            }
+            Form::Values(sub_schema) => {
+                // Each member's name takes at least a byte, for its length.
+                let min_size = self.min_size(sub_schema, &mut vec![]) + 1;
+                let len = self.read_count(min_size)?;
+                let mut out = Map::new();
+                for _ in 0..len {
+                    let name = self.read_str()?;
+                    let value = self.decode(sub_schema, None, depth)?;
+                    if out.insert(name, value).is_some() {
+                        bail!(JddfError::InvalidEncoding);
+                    }
+                }
+
+                Value::Object(out)
+            }
+            Form::Discriminator(tag, mapping) => {
+                let (value, sub_schema) = match mapping.get_index(self.read_len()?) {
+                    Some(entry) => entry,
+                    None => bail!(JddfError::InvalidEncoding),
+                };
+
+                let mut out = self.decode(sub_schema, Some(tag), depth)?;
+                out.as_object_mut()
+                    .expect("unreachable: mapping values are always properties")
+                    .insert(tag.clone(), Value::String(value.clone()));
+                out
+            }
+        })
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'b [u8], Error> {
+        if len > self.data.len() {
+            bail!(JddfError::InvalidEncoding);
+        }
+
+        let (out, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(out)
+    }
+
+    fn read_varint(&mut self) -> Result<u64, Error> {
+        let mut out = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_byte()?;
+            out |= u64::from(byte & 0x7f) << shift;
+
+            if byte & 0x80 == 0 {
+                // Reject encodings which overflow, or which are padded with
+                // needless zero bytes.
+                if (shift == 63 && byte > 1) || (shift != 0 && byte == 0) {
+                    bail!(JddfError::InvalidEncoding);
+                }
+
+                return Ok(out);
+            }
+        }
+
+        bail!(JddfError::InvalidEncoding)
+    }
+
+    fn read_signed(&mut self) -> Result<i64, Error> {
+        let n = self.read_varint()?;
+        Ok((n >> 1) as i64 ^ -((n & 1) as i64))
+    }
+
+    /// Read a varint or signed varint, depending on `T`, and check it fits in
+    /// a `T`.
+    fn read_int<T: Int>(&mut self) -> Result<T, Error> {
+        let n = if T::SIGNED {
+            self.read_signed()?
+        } else {
+            match self.read_varint()? {
+                n if n <= i64::MAX as u64 => n as i64,
+                _ => bail!(JddfError::InvalidEncoding),
+            }
+        };
+
+        match T::from_i64(n) {
+            Some(n) => Ok(n),
+            None => bail!(JddfError::InvalidEncoding),
+        }
+    }
+
+    /// Read a varint length or index.
+    fn read_len(&mut self) -> Result<usize, Error> {
+        match self.read_varint()? {
+            n if n <= usize::MAX as u64 => Ok(n as usize),
+            _ => bail!(JddfError::InvalidEncoding),
+        }
+    }
+
+    /// Read the count of elements of an array or object, each of which takes
+    /// at least `min_size` bytes.
+    fn read_count(&mut self, min_size: usize) -> Result<usize, Error> {
+        let len = self.read_len()?;
+        if min_size != 0 && len > self.data.len() / min_size {
+            bail!(JddfError::InvalidEncoding);
+        }
+
+        if len > self.max_elements {
+            bail!(JddfError::MaxElementsExceeded);
+        }
+
+        Ok(len)
+    }
+
+    /// The fewest bytes an instance of `schema` can be encoded in. `stack`
+    /// holds the definitions being followed, which count as taking no bytes
+    /// if they're reached again.
+    fn min_size(&mut self, schema: &'a Schema, stack: &mut Vec<&'a str>) -> usize {
+        match schema.form() {
+            Form::Empty => 2,
+            Form::Ref(name) => {
+                if let Some(size) = self.min_sizes.get(name.as_str()) {
+                    return *size;
+                }
+
+                if stack.contains(&name.as_str()) {
+                    return 0;
+                }
+
+                let defs = self.defs;
+                stack.push(name);
+                let size = self.min_size(&defs[name], stack);
+                stack.pop();
+
+                self.min_sizes.insert(name, size);
+                size
+            }
+            Form::Type(Type::Float32) => 4,
+            Form::Type(Type::Float64) => 8,
+            Form::Type(Type::Timestamp) => 3,
+            Form::Type(_) | Form::Enum(_) | Form::Elements(_) | Form::Values(_) => 1,
+            Form::Properties {
+                required,
+                optional,
+                allow_additional,
+                ..
+            } => {
+                let required: usize = required
+                    .values()
+                    .map(|sub_schema| self.min_size(sub_schema, stack))
+                    .sum();
+
+                required + optional.len().div_ceil(8) + *allow_additional as usize
+            }
+            Form::Discriminator(_, mapping) => {
+                let mapping = mapping
+                    .values()
+                    .map(|sub_schema| self.min_size(sub_schema, stack))
+                    .min();
+
+                1 + mapping.unwrap_or(0)
+            }
+        }
+    }
+
+    fn read_str(&mut self) -> Result<String, Error> {
+        let len = self.read_len()?;
+        match std::str::from_utf8(self.read_bytes(len)?) {
+            Ok(s) => Ok(s.to_owned()),
+            Err(_) => bail!(JddfError::InvalidEncoding),
+        }
+    }
+
+    fn read_json(&mut self) -> Result<Value, Error> {
+        let len = self.read_len()?;
+        match serde_json::from_slice(self.read_bytes(len)?) {
+            Ok(value) => Ok(value),
+            Err(_) => bail!(JddfError::InvalidEncoding),
+        }
+    }
+}
+
+/// A number read from a float type. Numbers with no fractional part are
+/// turned into integers, which is how they're most often written in JSON.
+fn float(n: f64) -> Result<Value, Error> {
+    const MAX_SAFE: f64 = 9_007_199_254_740_992.0;
+
+    if !n.is_finite() {
+        bail!(JddfError::InvalidEncoding);
+    }
+
+    if n.fract() == 0.0 && n.abs() <= MAX_SAFE {
+        Ok(Value::from(n as i64))
+    } else {
+        Ok(Value::from(n))
+    }
+}
+
+/// The integer types of JDDF.
+trait Int: Sized {
+    const SIGNED: bool;
+
+    fn from_i64(n: i64) -> Option<Self>;
+}
+
+macro_rules! int {
+    ($type:ty, $signed:expr) => {
+        impl Int for $type {
+            const SIGNED: bool = $signed;
+
+            fn from_i64(n: i64) -> Option<Self> {
+                use std::convert::TryFrom;
+                <$type>::try_from(n).ok()
+            }
+        }
+    };
+}
+
+int!(i16, true);
+int!(u16, false);
+int!(i32, true);
+int!(u32, false);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::Generator;
+    use crate::test_util::{self, schema};
+    use crate::Validator;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use serde_json::json;
+
+    /// Assert two instances are equal, up to the rounding of numbers which
+    /// aren't integers.
+    ///
+    /// `float32` values are rounded on purpose, and numbers within the JSON of
+    /// the empty form may be parsed back a bit off from the original.
+    fn assert_close(expected: &Value, actual: &Value) {
+        match (expected, actual) {
+            (Value::Number(a), Value::Number(b)) if a.is_f64() => {
+                let (a, b) = (a.as_f64().unwrap(), b.as_f64().unwrap());
+                let tolerance = a.abs().max(b.abs()) * f64::from(f32::EPSILON);
+                assert!((a - b).abs() <= tolerance, "{} != {}", a, b);
+            }
+            (Value::Array(a), Value::Array(b)) => {
+                assert_eq!(a.len(), b.len());
+                for (a, b) in a.iter().zip(b) {
+                    assert_close(a, b);
+                }
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                assert_eq!(a.keys().collect::<Vec<_>>(), b.keys().collect::<Vec<_>>());
+                for (a, b) in a.values().zip(b.values()) {
+                    assert_close(a, b);
+                }
+            }
+            _ => assert_eq!(expected, actual),
+        }
+    }
+
+    #[test]
+    fn encode_format() {
+        let schema = schema(json!({
+            "properties": {
+                "a": { "type": "int16" },
+                "b": { "type": "uint32" },
+                "c": { "enum": ["x", "y", "z"] },
+                "d": { "elements": { "type": "boolean" } },
+                "e": {
+                    "discriminator": {
+                        "tag": "t",
+                        "mapping": {
+                            "p": { "properties": {} },
+                            "q": {
+                                "optionalProperties": {
+                                    "f": { "type": "float32" },
+                                    "g": { "type": "timestamp" },
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+            "additionalProperties": true,
+        }));
+
+        let instance = json!({
+            "a": -2,
+            "b": 300,
+            "c": "z",
+            "d": [true, false],
+            "e": { "t": "q", "f": 1.5 },
+            "h": null,
+        });
+
+        let codec = Codec::new();
+        let bytes = codec.encode(&schema, &instance).unwrap();
+        assert_eq!(
+            vec![
+                3, // a
+                0xac, 0x02, // b
+                2,    // c
+                2, 1, 0, // d
+                1, 0b01, 0, 0, 0xc0, 0x3f, // e
+                1, 1, b'h', 4, b'n', b'u', b'l', b'l', // h
+            ],
+            bytes
+        );
+
+        assert_eq!(instance, codec.decode(&schema, &bytes).unwrap());
+
+        for len in 0..bytes.len() {
+            assert!(codec.decode(&schema, &bytes[..len]).is_err());
+        }
+
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert!(codec.decode(&schema, &extra).is_err());
+
+        let invalid = json!({ "a": 0, "b": 0, "c": "x", "d": [], "e": { "t": "q", "f": "x" } });
+        assert_eq!(
+            JddfError::InvalidInstance {
+                path: "/e/f".to_owned(),
+            }
+            .to_string(),
+            codec.encode(&schema, &invalid).unwrap_err().to_string()
+        );
+
+        let too_large = json!({ "a": 0, "b": 0, "c": "x", "d": [], "e": { "t": "q", "f": 1e300 } });
+        assert!(Validator::new()
+            .validate(&schema, &too_large)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            JddfError::NumberOutOfRange {
+                path: "/e/f".to_owned(),
+            }
+            .to_string(),
+            codec.encode(&schema, &too_large).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn decode_limits() {
+        // A count of 2^60, followed by a few bytes of data.
+        let mut data = vec![0x80; 8];
+        data.extend(&[0x10, 1, 2, 3]);
+
+        let cases = vec![
+            (
+                json!({ "elements": { "type": "uint8" } }),
+                JddfError::InvalidEncoding,
+            ),
+            (
+                json!({ "values": { "properties": {} } }),
+                JddfError::InvalidEncoding,
+            ),
+            (
+                json!({ "elements": { "properties": {} } }),
+                JddfError::MaxElementsExceeded,
+            ),
+            (
+                json!({
+                    "definitions": {
+                        "a": { "properties": { "b": { "ref": "b" } } },
+                        "b": { "optionalProperties": { "a": { "ref": "a" } } },
+                    },
+                    "elements": { "ref": "a" },
+                }),
+                JddfError::InvalidEncoding,
+            ),
+        ];
+
+        for (schema, err) in cases {
+            assert_eq!(
+                err.to_string(),
+                Codec::new()
+                    .decode(&self::schema(schema), &data)
+                    .unwrap_err()
+                    .to_string()
+            );
+        }
+
+        let schema = schema(json!({ "elements": { "properties": {} } }));
+        let mut config = Config::new();
+        config.max_elements(2);
+        let codec = Codec::new_with_config(config);
+
+        assert_eq!(json!([{}, {}]), codec.decode(&schema, &[2]).unwrap());
+        assert_eq!(
+            JddfError::MaxElementsExceeded.to_string(),
+            codec.decode(&schema, &[3]).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let schema = schema(json!({
+            "definitions": test_util::recursive_definitions(),
+            "properties": {
+                "node": { "ref": "node" },
+                "list": { "ref": "list" },
+                "role": { "enum": ["admin", "member"] },
+                "scores": { "values": { "type": "float64" } },
+                "ratio": { "type": "float32" },
+                "createdAt": { "type": "timestamp" },
+                "counts": {
+                    "optionalProperties": {
+                        "a": { "type": "uint8" },
+                        "b": { "type": "int16" },
+                        "c": { "type": "uint16" },
+                        "d": { "type": "int32" },
+                        "e": { "type": "uint32" },
+                        "f": { "type": "float64" },
+                        "g": { "type": "boolean" },
+                        "h": { "type": "string" },
+                        "i": { "type": "string" },
+                    },
+                },
+            },
+            "additionalProperties": true,
+        }));
+
+        let codec = Codec::new();
+        let validator = Validator::new();
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..500 {
+            let instance = Generator::new().generate(&schema, &mut rng);
+            let bytes = codec.encode(&schema, &instance).unwrap();
+            let decoded = codec.decode(&schema, &bytes).unwrap();
+
+            assert_close(&instance, &decoded);
+            assert!(validator.validate(&schema, &decoded).unwrap().is_empty());
+            assert!(bytes.len() < serde_json::to_vec(&instance).unwrap().len());
+        }
+    }
+
+    #[test]
+    fn round_trip_timestamps() {
+        let schema = schema(json!({ "elements": { "type": "timestamp" } }));
+        let codec = Codec::new();
+
+        let instance = json!([
+            "1985-04-12T23:20:50.52Z",
+            "1990-12-31T15:59:60-08:00",
+            "2100-01-01T00:00:00+14:00",
+        ]);
+
+        let bytes = codec.encode(&schema, &instance).unwrap();
+        assert_eq!(
+            json!([
+                "1985-04-12T23:20:50.520+00:00",
+                "1990-12-31T15:59:60-08:00",
+                "2100-01-01T00:00:00+14:00",
+            ]),
+            codec.decode(&schema, &bytes).unwrap()
+        );
+    }
+}
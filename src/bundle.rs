@@ -0,0 +1,534 @@
+//! Schemas split across several documents.
+//!
+//! On its own, a `ref` can only refer to a definition in the same root schema.
+//! This module lets a `ref` of the form `document#name` refer instead to the
+//! definition `name` in another root schema, called a *document*. Documents
+//! are loaded through a [`Resolver`](trait.Resolver.html), and collected into a
+//! [`Bundle`](struct.Bundle.html).
+//!
+//! A bundle can combine a document and everything it refers to into a single,
+//! self-contained schema, which can be used with a
+//! [`Validator`](../validator/struct.Validator.html) like any other.
+
+use crate::errors::JddfError;
+use crate::schema::{Form, Schema, Serde};
+use failure::{bail, Error};
+use indexmap::{IndexMap, IndexSet};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+
+/// Loads documents by name.
+pub trait Resolver {
+    /// Load the root schema for `document`.
+    fn resolve(&self, document: &str) -> Result<Serde, Error>;
+}
+
+/// Resolves documents from JSON files in a directory.
+///
+/// The document name is used as a path relative to the directory, exactly as
+/// written; a `ref` to `common.json#address` loads `common.json`. Names which
+/// would escape the directory, such as absolute paths or paths containing
+/// `..`, and names of files which don't exist, are rejected with
+/// [`JddfError::NoSuchDocument`](../errors/enum.JddfError.html#variant.NoSuchDocument).
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct FileResolver {
+    dir: PathBuf,
+}
+
+impl FileResolver {
+    /// Constructs a resolver which loads documents from `dir`.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl Resolver for FileResolver {
+    fn resolve(&self, document: &str) -> Result<Serde, Error> {
+        let path = Path::new(document);
+        if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+            bail!(JddfError::NoSuchDocument {
+                document: document.to_owned()
+            });
+        }
+
+        let file = match File::open(self.dir.join(path)) {
+            Ok(file) => file,
+            Err(ref err) if err.kind() == ErrorKind::NotFound => {
+                bail!(JddfError::NoSuchDocument {
+                    document: document.to_owned()
+                })
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+}
+
+/// Resolves documents from memory.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct MemoryResolver {
+    documents: HashMap<String, Serde>,
+}
+
+impl MemoryResolver {
+    /// Constructs a resolver with no documents.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a document, replacing any existing document with the same name.
+    pub fn insert(&mut self, document: &str, schema: Serde) -> &mut Self {
+        self.documents.insert(document.to_owned(), schema);
+        self
+    }
+}
+
+impl Resolver for MemoryResolver {
+    fn resolve(&self, document: &str) -> Result<Serde, Error> {
+        match self.documents.get(document) {
+            Some(schema) => Ok(schema.clone()),
+            None => bail!(JddfError::NoSuchDocument {
+                document: document.to_owned()
+            }),
+        }
+    }
+}
+
+/// A set of documents whose `ref`s have all been checked.
+///
+/// Within a document, a `ref` without a `#` refers to one of the document's own
+/// definitions, as usual. A `ref` of the form `document#name` refers to the
+/// definition `name` in `document`, which may be the same document. Because
+/// of this, definition names may not contain a `#`.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Bundle {
+    documents: IndexMap<String, Schema>,
+}
+
+impl Bundle {
+    /// Constructs a bundle with no documents.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load `document`, and every document it refers to, directly or
+    /// indirectly, which is not already in the bundle.
+    ///
+    /// Returns an error if a document can't be resolved or isn't a valid
+    /// schema, or if a `ref` refers to a definition which doesn't exist. If
+    /// an error is returned, the bundle is left unchanged.
+    pub fn load<R: Resolver + ?Sized>(
+        &mut self,
+        resolver: &R,
+        document: &str,
+    ) -> Result<(), Error> {
+        let mut loaded = IndexMap::new();
+        let mut pending = VecDeque::new();
+        pending.push_back(document.to_owned());
+
+        while let Some(name) = pending.pop_front() {
+            if self.documents.contains_key(&name) || loaded.contains_key(&name) {
+                continue;
+            }
+
+            let schema = Schema::from_serde_unchecked(resolver.resolve(&name)?)?;
+            for def in definitions(&schema).keys() {
+                if def.contains('#') {
+                    bail!(JddfError::AmbiguousDefinition {
+                        definition: def.clone()
+                    });
+                }
+            }
+
+            for rxf in refs(&schema) {
+                if let Some((target, _)) = split(rxf) {
+                    pending.push_back(target.to_owned());
+                }
+            }
+
+            loaded.insert(name, schema);
+        }
+
+        for (name, schema) in &loaded {
+            for rxf in refs(schema) {
+                let (target, def) = split(rxf).unwrap_or((name, rxf));
+                let target = loaded
+                    .get(target)
+                    .or_else(|| self.documents.get(target))
+                    .expect("unreachable: all referenced documents were loaded");
+
+                if !definitions(target).contains_key(def) {
+                    bail!(JddfError::NoSuchDefinition {
+                        definition: rxf.to_owned()
+                    });
+                }
+            }
+        }
+
+        self.documents.extend(loaded);
+        Ok(())
+    }
+
+    /// The documents in the bundle, in the order they were loaded.
+    pub fn documents(&self) -> &IndexMap<String, Schema> {
+        &self.documents
+    }
+
+    /// Combine `document`, and every document it refers to, into a single
+    /// self-contained schema.
+    ///
+    /// The root of the returned schema, and its definitions, are those of
+    /// `document`. The definitions of the other documents it refers to are
+    /// added under the name `other#name`, and `ref`s are rewritten to match.
+    /// Validation errors from those definitions therefore have schema paths
+    /// like `/definitions/other#name/type`.
+    ///
+    /// Returns an error if `document` is not in the bundle.
+    pub fn bundle(&self, document: &str) -> Result<Schema, Error> {
+        let entry = match self.documents.get(document) {
+            Some(entry) => entry,
+            None => bail!(JddfError::NoSuchDocument {
+                document: document.to_owned()
+            }),
+        };
+
+        let mut included = IndexSet::new();
+        included.insert(document);
+
+        let mut i = 0;
+        while let Some(name) = included.get_index(i).cloned() {
+            for rxf in refs(&self.documents[name]) {
+                if let Some((target, _)) = split(rxf) {
+                    included.insert(target);
+                }
+            }
+
+            i += 1;
+        }
+
+        let mut out = entry.clone().into_serde();
+        let mut defs = out.defs.take().unwrap_or_default();
+
+        rewrite(&mut out, document, document);
+        for sub_schema in defs.values_mut() {
+            rewrite(sub_schema, document, document);
+        }
+
+        for name in included.iter().skip(1) {
+            for (def, sub_schema) in definitions(&self.documents[*name]) {
+                let mut sub_schema = sub_schema.clone().into_serde();
+                rewrite(&mut sub_schema, name, document);
+                defs.insert(format!("{}#{}", name, def), sub_schema);
+            }
+        }
+
+        out.defs = Some(defs);
+        Schema::from_serde(out)
+    }
+}
+
+fn definitions(schema: &Schema) -> &IndexMap<String, Schema> {
+    schema
+        .definitions()
+        .as_ref()
+        .expect("unreachable: documents are root schemas")
+}
+
+/// Split a cross-document `ref` into its document and definition name.
+fn split(rxf: &str) -> Option<(&str, &str)> {
+    rxf.find('#').map(|i| (&rxf[..i], &rxf[i + 1..]))
+}
+
+/// All of the `ref`s in a root schema, including its definitions.
+fn refs(schema: &Schema) -> Vec<&str> {
+    fn collect<'a>(schema: &'a Schema, out: &mut Vec<&'a str>) {
+        match schema.form() {
+            Form::Ref(def) => out.push(def),
+            Form::Elements(sub_schema) | Form::Values(sub_schema) => collect(sub_schema, out),
+            Form::Properties {
+                required, optional, ..
+            } => {
+                for sub_schema in required.values().chain(optional.values()) {
+                    collect(sub_schema, out);
+                }
+            }
+            Form::Discriminator(_, mapping) => {
+                for sub_schema in mapping.values() {
+                    collect(sub_schema, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = vec![];
+    for sub_schema in definitions(schema).values() {
+        collect(sub_schema, &mut out);
+    }
+
+    collect(schema, &mut out);
+    out
+}
+
+/// Rewrite the `ref`s in `schema`, which comes from `document`, to the names
+/// they have in the bundled form of `entry`.
+fn rewrite(schema: &mut Serde, document: &str, entry: &str) {
+    if let Some(ref mut rxf) = schema.rxf {
+        let (target, def) = split(rxf).unwrap_or((document, rxf));
+        *rxf = if target == entry {
+            def.to_owned()
+        } else {
+            format!("{}#{}", target, def)
+        };
+    }
+
+    if let Some(ref mut sub_schema) = schema.elems {
+        rewrite(sub_schema, document, entry);
+    }
+
+    if let Some(ref mut sub_schema) = schema.values {
+        rewrite(sub_schema, document, entry);
+    }
+
+    for props in schema.props.iter_mut().chain(schema.opt_props.iter_mut()) {
+        for sub_schema in props.values_mut() {
+            rewrite(sub_schema, document, entry);
+        }
+    }
+
+    if let Some(ref mut discriminator) = schema.discriminator {
+        for sub_schema in discriminator.mapping.values_mut() {
+            rewrite(sub_schema, document, entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator::Validator;
+    use serde_json::{json, Value};
+    use std::fs;
+
+    fn resolver(documents: Vec<(&str, Value)>) -> MemoryResolver {
+        let mut resolver = MemoryResolver::new();
+        for (name, schema) in documents {
+            resolver.insert(name, serde_json::from_value(schema).unwrap());
+        }
+
+        resolver
+    }
+
+    fn load_err(resolver: &MemoryResolver, document: &str) -> String {
+        Bundle::new()
+            .load(resolver, document)
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn bundle_documents() {
+        let resolver = resolver(vec![
+            (
+                "main",
+                json!({
+                    "definitions": {
+                        "id": { "type": "string" },
+                    },
+                    "properties": {
+                        "id": { "ref": "main#id" },
+                        "home": { "ref": "common#address" },
+                        "tags": { "ref": "tags#tags" },
+                    },
+                }),
+            ),
+            (
+                "common",
+                json!({
+                    "definitions": {
+                        "address": {
+                            "properties": {
+                                "street": { "ref": "street" },
+                                "owner": { "ref": "main#id" },
+                            },
+                        },
+                        "street": { "type": "string" },
+                    },
+                }),
+            ),
+            (
+                "tags",
+                json!({
+                    "definitions": {
+                        "tags": { "elements": { "type": "string" } },
+                    },
+                }),
+            ),
+            ("unused", json!({})),
+        ]);
+
+        let mut bundle = Bundle::new();
+        bundle.load(&resolver, "main").unwrap();
+        assert_eq!(
+            bundle.documents().keys().collect::<Vec<_>>(),
+            vec!["main", "common", "tags"]
+        );
+
+        let schema = bundle.bundle("main").unwrap();
+        assert_eq!(
+            serde_json::to_value(schema.clone().into_serde()).unwrap(),
+            json!({
+                "definitions": {
+                    "id": { "type": "string" },
+                    "common#address": {
+                        "properties": {
+                            "street": { "ref": "common#street" },
+                            "owner": { "ref": "id" },
+                        },
+                    },
+                    "common#street": { "type": "string" },
+                    "tags#tags": { "elements": { "type": "string" } },
+                },
+                "properties": {
+                    "id": { "ref": "id" },
+                    "home": { "ref": "common#address" },
+                    "tags": { "ref": "tags#tags" },
+                },
+            })
+        );
+
+        let instance = json!({
+            "id": "a",
+            "home": { "street": 1, "owner": "b" },
+            "tags": ["c"],
+        });
+
+        let errors: Vec<_> = Validator::new()
+            .validate(&schema, &instance)
+            .unwrap()
+            .iter()
+            .map(|error| {
+                (
+                    error.instance_path().to_string(),
+                    error.schema_path().to_string(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            errors,
+            vec![(
+                "/home/street".to_owned(),
+                "/definitions/common#street/type".to_owned()
+            )]
+        );
+
+        // Bundling another document only includes what that one refers to.
+        let schema = bundle.bundle("tags").unwrap();
+        assert_eq!(
+            serde_json::to_value(schema.into_serde()).unwrap(),
+            json!({ "definitions": { "tags": { "elements": { "type": "string" } } } })
+        );
+    }
+
+    #[test]
+    fn load_errors() {
+        let resolver = resolver(vec![
+            ("missing-document", json!({ "ref": "nope#a" })),
+            ("missing-definition", json!({ "ref": "other#b" })),
+            ("missing-local", json!({ "ref": "b" })),
+            ("other", json!({ "definitions": { "a": {} } })),
+            ("ambiguous", json!({ "definitions": { "a#b": {} } })),
+            ("invalid", json!({ "type": "nope" })),
+        ]);
+
+        let cases = vec![
+            (
+                "missing-document",
+                JddfError::NoSuchDocument {
+                    document: "nope".to_owned(),
+                },
+            ),
+            (
+                "missing-definition",
+                JddfError::NoSuchDefinition {
+                    definition: "other#b".to_owned(),
+                },
+            ),
+            (
+                "missing-local",
+                JddfError::NoSuchDefinition {
+                    definition: "b".to_owned(),
+                },
+            ),
+            (
+                "ambiguous",
+                JddfError::AmbiguousDefinition {
+                    definition: "a#b".to_owned(),
+                },
+            ),
+            ("invalid", JddfError::InvalidForm),
+        ];
+
+        for (document, expected) in cases {
+            assert_eq!(load_err(&resolver, document), expected.to_string());
+        }
+
+        // A failed load leaves the bundle as it was.
+        let mut bundle = Bundle::new();
+        assert!(bundle.load(&resolver, "missing-definition").is_err());
+        assert!(bundle.documents().is_empty());
+        assert!(bundle.bundle("other").is_err());
+    }
+
+    #[test]
+    fn file_resolver() {
+        let dir = std::env::temp_dir().join(format!("jddf-bundle-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(
+            dir.join("main.json"),
+            r#"{ "elements": { "ref": "nested/common.json#a" } }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("nested/common.json"),
+            r#"{ "definitions": { "a": { "type": "boolean" } } }"#,
+        )
+        .unwrap();
+
+        let resolver = FileResolver::new(&dir);
+        let mut bundle = Bundle::new();
+        let result = bundle.load(&resolver, "main.json");
+        let escaped = resolver.resolve("../main.json");
+        let missing = resolver.resolve("missing.json");
+        fs::remove_dir_all(&dir).unwrap();
+
+        result.unwrap();
+        assert_eq!(
+            serde_json::to_value(bundle.bundle("main.json").unwrap().into_serde()).unwrap(),
+            json!({
+                "definitions": { "nested/common.json#a": { "type": "boolean" } },
+                "elements": { "ref": "nested/common.json#a" },
+            })
+        );
+
+        assert_eq!(
+            escaped.unwrap_err().to_string(),
+            JddfError::NoSuchDocument {
+                document: "../main.json".to_owned()
+            }
+            .to_string()
+        );
+
+        assert_eq!(
+            missing.unwrap_err().to_string(),
+            JddfError::NoSuchDocument {
+                document: "missing.json".to_owned()
+            }
+            .to_string()
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Canonical serialization and fingerprinting of schemas.
+//!
+//! Two schemas can be written differently, yet mean exactly the same thing.
+//! For example, the order of keys in `properties` is irrelevant to validation,
+//! as is the order of values in an `enum`. This module provides a *canonical*
+//! JSON form of a [`Schema`](../schema/struct.Schema.html), such that
+//! semantically identical schemas produce byte-for-byte identical output.
+//!
+//! The canonical form is useful as a cache key. See
+//! [`fingerprint`](fn.fingerprint.html) for a compact, stable hash of it.
+
+use crate::schema::{Schema, Serde};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Produce the canonical `Serde` representation of a schema.
+///
+/// The canonical representation:
+///
+/// * Sorts the keys of `definitions`, `properties`, `optionalProperties`, and
+///   `discriminator.mapping`,
+/// * Sorts the values of `enum`,
+/// * Omits `definitions` if there are none,
+/// * Omits `optionalProperties` if it is empty, and omits `properties` if it
+///   is empty unless `optionalProperties` is empty too, and
+/// * Omits `additionalProperties` unless it is `true`.
+pub fn canonicalize(schema: &Schema) -> Serde {
+    let mut out = schema.clone().into_serde();
+    normalize(&mut out);
+    out
+}
+
+/// Serialize a schema into its canonical JSON form.
+///
+/// The output is compact (no insignificant whitespace), and the keys of every
+/// object in it, including those of non-keyword data, are sorted.
+pub fn to_string(schema: &Schema) -> String {
+    let value = serde_json::to_value(canonicalize(schema))
+        .expect("unreachable: schemas always serialize to JSON");
+
+    let mut out = String::new();
+    write_value(&mut out, &value);
+    out
+}
+
+/// Compute a stable fingerprint of a schema.
+///
+/// The fingerprint is the SHA-256 digest of the schema's canonical JSON form
+/// (see [`to_string`](fn.to_string.html)). Semantically identical schemas
+/// share a fingerprint, and fingerprints do not vary between runs, platforms,
+/// or versions of this crate's dependencies.
+pub fn fingerprint(schema: &Schema) -> Fingerprint {
+    let mut bytes = [0; 32];
+    bytes.copy_from_slice(&Sha256::digest(to_string(schema).as_bytes()));
+    Fingerprint(bytes)
+}
+
+/// A SHA-256 fingerprint of a schema's canonical form.
+///
+/// `Fingerprint` implements `Display` as lowercase hex, which is convenient for
+/// use as a file name or cache key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fingerprint([u8; 32]);
+
+impl Fingerprint {
+    /// The raw bytes of the fingerprint.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn normalize(serde: &mut Serde) {
+    if let Some(defs) = serde.defs.as_mut() {
+        if defs.is_empty() {
+            serde.defs = None;
+        } else {
+            defs.sort_keys();
+            defs.values_mut().for_each(normalize);
+        }
+    }
+
+    if serde.additional_props != Some(true) {
+        serde.additional_props = None;
+    }
+
+    if let Some(enm) = serde.enm.as_mut() {
+        enm.sort();
+    }
+
+    if let Some(elems) = serde.elems.as_mut() {
+        normalize(elems);
+    }
+
+    if serde.props.is_some() || serde.opt_props.is_some() {
+        let mut props = serde.props.take().unwrap_or_default();
+        let mut opt_props = serde.opt_props.take().unwrap_or_default();
+
+        props.sort_keys();
+        props.values_mut().for_each(normalize);
+        opt_props.sort_keys();
+        opt_props.values_mut().for_each(normalize);
+
+        if !props.is_empty() || opt_props.is_empty() {
+            serde.props = Some(props);
+        }
+
+        if !opt_props.is_empty() {
+            serde.opt_props = Some(opt_props);
+        }
+    }
+
+    if let Some(values) = serde.values.as_mut() {
+        normalize(values);
+    }
+
+    if let Some(discriminator) = serde.discriminator.as_mut() {
+        discriminator.mapping.sort_keys();
+        discriminator.mapping.values_mut().for_each(normalize);
+    }
+
+    serde.extra.sort_keys();
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Array(elems) => {
+            out.push('[');
+            for (i, elem) in elems.iter().enumerate() {
+                if i != 0 {
+                    out.push(',');
+                }
+
+                write_value(out, elem);
+            }
+            out.push(']');
+        }
+        Value::Object(obj) => {
+            let mut keys: Vec<_> = obj.keys().collect();
+            keys.sort();
+
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i != 0 {
+                    out.push(',');
+                }
+
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_value(out, &obj[key]);
+            }
+            out.push('}');
+        }
+        _ => out.push_str(&value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::schema;
+    use serde_json::json;
+
+    #[test]
+    fn sorts_keys_and_enums() {
+        let a = schema(
+            serde_json::from_str(
+                r#"{
+                    "definitions": { "b": { "enum": ["Y", "X"] }, "a": {} },
+                    "properties": { "z": { "ref": "b" }, "y": { "type": "string" } },
+                    "metadata": { "z": 1, "a": [{ "d": null, "c": true }] }
+                }"#,
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(
+            to_string(&a),
+            r#"{"definitions":{"a":{},"b":{"enum":["X","Y"]}},"metadata":{"a":[{"c":true,"d":null}],"z":1},"properties":{"y":{"type":"string"},"z":{"ref":"b"}}}"#
+        );
+    }
+
+    #[test]
+    fn normalizes_equivalent_schemas() {
+        let cases = vec![
+            (json!({ "definitions": {} }), json!({})),
+            (
+                json!({ "properties": {}, "optionalProperties": { "a": {} } }),
+                json!({ "optionalProperties": { "a": {} } }),
+            ),
+            (
+                json!({ "properties": { "a": {} }, "optionalProperties": {} }),
+                json!({ "properties": { "a": {} } }),
+            ),
+            (
+                json!({ "optionalProperties": {} }),
+                json!({ "properties": {} }),
+            ),
+            (
+                json!({ "properties": {}, "additionalProperties": false }),
+                json!({ "properties": {} }),
+            ),
+            (
+                json!({ "enum": ["B", "A", "C"] }),
+                json!({ "enum": ["C", "A", "B"] }),
+            ),
+        ];
+
+        for (a, b) in cases {
+            let a = schema(a);
+            let b = schema(b);
+
+            assert_eq!(to_string(&a), to_string(&b));
+            assert_eq!(fingerprint(&a), fingerprint(&b));
+        }
+    }
+
+    #[test]
+    fn distinguishes_different_schemas() {
+        let cases = vec![
+            (
+                json!({ "properties": {}, "additionalProperties": true }),
+                json!({ "properties": {} }),
+            ),
+            (
+                json!({ "properties": { "a": {} } }),
+                json!({ "optionalProperties": { "a": {} } }),
+            ),
+            (json!({ "type": "int8" }), json!({ "type": "uint8" })),
+        ];
+
+        for (a, b) in cases {
+            let a = schema(a);
+            let b = schema(b);
+
+            assert_ne!(to_string(&a), to_string(&b));
+            assert_ne!(fingerprint(&a), fingerprint(&b));
+        }
+    }
+
+    #[test]
+    fn fingerprint_is_stable() {
+        let schema = schema(json!({ "type": "string" }));
+
+        assert_eq!(
+            fingerprint(&schema).to_string(),
+            "00404e686415370f1711c4d7acfa2905444d3cf23cef2e10c47d445ebe690f96"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Generate code from schemas.
+//!
+//! Each submodule of this module is a *target*, which turns a schema into
+//! source code in some language, into documentation, or into SQL tables.
+//! Definitions become named types, and nested schemas which need a name of
+//! their own, such as a struct within a struct, are named after the path
+//! leading to them.
+
+pub mod docs;
+pub mod go;
+pub mod ir;
+pub mod python;
+pub mod rust;
+pub mod sql;
+pub mod typescript;
+
+/// Split an identifier-ish string into words.
+///
+/// Words are separated by any non-alphanumeric character, and by the
+/// transition from a lowercase letter or digit to an uppercase letter. So
+/// `fooBar`, `foo_bar`, and `foo-bar` are all split into `foo` and `bar`.
+pub(crate) fn words(s: &str) -> Vec<String> {
+    let mut out = vec![];
+    let mut word = String::new();
+    let mut prev_lower = false;
+
+    for c in s.chars() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                out.push(std::mem::take(&mut word));
+            }
+
+            prev_lower = false;
+            continue;
+        }
+
+        if c.is_uppercase() && prev_lower && !word.is_empty() {
+            out.push(std::mem::take(&mut word));
+        }
+
+        prev_lower = c.is_lowercase() || c.is_numeric();
+        word.push(c);
+    }
+
+    if !word.is_empty() {
+        out.push(word);
+    }
+
+    out
+}
+
+/// Convert a string to `PascalCase`.
+pub(crate) fn pascal_case(s: &str) -> String {
+    words(s)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first
+                    .to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Convert a string to `snake_case`.
+pub(crate) fn snake_case(s: &str) -> String {
+    words(s)
+        .iter()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Make `name` unique among `taken` by appending a number to it if needed,
+/// and mark the result as taken.
+pub(crate) fn unique_name(taken: &mut std::collections::HashSet<String>, name: String) -> String {
+    let name = if taken.contains(&name) {
+        (2..)
+            .map(|n| format!("{}{}", name, n))
+            .find(|name| !taken.contains(name))
+            .unwrap()
+    } else {
+        name
+    };
+
+    taken.insert(name.clone());
+    name
+}
+
+/// Quote a string as a JSON string literal, which is also a valid string
+/// literal in every language we generate.
+pub(crate) fn string_literal(s: &str) -> String {
+    serde_json::to_string(s).unwrap()
+}
+
+/// Implement a target's `write` method in terms of its `generate` method.
+///
+/// `$output` describes what the target generates, for the doc comment.
+macro_rules! write_fn {
+    ($output:literal) => {
+        #[doc = concat!("Generate ", $output, " for a schema, and write the result to a file.")]
+        ///
+        /// This is a convenience for build scripts. Returns an error if the
+        /// file couldn't be written.
+        pub fn write<P: AsRef<::std::path::Path>>(
+            &self,
+            schema: &$crate::schema::Schema,
+            path: P,
+        ) -> Result<(), ::failure::Error> {
+            ::std::fs::write(path, self.generate(schema))?;
+            Ok(())
+        }
+    };
+}
+
+pub(crate) use write_fn;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cases() {
+        let cases = vec![
+            ("fooBar", "FooBar", "foo_bar"),
+            ("foo_bar", "FooBar", "foo_bar"),
+            ("foo-bar baz", "FooBarBaz", "foo_bar_baz"),
+            ("HTTPServer", "Httpserver", "httpserver"),
+            ("userID2fa", "UserId2fa", "user_id2fa"),
+            ("", "", ""),
+        ];
+
+        for (input, pascal, snake) in cases {
+            assert_eq!(pascal_case(input), pascal, "{}", input);
+            assert_eq!(snake_case(input), snake, "{}", input);
+        }
+    }
+}
@@ -0,0 +1,705 @@
+//! Generate human-readable documentation from schemas.
+//!
+//! Documentation can be generated as Markdown, for instance to be rendered
+//! by a code hosting site, or as a standalone HTML page:
+//!
+//! ```no_run
+//! use jddf::codegen::docs::{Config, Format, Generator};
+//! use jddf::{Schema, SerdeSchema};
+//!
+//! fn main() -> Result<(), failure::Error> {
+//!     let file = std::fs::read_to_string("schemas/event.json")?;
+//!     let serde_schema: SerdeSchema = serde_json::from_str(&file)?;
+//!     let schema = Schema::from_serde(serde_schema)?;
+//!
+//!     let mut config = Config::new();
+//!     config.title("Events").format(Format::Html);
+//!     Generator::new_with_config(config).write(&schema, "docs/events.html")?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! See the docs for [`Generator`](struct.Generator.html) for how each form is
+//! documented.
+
+use super::ir::{self, Expr, Field, Kind, Name, NamedType, TypeId};
+use super::{string_literal, write_fn};
+use crate::schema::{Schema, Type};
+use std::collections::HashMap;
+
+/// Generates documentation from schemas.
+///
+/// The root schema and each definition get a section of their own, as do the
+/// schemas nested within them which would be a type of their own in
+/// generated code. Sections are named the same way the types generated by
+/// the other targets are, so a struct in the `address` property of `User` is
+/// documented under `UserAddress`; see [`ir::lower`](../ir/fn.lower.html)
+/// for the details. A `description` in a schema's `metadata` is included
+/// as-is, so it may contain Markdown when generating Markdown.
+///
+/// Each section documents its schema as follows:
+///
+/// * `properties` becomes a table of properties, with their types, whether
+///   they're required, and their descriptions.
+/// * `enum` becomes a list of values.
+/// * `discriminator` becomes a table of tag values, each linking to the
+///   section documenting the properties that go with it.
+/// * Any other form is described by its type, such as "array of `string`".
+///
+/// References to other sections, such as those made by `ref`, are links.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate documentation for a schema.
+    ///
+    /// If `schema` is a root schema, its definitions are documented as well.
+    pub fn generate(&self, schema: &Schema) -> String {
+        let mut ir_config = ir::Config::new();
+        ir_config.root_name(&self.config.root_name);
+        let ir = ir::lower(schema, &ir_config);
+
+        let mut ctx = Context {
+            names: ir
+                .types()
+                .map(|(id, named_type)| (id, section_name(&named_type.name)))
+                .collect(),
+            parents: HashMap::new(),
+        };
+
+        for (id, named_type) in ir.types() {
+            if let Kind::Union { tag, variants } = &named_type.kind {
+                for (value, variant_id) in variants {
+                    ctx.parents
+                        .insert(*variant_id, (id, tag.as_str(), value.as_str()));
+                }
+            }
+        }
+
+        let sections: Vec<_> = ir
+            .types()
+            .map(|(id, named_type)| ctx.section(id, named_type))
+            .collect();
+
+        match self.config.format {
+            Format::Markdown => markdown(&self.config.title, &sections),
+            Format::Html => html(&self.config.title, &sections),
+        }
+    }
+
+    write_fn!("documentation");
+}
+
+/// Configuration for how documentation should be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    root_name: String,
+    title: String,
+    format: Format,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the section for the root schema. The default is
+    /// `Root`.
+    ///
+    /// It's cased like the names of the other sections.
+    pub fn root_name(&mut self, root_name: &str) -> &mut Self {
+        self.root_name = root_name.to_owned();
+        self
+    }
+
+    /// Sets the title of the document. The default is `Schema`.
+    pub fn title(&mut self, title: &str) -> &mut Self {
+        self.title = title.to_owned();
+        self
+    }
+
+    /// Sets the format of the document. The default is Markdown.
+    pub fn format(&mut self, format: Format) -> &mut Self {
+        self.format = format;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            root_name: "Root".to_owned(),
+            title: "Schema".to_owned(),
+            format: Format::Markdown,
+        }
+    }
+}
+
+/// The formats documentation can be generated in.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Format {
+    /// GitHub Flavored Markdown.
+    Markdown,
+
+    /// A standalone HTML page.
+    Html,
+}
+
+/// A piece of text, which the formats render differently.
+enum Span {
+    Text(String),
+    Code(String),
+    Link(String),
+}
+
+/// The documentation for a named type, independent of the format.
+struct Section {
+    name: String,
+    path: String,
+    description: Option<String>,
+    /// The discriminator this is a variant of, and the tag value it goes
+    /// with.
+    variant_of: Option<(String, String, String)>,
+    content: Content,
+}
+
+enum Content {
+    Type(Vec<Span>),
+    Values(Vec<String>),
+    Properties {
+        rows: Vec<Property>,
+        allow_additional: bool,
+    },
+    Variants {
+        tag: String,
+        rows: Vec<(String, String)>,
+    },
+}
+
+struct Property {
+    name: String,
+    type_: Vec<Span>,
+    optional: bool,
+    description: Option<String>,
+}
+
+struct Context<'a> {
+    names: HashMap<TypeId, String>,
+    /// The discriminator, tag, and tag value of each discriminator variant's
+    /// type.
+    parents: HashMap<TypeId, (TypeId, &'a str, &'a str)>,
+}
+
+impl<'a> Context<'a> {
+    fn section(&self, id: TypeId, named_type: &NamedType) -> Section {
+        let content = match &named_type.kind {
+            Kind::Alias(expr) => Content::Type(self.type_expr(expr)),
+            Kind::Enum(values) => Content::Values(values.clone()),
+            Kind::Struct {
+                fields,
+                allow_additional,
+            } => Content::Properties {
+                rows: fields.iter().map(|field| self.property(field)).collect(),
+                allow_additional: *allow_additional,
+            },
+            Kind::Union { tag, variants } => Content::Variants {
+                tag: tag.clone(),
+                rows: variants
+                    .iter()
+                    .map(|(value, id)| (value.clone(), self.names[id].clone()))
+                    .collect(),
+            },
+        };
+
+        Section {
+            name: self.names[&id].clone(),
+            path: format!("#{}", named_type.path),
+            description: named_type.description.clone(),
+            variant_of: self.parents.get(&id).map(|(parent, tag, value)| {
+                (
+                    self.names[parent].clone(),
+                    (*tag).to_owned(),
+                    (*value).to_owned(),
+                )
+            }),
+            content,
+        }
+    }
+
+    fn property(&self, field: &Field) -> Property {
+        Property {
+            name: field.name.clone(),
+            type_: self.type_expr(&field.type_),
+            optional: field.optional,
+            description: field.description.clone(),
+        }
+    }
+
+    /// A description of a type expression, such as "array of `string`".
+    fn type_expr(&self, expr: &Expr) -> Vec<Span> {
+        match expr {
+            Expr::Any => vec![Span::Text("any".to_owned())],
+            Expr::Primitive(type_) => vec![Span::Code(primitive(type_).to_owned())],
+            Expr::Named(id) => vec![Span::Link(self.names[id].clone())],
+            Expr::Array(expr) => {
+                let mut out = vec![Span::Text("array of ".to_owned())];
+                out.extend(self.type_expr(expr));
+                out
+            }
+            Expr::Map(expr) => {
+                let mut out = vec![Span::Text("map of ".to_owned())];
+                out.extend(self.type_expr(expr));
+                out
+            }
+        }
+    }
+}
+
+fn primitive(type_: &Type) -> &'static str {
+    match type_ {
+        Type::Boolean => "boolean",
+        Type::Float32 => "float32",
+        Type::Float64 => "float64",
+        Type::Int8 => "int8",
+        Type::Uint8 => "uint8",
+        Type::Int16 => "int16",
+        Type::Uint16 => "uint16",
+        Type::Int32 => "int32",
+        Type::Uint32 => "uint32",
+        Type::String => "string",
+        Type::Timestamp => "timestamp",
+    }
+}
+
+fn section_name(name: &Name) -> String {
+    name.pascal_case()
+}
+
+/// The anchor a section can be linked to with.
+///
+/// This is how GitHub makes anchors for headings, for the names sections
+/// are given.
+fn anchor(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+fn markdown(title: &str, sections: &[Section]) -> String {
+    let mut out = format!("# {}\n", title);
+
+    for section in sections {
+        out.push_str(&format!("\n## {}\n\n", section.name));
+
+        if let Some((parent, tag, value)) = &section.variant_of {
+            out.push_str(&format!(
+                "A variant of {}, where {} is {}.\n\n",
+                markdown_span(&Span::Link(parent.clone())),
+                markdown_span(&Span::Code(tag.clone())),
+                markdown_span(&Span::Code(string_literal(value))),
+            ));
+        }
+
+        if let Some(description) = &section.description {
+            out.push_str(description);
+            out.push_str("\n\n");
+        }
+
+        match &section.content {
+            Content::Type(type_) => {
+                out.push_str(&format!("Type: {}\n", markdown_spans(type_)));
+            }
+            Content::Values(values) => {
+                out.push_str("One of:\n\n");
+                for value in values {
+                    out.push_str(&format!(
+                        "- {}\n",
+                        markdown_span(&Span::Code(string_literal(value)))
+                    ));
+                }
+            }
+            Content::Properties {
+                rows,
+                allow_additional,
+            } => {
+                if rows.is_empty() {
+                    out.push_str("No properties.\n");
+                } else {
+                    out.push_str("| Property | Type | Required | Description |\n");
+                    out.push_str("| --- | --- | --- | --- |\n");
+                    for row in rows {
+                        out.push_str(&format!(
+                            "| {} | {} | {} | {} |\n",
+                            markdown_cell(&markdown_span(&Span::Code(row.name.clone()))),
+                            markdown_cell(&markdown_spans(&row.type_)),
+                            if row.optional { "No" } else { "Yes" },
+                            markdown_cell(row.description.as_deref().unwrap_or("")),
+                        ));
+                    }
+                }
+
+                if *allow_additional {
+                    out.push_str("\nAdditional properties are allowed.\n");
+                }
+            }
+            Content::Variants { tag, rows } => {
+                out.push_str(&format!(
+                    "Discriminated by the {} property.\n\n",
+                    markdown_span(&Span::Code(tag.clone()))
+                ));
+                out.push_str(&format!(
+                    "| {} | Properties |\n",
+                    markdown_cell(&markdown_span(&Span::Code(tag.clone())))
+                ));
+                out.push_str("| --- | --- |\n");
+                for (value, variant) in rows {
+                    out.push_str(&format!(
+                        "| {} | {} |\n",
+                        markdown_cell(&markdown_span(&Span::Code(string_literal(value)))),
+                        markdown_cell(&markdown_span(&Span::Link(variant.clone()))),
+                    ));
+                }
+            }
+        }
+
+        out.push_str(&format!(
+            "\nSchema: {}\n",
+            markdown_span(&Span::Code(section.path.clone()))
+        ));
+    }
+
+    out
+}
+
+fn markdown_spans(spans: &[Span]) -> String {
+    spans.iter().map(markdown_span).collect()
+}
+
+fn markdown_span(span: &Span) -> String {
+    match span {
+        Span::Text(text) => text.clone(),
+        Span::Code(code) => {
+            if code.contains('`') {
+                format!("`` {} ``", code)
+            } else {
+                format!("`{}`", code)
+            }
+        }
+        Span::Link(name) => format!("[{}](#{})", name, anchor(name)),
+    }
+}
+
+/// Escape text within a table cell, which must be on a single line.
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+        .lines()
+        .collect::<Vec<_>>()
+        .join("<br>")
+}
+
+fn html(title: &str, sections: &[Section]) -> String {
+    let mut out = "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n".to_owned();
+    out.push_str(&format!("<title>{}</title>\n", escape(title)));
+    out.push_str(STYLE);
+    out.push_str("</head>\n<body>\n");
+    out.push_str(&format!("<h1>{}</h1>\n", escape(title)));
+
+    for section in sections {
+        out.push_str(&format!(
+            "<section id=\"{}\">\n<h2>{}</h2>\n",
+            escape(&anchor(&section.name)),
+            escape(&section.name)
+        ));
+
+        if let Some((parent, tag, value)) = &section.variant_of {
+            out.push_str(&format!(
+                "<p>A variant of {}, where {} is {}.</p>\n",
+                html_span(&Span::Link(parent.clone())),
+                html_span(&Span::Code(tag.clone())),
+                html_span(&Span::Code(string_literal(value))),
+            ));
+        }
+
+        if let Some(description) = &section.description {
+            out.push_str(&html_paragraphs(description));
+        }
+
+        match &section.content {
+            Content::Type(type_) => {
+                out.push_str(&format!("<p>Type: {}</p>\n", html_spans(type_)));
+            }
+            Content::Values(values) => {
+                out.push_str("<p>One of:</p>\n<ul>\n");
+                for value in values {
+                    out.push_str(&format!(
+                        "<li>{}</li>\n",
+                        html_span(&Span::Code(string_literal(value)))
+                    ));
+                }
+                out.push_str("</ul>\n");
+            }
+            Content::Properties {
+                rows,
+                allow_additional,
+            } => {
+                if rows.is_empty() {
+                    out.push_str("<p>No properties.</p>\n");
+                } else {
+                    out.push_str("<table>\n<thead>\n");
+                    out.push_str("<tr><th>Property</th><th>Type</th><th>Required</th><th>Description</th></tr>\n");
+                    out.push_str("</thead>\n<tbody>\n");
+                    for row in rows {
+                        out.push_str(&format!(
+                            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                            html_span(&Span::Code(row.name.clone())),
+                            html_spans(&row.type_),
+                            if row.optional { "No" } else { "Yes" },
+                            escape(row.description.as_deref().unwrap_or("")),
+                        ));
+                    }
+                    out.push_str("</tbody>\n</table>\n");
+                }
+
+                if *allow_additional {
+                    out.push_str("<p>Additional properties are allowed.</p>\n");
+                }
+            }
+            Content::Variants { tag, rows } => {
+                out.push_str(&format!(
+                    "<p>Discriminated by the {} property.</p>\n",
+                    html_span(&Span::Code(tag.clone()))
+                ));
+                out.push_str("<table>\n<thead>\n");
+                out.push_str(&format!(
+                    "<tr><th>{}</th><th>Properties</th></tr>\n",
+                    html_span(&Span::Code(tag.clone()))
+                ));
+                out.push_str("</thead>\n<tbody>\n");
+                for (value, variant) in rows {
+                    out.push_str(&format!(
+                        "<tr><td>{}</td><td>{}</td></tr>\n",
+                        html_span(&Span::Code(string_literal(value))),
+                        html_span(&Span::Link(variant.clone())),
+                    ));
+                }
+                out.push_str("</tbody>\n</table>\n");
+            }
+        }
+
+        out.push_str(&format!(
+            "<p class=\"schema\">Schema: {}</p>\n</section>\n",
+            html_span(&Span::Code(section.path.clone()))
+        ));
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+const STYLE: &str = "<style>
+body { font-family: sans-serif; max-width: 60em; margin: 0 auto; padding: 1em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.5em; text-align: left; vertical-align: top; }
+.schema { color: #666; font-size: smaller; }
+</style>
+";
+
+fn html_spans(spans: &[Span]) -> String {
+    spans.iter().map(html_span).collect()
+}
+
+fn html_span(span: &Span) -> String {
+    match span {
+        Span::Text(text) => escape(text),
+        Span::Code(code) => format!("<code>{}</code>", escape(code)),
+        Span::Link(name) => format!(
+            "<a href=\"#{}\">{}</a>",
+            escape(&anchor(name)),
+            escape(name)
+        ),
+    }
+}
+
+/// Paragraphs for a description, which are separated by blank lines.
+fn html_paragraphs(text: &str) -> String {
+    text.split("\n\n")
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| format!("<p>{}</p>\n", escape(paragraph)))
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{schema, shapes};
+    use serde_json::json;
+
+    #[test]
+    fn generate_markdown() {
+        let out = Generator::new().generate(&shapes());
+
+        assert_eq!(
+            out,
+            r#"# Schema
+
+## Root
+
+Type: [Shape](#shape)
+
+Schema: `#`
+
+## Node
+
+| Property | Type | Required | Description |
+| --- | --- | --- | --- |
+| `value` | `float64` | Yes |  |
+| `next` | [Node](#node) | No |  |
+
+Schema: `#/definitions/node`
+
+## Shape
+
+Discriminated by the `kind` property.
+
+| `kind` | Properties |
+| --- | --- |
+| `"empty"` | [ShapeEmpty](#shapeempty) |
+| `"group"` | [ShapeGroup](#shapegroup) |
+| `"list"` | [ShapeList](#shapelist) |
+
+Schema: `#/definitions/shape`
+
+## ShapeEmpty
+
+A variant of [Shape](#shape), where `kind` is `"empty"`.
+
+No properties.
+
+Schema: `#/definitions/shape/discriminator/mapping/empty`
+
+## ShapeGroup
+
+A variant of [Shape](#shape), where `kind` is `"group"`.
+
+| Property | Type | Required | Description |
+| --- | --- | --- | --- |
+| `shapes` | [Shapes](#shapes) | Yes |  |
+
+Schema: `#/definitions/shape/discriminator/mapping/group`
+
+## ShapeList
+
+A variant of [Shape](#shape), where `kind` is `"list"`.
+
+| Property | Type | Required | Description |
+| --- | --- | --- | --- |
+| `head` | [Node](#node) | Yes |  |
+
+Schema: `#/definitions/shape/discriminator/mapping/list`
+
+## Shapes
+
+Type: array of [Shape](#shape)
+
+Schema: `#/definitions/shapes`
+"#
+        );
+    }
+
+    #[test]
+    fn generate_html() {
+        let mut config = Config::new();
+        config.title("Tags").format(Format::Html);
+
+        let out = Generator::new_with_config(config).generate(&schema(json!({
+            "metadata": { "description": "Tags & <labels>.\n\nSecond paragraph." },
+            "properties": {
+                "tags": { "elements": { "enum": ["a", "b"] } },
+            },
+            "additionalProperties": true,
+        })));
+
+        assert!(out.starts_with("<!DOCTYPE html>\n"));
+        assert!(out.contains("<title>Tags</title>\n"));
+        assert!(out.ends_with(
+            r##"<body>
+<h1>Tags</h1>
+<section id="root">
+<h2>Root</h2>
+<p>Tags &amp; &lt;labels&gt;.</p>
+<p>Second paragraph.</p>
+<table>
+<thead>
+<tr><th>Property</th><th>Type</th><th>Required</th><th>Description</th></tr>
+</thead>
+<tbody>
+<tr><td><code>tags</code></td><td>array of <a href="#roottagsitem">RootTagsItem</a></td><td>Yes</td><td></td></tr>
+</tbody>
+</table>
+<p>Additional properties are allowed.</p>
+<p class="schema">Schema: <code>#</code></p>
+</section>
+<section id="roottagsitem">
+<h2>RootTagsItem</h2>
+<p>One of:</p>
+<ul>
+<li><code>&quot;a&quot;</code></li>
+<li><code>&quot;b&quot;</code></li>
+</ul>
+<p class="schema">Schema: <code>#/properties/tags/elements</code></p>
+</section>
+</body>
+</html>
+"##
+        ));
+    }
+
+    #[test]
+    fn generate_root_name() {
+        let mut config = Config::new();
+        config.root_name("user account");
+        let out = Generator::new_with_config(config).generate(&schema(
+            json!({ "properties": { "id": { "type": "string" } } }),
+        ));
+
+        assert_eq!(
+            out,
+            r#"# Schema
+
+## UserAccount
+
+| Property | Type | Required | Description |
+| --- | --- | --- | --- |
+| `id` | `string` | Yes |  |
+
+Schema: `#`
+"#
+        );
+    }
+}
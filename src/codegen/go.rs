@@ -0,0 +1,877 @@
+//! Generate Go types from schemas.
+//!
+//! The generated code only depends on the standard library, and is formatted
+//! the way `gofmt` would format it. Use `encoding/json` to marshal and
+//! unmarshal the generated types, except for discriminators, which are
+//! interfaces and so need a generated function to unmarshal them:
+//!
+//! ```go
+//! event, err := UnmarshalEvent(data)
+//! ```
+//!
+//! See the docs for [`Generator`](struct.Generator.html) for how each form is
+//! converted.
+
+use super::ir::{self, Expr, Field, Ir, Kind, Name, NamedType, TypeId};
+use super::{pascal_case, string_literal, unique_name, write_fn};
+use crate::schema::{Schema, Type};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Generates Go source code from schemas.
+///
+/// The forms are converted as follows:
+///
+/// * The empty form becomes `interface{}`.
+/// * `type` becomes the corresponding primitive. `timestamp` becomes
+///   `time.Time`.
+/// * `enum` becomes a `string` type, with a constant for each value.
+/// * `elements` becomes a slice.
+/// * `properties` becomes a struct, with a `json` tag on each field. Optional
+///   properties become pointers tagged `omitempty`, so that they're omitted
+///   when `nil`.
+/// * `values` becomes a `map[string]_`.
+/// * `discriminator` becomes an interface, with a struct per mapping value
+///   implementing it. Each struct marshals with its tag, and a function named
+///   after the interface, such as `UnmarshalEvent` for an interface `Event`,
+///   unmarshals the struct for an instance's tag.
+/// * `ref` refers to the type generated for the definition. References to
+///   recursive structs are pointers.
+///
+/// The root schema and each definition become a named type. Types nested
+/// within them are named after the path leading to them, so a struct in the
+/// `address` property of `User` becomes `UserAddress`; see
+/// [`ir::lower`](../ir/fn.lower.html) for the details. A `description` in a
+/// schema's `metadata` becomes a comment.
+///
+/// Structs which contain discriminators get an `UnmarshalJSON` method, which
+/// uses the discriminator's function. So do definitions which are slices or
+/// maps of discriminators, which become types of their own rather than
+/// aliases for this reason.
+///
+/// Property names containing quotes, backslashes, or commas can't be
+/// expressed in a `json` tag, and aren't supported.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate Go source code for a schema.
+    ///
+    /// If `schema` is a root schema, its definitions are generated as well.
+    pub fn generate(&self, schema: &Schema) -> String {
+        let mut ir_config = ir::Config::new();
+        ir_config.root_name(&self.config.root_name);
+        let ir = ir::lower(schema, &ir_config);
+
+        let names: HashMap<_, _> = ir
+            .types()
+            .map(|(id, named_type)| (id, type_name(&named_type.name)))
+            .collect();
+
+        let mut ctx = Context {
+            ir: &ir,
+            taken: names.values().cloned().collect(),
+            names,
+            unmarshalers: HashMap::new(),
+            variants: HashMap::new(),
+            imports: BTreeSet::new(),
+        };
+
+        for (id, named_type) in ir.types() {
+            if let Kind::Union { tag, variants } = &named_type.kind {
+                let unmarshaler = format!("Unmarshal{}", ctx.names[&id]);
+                let unmarshaler = unique_name(&mut ctx.taken, unmarshaler);
+                ctx.unmarshalers.insert(id, unmarshaler);
+
+                for (value, variant_id) in variants {
+                    ctx.variants
+                        .insert(*variant_id, (id, tag.as_str(), value.as_str()));
+                }
+            }
+        }
+
+        let mut items = vec![];
+        for (id, named_type) in ir.types() {
+            items.push(ctx.item(id, named_type));
+        }
+
+        let mut out = "// Code generated by jddf. DO NOT EDIT.\n\n".to_owned();
+        out.push_str(&format!("package {}\n", self.config.package));
+
+        if !ctx.imports.is_empty() {
+            out.push_str("\nimport (\n");
+            for import in &ctx.imports {
+                out.push_str(&format!("\t{:?}\n", import));
+            }
+            out.push_str(")\n");
+        }
+
+        for item in items {
+            out.push('\n');
+            out.push_str(&item);
+        }
+
+        out
+    }
+
+    write_fn!("Go source code");
+}
+
+/// Configuration for how code should be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    root_name: String,
+    package: String,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the type generated for the root schema. The default is
+    /// `Root`.
+    ///
+    /// It's cased like any other type name, so that it's exported.
+    pub fn root_name(&mut self, root_name: &str) -> &mut Self {
+        self.root_name = root_name.to_owned();
+        self
+    }
+
+    /// Sets the name of the package the generated code belongs to. The
+    /// default is `schema`.
+    pub fn package(&mut self, package: &str) -> &mut Self {
+        self.package = package.to_owned();
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            root_name: "Root".to_owned(),
+            package: "schema".to_owned(),
+        }
+    }
+}
+
+struct Context<'a> {
+    ir: &'a Ir,
+    names: HashMap<TypeId, String>,
+    /// Every top-level name in the generated code.
+    taken: HashSet<String>,
+    /// The name of the function unmarshaling each discriminator.
+    unmarshalers: HashMap<TypeId, String>,
+    /// The discriminator, tag, and tag value of each discriminator variant's
+    /// type.
+    variants: HashMap<TypeId, (TypeId, &'a str, &'a str)>,
+    imports: BTreeSet<&'static str>,
+}
+
+impl<'a> Context<'a> {
+    /// Generate the declarations for a named type.
+    fn item(&mut self, id: TypeId, named_type: &NamedType) -> String {
+        let name = self.names[&id].clone();
+        let mut out = comment(&named_type.description, "");
+
+        match &named_type.kind {
+            Kind::Alias(expr) => {
+                let type_ = self.type_expr(expr, false);
+                if self.is_alias(id) {
+                    out.push_str(&format!("type {} = {}\n", name, type_));
+                } else {
+                    out.push_str(&format!("type {} {}\n", name, type_));
+                    if self.contains_union(expr) {
+                        out.push('\n');
+                        out.push_str(&self.alias_unmarshaler(&name, expr));
+                    }
+                }
+            }
+            Kind::Enum(values) => {
+                out.push_str(&format!("type {} string\n", name));
+
+                if !values.is_empty() {
+                    let consts: Vec<_> = values
+                        .iter()
+                        .map(|value| {
+                            let const_name = format!("{}{}", name, pascal_case(value));
+                            let const_name = unique_name(&mut self.taken, const_name);
+                            vec![const_name, format!("{} = {}", name, string_literal(value))]
+                        })
+                        .collect();
+
+                    out.push_str("\nconst (\n");
+                    out.push_str(&align(&consts, "\t"));
+                    out.push_str(")\n");
+                }
+            }
+            Kind::Struct { fields, .. } => {
+                let fields = field_names(fields);
+
+                if fields.is_empty() {
+                    out.push_str(&format!("type {} struct{{}}\n", name));
+                } else {
+                    out.push_str(&format!("type {} struct {{\n", name));
+                    out.push_str(&self.fields(&fields));
+                    out.push_str("}\n");
+                }
+
+                if let Some((union, tag, value)) = self.variants.get(&id).cloned() {
+                    self.imports.insert("encoding/json");
+                    out.push('\n');
+                    out.push_str(&format!(
+                        "func ({}) is{}() {{}}\n\n",
+                        name, self.names[&union]
+                    ));
+                    out.push_str(&format!(
+                        "func (v {}) MarshalJSON() ([]byte, error) {{\n",
+                        name
+                    ));
+                    out.push_str(&format!("\ttype plain {}\n", name));
+                    out.push_str("\treturn json.Marshal(struct {\n");
+                    out.push_str(&format!("\t\tTag string `json:{}`\n", string_literal(tag)));
+                    out.push_str("\t\tplain\n");
+                    out.push_str(&format!("\t}}{{{}, plain(v)}})\n", string_literal(value)));
+                    out.push_str("}\n");
+                }
+
+                if fields
+                    .iter()
+                    .any(|(_, field)| self.contains_union(&field.type_))
+                {
+                    out.push('\n');
+                    out.push_str(&self.struct_unmarshaler(&name, &fields));
+                }
+            }
+            Kind::Union { tag, variants } => {
+                out.push_str(&format!("type {} interface {{\n", name));
+                out.push_str(&format!("\tis{}()\n", name));
+                out.push_str("}\n\n");
+
+                self.imports.insert("encoding/json");
+                self.imports.insert("fmt");
+
+                out.push_str(&format!(
+                    "func {}(data []byte) ({}, error) {{\n",
+                    self.unmarshalers[&id], name
+                ));
+                out.push_str("\tvar tag struct {\n");
+                out.push_str(&format!("\t\tTag string `json:{}`\n", string_literal(tag)));
+                out.push_str("\t}\n");
+                out.push_str("\tif err := json.Unmarshal(data, &tag); err != nil {\n");
+                out.push_str("\t\treturn nil, err\n");
+                out.push_str("\t}\n\n");
+
+                if !variants.is_empty() {
+                    out.push_str("\tswitch tag.Tag {\n");
+                    for (value, variant_id) in variants {
+                        out.push_str(&format!("\tcase {}:\n", string_literal(value)));
+                        out.push_str(&format!("\t\tvar v {}\n", self.names[variant_id]));
+                        out.push_str("\t\terr := json.Unmarshal(data, &v)\n");
+                        out.push_str("\t\treturn v, err\n");
+                    }
+                    out.push_str("\t}\n\n");
+                }
+
+                let message = format!("unknown {} {}: %q", name, tag.replace('%', "%%"));
+                out.push_str(&format!(
+                    "\treturn nil, fmt.Errorf({}, tag.Tag)\n",
+                    string_literal(&message)
+                ));
+                out.push_str("}\n");
+            }
+        }
+
+        out
+    }
+
+    /// Generate the fields of a struct, aligned the way `gofmt` aligns them.
+    ///
+    /// Fields with comments are set apart by a blank line.
+    fn fields(&mut self, fields: &[(String, &Field)]) -> String {
+        let mut out = String::new();
+        let mut block = vec![];
+
+        for (i, (field_name, field)) in fields.iter().enumerate() {
+            if field.description.is_some() {
+                out.push_str(&align(&block, "\t"));
+                block.clear();
+
+                if i != 0 {
+                    out.push('\n');
+                }
+
+                out.push_str(&comment(&field.description, "\t"));
+            }
+
+            let type_ = self.field_type(field);
+            let tag = if field.optional {
+                format!(
+                    "`json:{}`",
+                    string_literal(&format!("{},omitempty", field.name))
+                )
+            } else {
+                format!("`json:{}`", string_literal(&field.name))
+            };
+
+            block.push(vec![field_name.clone(), type_, tag]);
+        }
+
+        out.push_str(&align(&block, "\t"));
+        out
+    }
+
+    /// Generate an `UnmarshalJSON` method for a struct with fields which
+    /// contain discriminators.
+    ///
+    /// Those fields are first unmarshaled as raw JSON, by shadowing them in a
+    /// struct which embeds the struct's fields, and then unmarshaled into
+    /// their actual types.
+    fn struct_unmarshaler(&mut self, name: &str, fields: &[(String, &Field)]) -> String {
+        let mut shadowed = vec![];
+        let mut decodes = String::new();
+
+        for (field_name, field) in fields {
+            if !self.contains_union(&field.type_) {
+                continue;
+            }
+
+            let tag = if field.optional {
+                format!(
+                    "`json:{}`",
+                    string_literal(&format!("{},omitempty", field.name))
+                )
+            } else {
+                format!("`json:{}`", string_literal(&field.name))
+            };
+
+            shadowed.push(vec![
+                field_name.clone(),
+                self.shadow_type(&field.type_),
+                tag,
+            ]);
+
+            let src = format!("shadow.{}", field_name);
+            let dst = format!("v.{}", field_name);
+
+            if !field.optional {
+                decodes.push_str(&self.decode(&field.type_, &src, &dst, 0, "\t"));
+            } else if self.is_interface(&field.type_) {
+                decodes.push_str(&format!("\tif {} != nil {{\n", src));
+                decodes.push_str(&self.decode(&field.type_, &src, &dst, 0, "\t\t"));
+                decodes.push_str("\t}\n");
+            } else {
+                decodes.push_str(&format!("\tif {} != nil {{\n", src));
+                decodes.push_str(&format!(
+                    "\t\tvar x {}\n",
+                    self.type_expr(&field.type_, true)
+                ));
+                decodes.push_str(&self.decode(&field.type_, &src, "x", 0, "\t\t"));
+                decodes.push_str(&format!("\t\t{} = &x\n", dst));
+                decodes.push_str("\t}\n");
+            }
+        }
+
+        self.imports.insert("encoding/json");
+
+        let mut out = format!("func (v *{}) UnmarshalJSON(data []byte) error {{\n", name);
+        out.push_str(&format!("\ttype plain {}\n", name));
+        out.push_str("\tvar shadow struct {\n");
+        out.push_str("\t\tplain\n");
+        out.push_str(&align(&shadowed, "\t\t"));
+        out.push_str("\t}\n");
+        out.push_str("\tif err := json.Unmarshal(data, &shadow); err != nil {\n");
+        out.push_str("\t\treturn err\n");
+        out.push_str("\t}\n\n");
+        out.push_str(&format!("\t*v = {}(shadow.plain)\n", name));
+        out.push_str("\tvar err error\n");
+        out.push_str(&decodes);
+        out.push_str("\treturn nil\n");
+        out.push_str("}\n");
+        out
+    }
+
+    /// Generate an `UnmarshalJSON` method for a slice or map type which
+    /// contains discriminators.
+    fn alias_unmarshaler(&mut self, name: &str, expr: &Expr) -> String {
+        self.imports.insert("encoding/json");
+
+        let mut out = format!("func (v *{}) UnmarshalJSON(data []byte) error {{\n", name);
+        out.push_str(&format!("\tvar shadow {}\n", self.shadow_type(expr)));
+        out.push_str("\tif err := json.Unmarshal(data, &shadow); err != nil {\n");
+        out.push_str("\t\treturn err\n");
+        out.push_str("\t}\n\n");
+        out.push_str("\tvar err error\n");
+        out.push_str(&format!("\tvar x {}\n", self.type_expr(expr, false)));
+        out.push_str(&self.decode(expr, "shadow", "x", 0, "\t"));
+        out.push_str("\t*v = x\n");
+        out.push_str("\treturn nil\n");
+        out.push_str("}\n");
+        out
+    }
+
+    /// Statements unmarshaling `src`, of the type `shadow_type` gives for
+    /// `expr`, into `dst`. `depth` is used to name loop variables.
+    fn decode(&mut self, expr: &Expr, src: &str, dst: &str, depth: usize, indent: &str) -> String {
+        match expr {
+            Expr::Named(id) => {
+                let unmarshaler = self.unmarshalers[&self.target(*id)].clone();
+                format!(
+                    "{}if {}, err = {}({}); err != nil {{\n{}\treturn err\n{}}}\n",
+                    indent, dst, unmarshaler, src, indent, indent
+                )
+            }
+            Expr::Array(item) => {
+                let inner = format!("{}\t", indent);
+                let mut out = format!(
+                    "{}{} = make({}, len({}))\n",
+                    indent,
+                    dst,
+                    self.type_expr(expr, false),
+                    src
+                );
+                out.push_str(&format!(
+                    "{}for i{}, v{} := range {} {{\n",
+                    indent, depth, depth, src
+                ));
+                out.push_str(&self.decode(
+                    item,
+                    &format!("v{}", depth),
+                    &format!("{}[i{}]", dst, depth),
+                    depth + 1,
+                    &inner,
+                ));
+                out.push_str(&format!("{}}}\n", indent));
+                out
+            }
+            Expr::Map(value) => {
+                let inner = format!("{}\t", indent);
+                let mut out = format!(
+                    "{}{} = make({}, len({}))\n",
+                    indent,
+                    dst,
+                    self.type_expr(expr, false),
+                    src
+                );
+                out.push_str(&format!(
+                    "{}for k{}, v{} := range {} {{\n",
+                    indent, depth, depth, src
+                ));
+                out.push_str(&format!(
+                    "{}var x{} {}\n",
+                    inner,
+                    depth,
+                    self.type_expr(value, false)
+                ));
+                out.push_str(&self.decode(
+                    value,
+                    &format!("v{}", depth),
+                    &format!("x{}", depth),
+                    depth + 1,
+                    &inner,
+                ));
+                out.push_str(&format!("{}{}[k{}] = x{}\n", inner, dst, depth, depth));
+                out.push_str(&format!("{}}}\n", indent));
+                out
+            }
+            Expr::Any | Expr::Primitive(_) => unreachable!("only discriminators are decoded"),
+        }
+    }
+
+    /// The type of a field, which is a pointer if the field is optional.
+    fn field_type(&mut self, field: &Field) -> String {
+        let type_ = self.type_expr(&field.type_, true);
+        if field.optional && !self.is_interface(&field.type_) && !type_.starts_with('*') {
+            format!("*{}", type_)
+        } else {
+            type_
+        }
+    }
+
+    /// The Go type for a type expression.
+    ///
+    /// `direct` is whether the type is stored inline in its parent, in which
+    /// case references to recursive structs need to be pointers.
+    fn type_expr(&mut self, expr: &Expr, direct: bool) -> String {
+        match expr {
+            Expr::Any => "interface{}".to_owned(),
+            Expr::Primitive(type_) => {
+                if let Type::Timestamp = type_ {
+                    self.imports.insert("time");
+                }
+
+                primitive(type_).to_owned()
+            }
+            Expr::Named(id) => {
+                let target = self.ir.get(self.target(*id));
+                if direct && target.recursive && matches!(target.kind, Kind::Struct { .. }) {
+                    format!("*{}", self.names[id])
+                } else {
+                    self.names[id].clone()
+                }
+            }
+            Expr::Array(expr) => format!("[]{}", self.type_expr(expr, false)),
+            Expr::Map(expr) => format!("map[string]{}", self.type_expr(expr, false)),
+        }
+    }
+
+    /// The type a type expression is first unmarshaled into, with raw JSON in
+    /// place of discriminators.
+    fn shadow_type(&mut self, expr: &Expr) -> String {
+        match expr {
+            _ if !self.contains_union(expr) => self.type_expr(expr, true),
+            Expr::Array(expr) => format!("[]{}", self.shadow_type(expr)),
+            Expr::Map(expr) => format!("map[string]{}", self.shadow_type(expr)),
+            _ => "json.RawMessage".to_owned(),
+        }
+    }
+
+    /// Follow references to definitions which are merely another name for
+    /// some other type.
+    fn target(&self, mut id: TypeId) -> TypeId {
+        for _ in 0..self.names.len() {
+            match &self.ir.get(id).kind {
+                Kind::Alias(Expr::Named(target)) => id = *target,
+                _ => break,
+            }
+        }
+
+        id
+    }
+
+    /// Whether a named type is generated as an alias, as opposed to a type of
+    /// its own.
+    ///
+    /// Types which refer to themselves can't be aliases, nor can types which
+    /// need an `UnmarshalJSON` method.
+    fn is_alias(&self, id: TypeId) -> bool {
+        let named_type = self.ir.get(id);
+        match &named_type.kind {
+            Kind::Alias(Expr::Named(_)) => true,
+            Kind::Alias(expr) => !named_type.recursive && !self.contains_union(expr),
+            _ => false,
+        }
+    }
+
+    /// Whether a type expression is generated as an interface, and so is
+    /// already nullable.
+    fn is_interface(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Any => true,
+            Expr::Named(id) => match &self.ir.get(self.target(*id)).kind {
+                Kind::Union { .. } => true,
+                Kind::Alias(expr) => self.is_alias(self.target(*id)) && self.is_interface(expr),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether a type expression contains discriminators which need to be
+    /// unmarshaled with their function.
+    ///
+    /// Discriminators within other named types are taken care of by those
+    /// types' own `UnmarshalJSON` methods.
+    fn contains_union(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Named(id) => matches!(self.ir.get(self.target(*id)).kind, Kind::Union { .. }),
+            Expr::Array(expr) | Expr::Map(expr) => self.contains_union(expr),
+            Expr::Any | Expr::Primitive(_) => false,
+        }
+    }
+}
+
+fn primitive(type_: &Type) -> &'static str {
+    match type_ {
+        Type::Boolean => "bool",
+        Type::Float32 => "float32",
+        Type::Float64 => "float64",
+        Type::Int8 => "int8",
+        Type::Uint8 => "uint8",
+        Type::Int16 => "int16",
+        Type::Uint16 => "uint16",
+        Type::Int32 => "int32",
+        Type::Uint32 => "uint32",
+        Type::String => "string",
+        Type::Timestamp => "time.Time",
+    }
+}
+
+/// Lay out lines of cells in columns, the way `gofmt` does. The last cell of
+/// each line isn't padded.
+fn align(lines: &[Vec<String>], indent: &str) -> String {
+    let mut widths = vec![];
+    for line in lines {
+        for (i, cell) in line.iter().enumerate().take(line.len() - 1) {
+            if widths.len() <= i {
+                widths.push(0);
+            }
+
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(indent);
+        for (i, cell) in line.iter().enumerate() {
+            if i == line.len() - 1 {
+                out.push_str(cell);
+            } else {
+                out.push_str(&format!("{:width$} ", cell, width = widths[i]));
+            }
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Comments for a description, if any.
+fn comment(description: &Option<String>, indent: &str) -> String {
+    match description {
+        Some(description) => description
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    format!("{}//\n", indent)
+                } else {
+                    format!("{}// {}\n", indent, line)
+                }
+            })
+            .collect(),
+        None => String::new(),
+    }
+}
+
+fn type_name(name: &Name) -> String {
+    exported(name.pascal_case(), "T")
+}
+
+/// Make the Go name of each field, unique within the struct.
+fn field_names(fields: &[Field]) -> Vec<(String, &Field)> {
+    let mut taken = HashSet::new();
+    fields
+        .iter()
+        .map(|field| {
+            let name = if pascal_case(&field.name).is_empty() {
+                "Field".to_owned()
+            } else {
+                exported(pascal_case(&field.name), "F")
+            };
+
+            (unique_name(&mut taken, name), field)
+        })
+        .collect()
+}
+
+/// Make a name exported, by prefixing it if it doesn't start with an
+/// uppercase letter.
+fn exported(name: String, prefix: &str) -> String {
+    if name.starts_with(char::is_uppercase) {
+        name
+    } else {
+        format!("{}{}", prefix, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_parses, schema, shapes, user};
+    use serde_json::json;
+
+    /// Reports syntax errors in Go from stdin.
+    const PARSE: &[&str] = &["gofmt", "-e"];
+
+    #[test]
+    fn generate_structs() {
+        let out = Generator::new().generate(&user());
+
+        assert_parses(&out, PARSE);
+        assert_eq!(
+            out,
+            r#"// Code generated by jddf. DO NOT EDIT.
+
+package schema
+
+import (
+	"time"
+)
+
+// A user.
+type Root struct {
+	Class     uint8              `json:"class"`
+	CreatedAt time.Time          `json:"createdAt"`
+	Id        string             `json:"id"`
+	Role      RootRole           `json:"role"`
+	Address   *RootAddress       `json:"address,omitempty"`
+	Friends   *[]interface{}     `json:"friends,omitempty"`
+	Labels    *map[string]string `json:"labels,omitempty"`
+}
+
+type RootRole string
+
+const (
+	RootRoleAdmin    RootRole = "admin"
+	RootRoleReadOnly RootRole = "read-only"
+)
+
+type RootAddress struct {
+	// The street.
+	Street string `json:"street"`
+}
+"#
+        );
+    }
+
+    #[test]
+    fn generate_unions() {
+        let out = Generator::new().generate(&shapes());
+
+        assert_parses(&out, PARSE);
+        assert_eq!(
+            out,
+            r#"// Code generated by jddf. DO NOT EDIT.
+
+package schema
+
+import (
+	"encoding/json"
+	"fmt"
+)
+
+type Root = Shape
+
+type Node struct {
+	Value float64 `json:"value"`
+	Next  *Node   `json:"next,omitempty"`
+}
+
+type Shape interface {
+	isShape()
+}
+
+func UnmarshalShape(data []byte) (Shape, error) {
+	var tag struct {
+		Tag string `json:"kind"`
+	}
+	if err := json.Unmarshal(data, &tag); err != nil {
+		return nil, err
+	}
+
+	switch tag.Tag {
+	case "empty":
+		var v ShapeEmpty
+		err := json.Unmarshal(data, &v)
+		return v, err
+	case "group":
+		var v ShapeGroup
+		err := json.Unmarshal(data, &v)
+		return v, err
+	case "list":
+		var v ShapeList
+		err := json.Unmarshal(data, &v)
+		return v, err
+	}
+
+	return nil, fmt.Errorf("unknown Shape kind: %q", tag.Tag)
+}
+
+type ShapeEmpty struct{}
+
+func (ShapeEmpty) isShape() {}
+
+func (v ShapeEmpty) MarshalJSON() ([]byte, error) {
+	type plain ShapeEmpty
+	return json.Marshal(struct {
+		Tag string `json:"kind"`
+		plain
+	}{"empty", plain(v)})
+}
+
+type ShapeGroup struct {
+	Shapes Shapes `json:"shapes"`
+}
+
+func (ShapeGroup) isShape() {}
+
+func (v ShapeGroup) MarshalJSON() ([]byte, error) {
+	type plain ShapeGroup
+	return json.Marshal(struct {
+		Tag string `json:"kind"`
+		plain
+	}{"group", plain(v)})
+}
+
+type ShapeList struct {
+	Head *Node `json:"head"`
+}
+
+func (ShapeList) isShape() {}
+
+func (v ShapeList) MarshalJSON() ([]byte, error) {
+	type plain ShapeList
+	return json.Marshal(struct {
+		Tag string `json:"kind"`
+		plain
+	}{"list", plain(v)})
+}
+
+type Shapes []Shape
+
+func (v *Shapes) UnmarshalJSON(data []byte) error {
+	var shadow []json.RawMessage
+	if err := json.Unmarshal(data, &shadow); err != nil {
+		return err
+	}
+
+	var err error
+	var x []Shape
+	x = make([]Shape, len(shadow))
+	for i0, v0 := range shadow {
+		if x[i0], err = UnmarshalShape(v0); err != nil {
+			return err
+		}
+	}
+	*v = x
+	return nil
+}
+"#
+        );
+    }
+
+    #[test]
+    fn generate_root_name() {
+        let mut config = Config::new();
+        config.root_name("2fa");
+        let out = Generator::new_with_config(config).generate(&schema(
+            json!({ "properties": { "id": { "type": "string" } } }),
+        ));
+
+        assert_parses(&out, PARSE);
+        assert_eq!(
+            out,
+            r#"// Code generated by jddf. DO NOT EDIT.
+
+package schema
+
+type T2fa struct {
+	Id string `json:"id"`
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,666 @@
+//! A language-neutral intermediate representation for code generation.
+//!
+//! Generating code from a schema means solving the same problems whatever the
+//! target language: naming the schemas nested within other schemas, telling
+//! which types are recursive, and turning discriminator variants into types
+//! of their own. This module solves them once, by lowering a schema into an
+//! [`Ir`](struct.Ir.html): a graph of named types which a target can walk
+//! without looking at the schema again.
+//!
+//! Names are given as a list of words, rather than as an identifier, so that
+//! each target can case them according to its own conventions. Two names are
+//! never the same once cased, whatever the casing.
+
+use super::words;
+use crate::schema::simplify::key;
+use crate::schema::{Form, Schema, Type};
+use indexmap::IndexMap;
+use json_pointer::JsonPointer;
+use std::collections::{HashMap, HashSet};
+
+/// Lower a schema into a graph of named types.
+///
+/// The root schema and each definition become a named type. So do the
+/// schemas nested within them which need a type of their own in most
+/// languages: enums, properties, and discriminators. These are named after
+/// the path leading to them, so the properties in the `address` property of
+/// `user` are named `user address`. Elements and values add `item` and
+/// `value` to the path, respectively.
+///
+/// A schema can override the name it would be given with a `name` in its
+/// `metadata`.
+///
+/// Each variant of a discriminator becomes a struct type of its own, named
+/// after the discriminator and the variant's tag value.
+pub fn lower(schema: &Schema, config: &Config) -> Ir {
+    let no_defs = IndexMap::new();
+    let defs = schema.definitions().as_ref().unwrap_or(&no_defs);
+
+    let mut lowering = Lowering {
+        config,
+        def_ids: HashMap::new(),
+        types: vec![],
+        taken: config
+            .reserved
+            .iter()
+            .map(|name| normalize(&words(name)))
+            .collect(),
+        shapes: HashMap::new(),
+        tokens: vec![],
+        owner: 0,
+        owners: vec![],
+    };
+
+    let root = lowering.reserve(schema, words(&config.root_name));
+    for (index, (name, sub_schema)) in defs.iter().enumerate() {
+        lowering.owner = index + 1;
+        let id = lowering.reserve(sub_schema, words(name));
+        lowering.def_ids.insert(name.as_str(), id);
+    }
+
+    lowering.owner = 0;
+
+    let root_words = lowering.types[root.0].name.words.clone();
+    let kind = lowering.kind(schema, &root_words);
+    lowering.types[root.0].kind = kind;
+
+    for (index, (name, sub_schema)) in defs.iter().enumerate() {
+        let id = lowering.def_ids[name.as_str()];
+        lowering.owner = index + 1;
+        let type_words = lowering.types[id.0].name.words.clone();
+
+        lowering.tokens = vec!["definitions".to_owned(), name.clone()];
+        lowering.types[id.0].path = pointer(&lowering.tokens);
+        let kind = lowering.kind(sub_schema, &type_words);
+        lowering.types[id.0].kind = kind;
+    }
+
+    // Types are created as they're needed, so the types nested within the
+    // root schema come after every definition. Put them back in place.
+    let mut order: Vec<_> = (0..lowering.types.len()).map(TypeId).collect();
+    order.sort_by_key(|id| lowering.owners[id.0]);
+
+    let mut ir = Ir {
+        root,
+        types: lowering.types,
+        order,
+    };
+
+    for index in 0..ir.types.len() {
+        let id = TypeId(index);
+        ir.types[index].recursive = ir.reachable(id).contains(&id);
+    }
+
+    ir
+}
+
+/// Configuration for how schemas are lowered.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    root_name: String,
+    reserved: Vec<String>,
+    dedupe: bool,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the type for the root schema. The default is `root`.
+    pub fn root_name(&mut self, root_name: &str) -> &mut Self {
+        self.root_name = root_name.to_owned();
+        self
+    }
+
+    /// Sets names no type may be given, such as the names of types built into
+    /// the target language. By default, no names are reserved.
+    pub fn reserved(&mut self, reserved: &[&str]) -> &mut Self {
+        self.reserved = reserved.iter().map(|name| (*name).to_owned()).collect();
+        self
+    }
+
+    /// Sets whether nested schemas with identical contents share a single
+    /// type. The type is named after the first of them. The default is to
+    /// share types.
+    pub fn dedupe(&mut self, dedupe: bool) -> &mut Self {
+        self.dedupe = dedupe;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            root_name: "root".to_owned(),
+            reserved: vec![],
+            dedupe: true,
+        }
+    }
+}
+
+/// A graph of named types, lowered from a schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ir {
+    root: TypeId,
+    types: Vec<NamedType>,
+    order: Vec<TypeId>,
+}
+
+impl Ir {
+    /// The type of the root schema.
+    pub fn root(&self) -> TypeId {
+        self.root
+    }
+
+    /// Get a type by its ID.
+    pub fn get(&self, id: TypeId) -> &NamedType {
+        &self.types[id.0]
+    }
+
+    /// All of the types, in a stable order.
+    ///
+    /// The root type comes first, followed by each definition in the order in
+    /// which they're declared. Each of these is followed by the types nested
+    /// within it, in the order in which they appear in the schema.
+    pub fn types(&self) -> impl Iterator<Item = (TypeId, &NamedType)> {
+        self.order.iter().map(move |id| (*id, self.get(*id)))
+    }
+
+    /// The types `id` refers to, directly or indirectly.
+    pub fn reachable(&self, id: TypeId) -> HashSet<TypeId> {
+        let mut out = HashSet::new();
+        let mut stack = vec![id];
+
+        while let Some(id) = stack.pop() {
+            let mut refs = vec![];
+            match &self.get(id).kind {
+                Kind::Alias(expr) => expr.refs(&mut refs),
+                Kind::Enum(_) => {}
+                Kind::Struct { fields, .. } => {
+                    for field in fields {
+                        field.type_.refs(&mut refs);
+                    }
+                }
+                Kind::Union { variants, .. } => {
+                    refs.extend(variants.iter().map(|(_, variant)| *variant))
+                }
+            }
+
+            for id in refs {
+                if out.insert(id) {
+                    stack.push(id);
+                }
+            }
+        }
+
+        out
+    }
+}
+
+/// Identifies a type within an [`Ir`](struct.Ir.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TypeId(usize);
+
+/// A type with a name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedType {
+    /// The name of the type.
+    pub name: Name,
+
+    /// A JSON Pointer to the schema the type was lowered from.
+    pub path: String,
+
+    /// The `description` in the schema's `metadata`, if any.
+    pub description: Option<String>,
+
+    /// Whether the type refers to itself, directly or indirectly.
+    ///
+    /// In languages where types are stored inline, references to recursive
+    /// types need to be boxed unless they're within an array or map.
+    pub recursive: bool,
+
+    /// What sort of type this is.
+    pub kind: Kind,
+}
+
+/// The sorts of named types.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    /// Another name for a type expression. Only the root schema and
+    /// definitions may be aliases.
+    Alias(Expr),
+
+    /// One of a set of strings.
+    Enum(Vec<String>),
+
+    /// An object with a known set of properties.
+    Struct {
+        fields: Vec<Field>,
+        allow_additional: bool,
+    },
+
+    /// An object whose properties depend on the value of its tag property.
+    ///
+    /// Each variant is a pair of a tag value and a `Struct` type. The struct's
+    /// fields don't include the tag.
+    Union {
+        tag: String,
+        variants: Vec<(String, TypeId)>,
+    },
+}
+
+/// A property of a `Struct` type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    /// The name of the property in JSON.
+    pub name: String,
+
+    /// The type of the property.
+    pub type_: Expr,
+
+    /// Whether the property may be omitted.
+    pub optional: bool,
+
+    /// The `description` in the property's `metadata`, if any.
+    pub description: Option<String>,
+}
+
+/// A type which doesn't need a name of its own.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Any JSON value.
+    Any,
+
+    /// A JSON primitive.
+    Primitive(Type),
+
+    /// A reference to a named type.
+    Named(TypeId),
+
+    /// An array of a type.
+    Array(Box<Expr>),
+
+    /// An object with arbitrary keys, and values of a type.
+    Map(Box<Expr>),
+}
+
+impl Expr {
+    fn refs(&self, out: &mut Vec<TypeId>) {
+        match self {
+            Expr::Named(id) => out.push(*id),
+            Expr::Array(expr) | Expr::Map(expr) => expr.refs(out),
+            Expr::Any | Expr::Primitive(_) => {}
+        }
+    }
+}
+
+/// The name of a type, as a list of lowercase words.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Name {
+    words: Vec<String>,
+}
+
+impl Name {
+    /// The words making up the name.
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    /// The name in `PascalCase`.
+    pub fn pascal_case(&self) -> String {
+        self.words.iter().map(|word| capitalize(word)).collect()
+    }
+
+    /// The name in `camelCase`.
+    pub fn camel_case(&self) -> String {
+        let mut out = String::new();
+        for (i, word) in self.words.iter().enumerate() {
+            if i == 0 {
+                out.push_str(word);
+            } else {
+                out.push_str(&capitalize(word));
+            }
+        }
+
+        out
+    }
+
+    /// The name in `snake_case`.
+    pub fn snake_case(&self) -> String {
+        self.words.join("_")
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The form of a name used to check for collisions. Two names which differ
+/// only in case or word boundaries could end up the same in some casing.
+fn normalize(words: &[String]) -> String {
+    words.concat().to_lowercase()
+}
+
+fn pointer(tokens: &[String]) -> String {
+    JsonPointer::new(tokens.to_vec()).to_string()
+}
+
+struct Lowering<'a> {
+    config: &'a Config,
+    def_ids: HashMap<&'a str, TypeId>,
+    types: Vec<NamedType>,
+    taken: HashSet<String>,
+    shapes: HashMap<String, TypeId>,
+    tokens: Vec<String>,
+
+    /// The index of the root schema (zero) or definition (one onwards) being
+    /// lowered, and of the one each type was created for.
+    owner: usize,
+    owners: Vec<usize>,
+}
+
+impl<'a> Lowering<'a> {
+    /// Add a type for `schema`, with a unique name based on `words` unless the
+    /// schema has a name hint. Its kind is filled in later.
+    fn reserve(&mut self, schema: &Schema, words: Vec<String>) -> TypeId {
+        let hint = schema
+            .extra()
+            .get("metadata")
+            .and_then(|metadata| metadata.get("name"))
+            .and_then(|name| name.as_str())
+            .map(super::words);
+
+        let mut words: Vec<String> = hint
+            .unwrap_or(words)
+            .iter()
+            .map(|word| word.to_lowercase())
+            .collect();
+
+        if words.is_empty() {
+            words.push("type".to_owned());
+        }
+
+        if self.taken.contains(&normalize(&words)) {
+            let n = (2..)
+                .find(|n| {
+                    let mut candidate = words.clone();
+                    candidate.push(n.to_string());
+                    !self.taken.contains(&normalize(&candidate))
+                })
+                .unwrap();
+
+            words.push(n.to_string());
+        }
+
+        self.taken.insert(normalize(&words));
+        self.owners.push(self.owner);
+        self.types.push(NamedType {
+            name: Name { words },
+            path: pointer(&self.tokens),
+            description: description(schema),
+            recursive: false,
+            kind: Kind::Alias(Expr::Any),
+        });
+
+        TypeId(self.types.len() - 1)
+    }
+
+    /// The kind of named type for `schema`, whose own name is `words`.
+    fn kind(&mut self, schema: &Schema, words: &[String]) -> Kind {
+        match schema.form() {
+            Form::Enum(values) => Kind::Enum(values.iter().cloned().collect()),
+            Form::Properties {
+                required,
+                optional,
+                allow_additional,
+                ..
+            } => Kind::Struct {
+                fields: self.fields(words, required, optional),
+                allow_additional: *allow_additional,
+            },
+            Form::Discriminator(tag, mapping) => {
+                self.tokens.push("discriminator".to_owned());
+                self.tokens.push("mapping".to_owned());
+
+                let mut variants = vec![];
+                for (value, sub_schema) in mapping {
+                    self.tokens.push(value.clone());
+
+                    let mut variant_words = words.to_vec();
+                    variant_words.extend(super::words(value));
+
+                    let id = self.reserve(sub_schema, variant_words);
+                    let variant_words = self.types[id.0].name.words.clone();
+                    let kind = self.kind(sub_schema, &variant_words);
+                    self.types[id.0].kind = kind;
+                    variants.push((value.clone(), id));
+
+                    self.tokens.pop();
+                }
+
+                self.tokens.pop();
+                self.tokens.pop();
+
+                Kind::Union {
+                    tag: tag.clone(),
+                    variants,
+                }
+            }
+            _ => Kind::Alias(self.expr(schema, words)),
+        }
+    }
+
+    fn fields(
+        &mut self,
+        parent: &[String],
+        required: &IndexMap<String, Schema>,
+        optional: &IndexMap<String, Schema>,
+    ) -> Vec<Field> {
+        let mut out = vec![];
+
+        let properties = required
+            .iter()
+            .map(|(name, sub_schema)| ("properties", name, sub_schema, false))
+            .chain(
+                optional
+                    .iter()
+                    .map(|(name, sub_schema)| ("optionalProperties", name, sub_schema, true)),
+            );
+
+        for (keyword, name, sub_schema, optional) in properties {
+            self.tokens.push(keyword.to_owned());
+            self.tokens.push(name.clone());
+
+            let mut field_words = parent.to_vec();
+            field_words.extend(words(name));
+
+            out.push(Field {
+                name: name.clone(),
+                type_: self.expr(sub_schema, &field_words),
+                optional,
+                description: description(sub_schema),
+            });
+
+            self.tokens.pop();
+            self.tokens.pop();
+        }
+
+        out
+    }
+
+    /// The type expression for `schema`, adding named types for nested
+    /// schemas as needed. `words` is the name such a type would be given.
+    fn expr(&mut self, schema: &Schema, words: &[String]) -> Expr {
+        match schema.form() {
+            Form::Empty => Expr::Any,
+            Form::Ref(def) => Expr::Named(self.def_ids[def.as_str()]),
+            Form::Type(type_) => Expr::Primitive(type_.clone()),
+            Form::Elements(sub_schema) => {
+                self.tokens.push("elements".to_owned());
+                let mut item_words = words.to_vec();
+                item_words.push("item".to_owned());
+                let expr = self.expr(sub_schema, &item_words);
+                self.tokens.pop();
+
+                Expr::Array(Box::new(expr))
+            }
+            Form::Values(sub_schema) => {
+                self.tokens.push("values".to_owned());
+                let mut value_words = words.to_vec();
+                value_words.push("value".to_owned());
+                let expr = self.expr(sub_schema, &value_words);
+                self.tokens.pop();
+
+                Expr::Map(Box::new(expr))
+            }
+            Form::Enum(_) | Form::Properties { .. } | Form::Discriminator(..) => {
+                let shape = key(schema);
+                if self.config.dedupe {
+                    if let Some(id) = self.shapes.get(&shape) {
+                        return Expr::Named(*id);
+                    }
+                }
+
+                let id = self.reserve(schema, words.to_vec());
+                self.shapes.insert(shape, id);
+
+                let type_words = self.types[id.0].name.words.clone();
+                let kind = self.kind(schema, &type_words);
+                self.types[id.0].kind = kind;
+
+                Expr::Named(id)
+            }
+        }
+    }
+}
+
+fn description(schema: &Schema) -> Option<String> {
+    schema
+        .extra()
+        .get("metadata")
+        .and_then(|metadata| metadata.get("description"))
+        .and_then(|description| description.as_str())
+        .map(str::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::schema;
+    use serde_json::json;
+
+    fn lower_json(config: &Config, value: serde_json::Value) -> Ir {
+        lower(&schema(value), config)
+    }
+
+    fn names(ir: &Ir) -> Vec<(String, String)> {
+        ir.types()
+            .map(|(_, named_type)| (named_type.name.pascal_case(), named_type.path.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn names_and_paths() {
+        let mut config = Config::new();
+        config.reserved(&["string"]);
+
+        let ir = lower_json(
+            &config,
+            json!({
+                "definitions": {
+                    "string": { "type": "string" },
+                    "rootAddress": { "type": "string" },
+                },
+                "properties": {
+                    "address": {
+                        "properties": {
+                            "lines": {
+                                "elements": {
+                                    "metadata": { "name": "line" },
+                                    "properties": {},
+                                },
+                            },
+                        },
+                    },
+                    "event": {
+                        "discriminator": {
+                            "tag": "type",
+                            "mapping": {
+                                "user_created": { "properties": {} },
+                            },
+                        },
+                    },
+                },
+            }),
+        );
+
+        assert_eq!(
+            names(&ir),
+            vec![
+                ("Root".to_owned(), "".to_owned()),
+                ("RootAddress2".to_owned(), "/properties/address".to_owned()),
+                (
+                    "Line".to_owned(),
+                    "/properties/address/properties/lines/elements".to_owned()
+                ),
+                ("RootEvent".to_owned(), "/properties/event".to_owned()),
+                (
+                    "RootEventUserCreated".to_owned(),
+                    "/properties/event/discriminator/mapping/user_created".to_owned()
+                ),
+                (
+                    "RootAddress".to_owned(),
+                    "/definitions/rootAddress".to_owned()
+                ),
+                ("String2".to_owned(), "/definitions/string".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn recursion_and_dedupe() {
+        let ir = lower_json(
+            &Config::new(),
+            json!({
+                "definitions": {
+                    "node": {
+                        "properties": {
+                            "children": { "elements": { "ref": "node" } },
+                            "color": { "enum": ["red", "black"] },
+                        },
+                    },
+                },
+                "properties": {
+                    "tree": { "ref": "node" },
+                    "color": { "enum": ["red", "black"] },
+                },
+            }),
+        );
+
+        let types: Vec<_> = ir
+            .types()
+            .map(|(_, named_type)| (named_type.name.snake_case(), named_type.recursive))
+            .collect();
+
+        assert_eq!(
+            types,
+            vec![
+                ("root".to_owned(), false),
+                ("root_color".to_owned(), false),
+                ("node".to_owned(), true),
+            ]
+        );
+
+        let (node, color) = (ir.get(TypeId(1)), TypeId(2));
+        match &node.kind {
+            Kind::Struct { fields, .. } => assert_eq!(fields[1].type_, Expr::Named(color)),
+            kind => panic!("unexpected kind: {:?}", kind),
+        }
+    }
+}
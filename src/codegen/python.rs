@@ -0,0 +1,864 @@
+//! Generate Python dataclasses from schemas.
+//!
+//! The generated code only depends on the standard library, and needs Python
+//! 3.7 or later. Each generated class has a `from_json` class method, which
+//! builds an instance from the output of `json.loads`, and a `to_json` method,
+//! which does the reverse:
+//!
+//! ```python
+//! user = User.from_json(json.loads(data))
+//! data = json.dumps(user.to_json())
+//! ```
+//!
+//! See the docs for [`Generator`](struct.Generator.html) for how each form is
+//! converted.
+
+use super::ir::{self, Expr, Field, Ir, Kind, Name, NamedType, TypeId};
+use super::{snake_case, string_literal, unique_name, write_fn};
+use crate::schema::{Schema, Type};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Generates Python source code from schemas.
+///
+/// The forms are converted as follows:
+///
+/// * The empty form becomes `Any`.
+/// * `type` becomes `bool`, `float`, `int`, or `str`. Timestamps are strings.
+/// * `enum` becomes an `Enum` whose members' values are the enum's values.
+/// * `elements` becomes a `List`.
+/// * `properties` becomes a dataclass. Optional properties become `Optional`
+///   fields which default to `None`, and are omitted by `to_json` when `None`.
+/// * `values` becomes a `Dict[str, _]`.
+/// * `discriminator` becomes a base class, with a dataclass subclass per
+///   mapping value. The base class's `from_json` builds the subclass for the
+///   instance's tag.
+/// * `ref` refers to the type generated for the definition.
+///
+/// The root schema and each definition become a named type. Classes nested
+/// within them are named after the path leading to them, so a class in the
+/// `address` property of `User` becomes `UserAddress`; see
+/// [`ir::lower`](../ir/fn.lower.html) for the details. A `description` in a
+/// schema's `metadata` becomes a docstring.
+///
+/// Definitions which aren't classes become type aliases. Since an alias can't
+/// have methods, each also gets a pair of functions named after it, such as
+/// `user_ids_from_json` and `user_ids_to_json` for an alias `UserIds`.
+///
+/// `from_json` assumes its input is valid. Use a
+/// [`Validator`](../../struct.Validator.html) first if it might not be.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate Python source code for a schema.
+    ///
+    /// If `schema` is a root schema, its definitions are generated as well.
+    pub fn generate(&self, schema: &Schema) -> String {
+        let mut ir_config = ir::Config::new();
+        ir_config
+            .root_name(&self.config.root_name)
+            .reserved(RESERVED);
+        let ir = ir::lower(schema, &ir_config);
+
+        let mut ctx = Context {
+            ir: &ir,
+            names: ir
+                .types()
+                .map(|(id, named_type)| (id, type_name(&named_type.name)))
+                .collect(),
+            bases: HashMap::new(),
+            imports: BTreeSet::new(),
+        };
+
+        for (id, named_type) in ir.types() {
+            if let Kind::Union { tag, variants } = &named_type.kind {
+                for (value, variant_id) in variants {
+                    ctx.bases
+                        .insert(*variant_id, (id, tag.as_str(), value.as_str()));
+                }
+            }
+        }
+
+        // Aliases are evaluated when the module is loaded, so they come after
+        // the classes they may refer to, and after any alias they're simply
+        // another name for.
+        let mut items = vec![];
+        let mut pending = vec![];
+        for (id, named_type) in ir.types() {
+            match &named_type.kind {
+                Kind::Alias(_) => pending.push((id, named_type)),
+                _ => items.push(ctx.class(id, named_type)),
+            }
+        }
+
+        let mut done = HashSet::new();
+        while !pending.is_empty() {
+            let next = pending
+                .iter()
+                .position(|(_, named_type)| match &named_type.kind {
+                    Kind::Alias(Expr::Named(target)) => {
+                        !matches!(ir.get(*target).kind, Kind::Alias(_)) || done.contains(target)
+                    }
+                    _ => true,
+                })
+                .unwrap_or(0);
+
+            let (id, named_type) = pending.remove(next);
+            done.insert(id);
+            items.push(ctx.alias(id, named_type));
+        }
+
+        let mut out = "# This file was generated by jddf. Do not edit it by hand.\n\n".to_owned();
+        out.push_str("from __future__ import annotations\n\n");
+
+        if ctx.imports.remove("dataclass") {
+            out.push_str("from dataclasses import dataclass\n");
+        }
+
+        if ctx.imports.remove("Enum") {
+            out.push_str("from enum import Enum\n");
+        }
+
+        let typing: Vec<_> = ctx.imports.iter().cloned().collect();
+        if !typing.is_empty() {
+            out.push_str(&format!("from typing import {}\n", typing.join(", ")));
+        }
+
+        for item in items {
+            out.push_str("\n\n");
+            out.push_str(&item);
+        }
+
+        out
+    }
+
+    write_fn!("Python source code");
+}
+
+/// Configuration for how code should be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    root_name: String,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the type generated for the root schema. The default is
+    /// `Root`.
+    ///
+    /// It's cased like any other class name, and given a number if it clashes
+    /// with a name the generated code uses, such as `Optional`.
+    pub fn root_name(&mut self, root_name: &str) -> &mut Self {
+        self.root_name = root_name.to_owned();
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            root_name: "Root".to_owned(),
+        }
+    }
+}
+
+/// Names the generated code imports, or which are keywords, and so which
+/// generated types must not be given.
+const RESERVED: &[&str] = &[
+    "Any", "Dict", "Enum", "False", "List", "None", "Optional", "True",
+];
+
+const KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// Names fields must not be given, because they'd shadow a method.
+const METHODS: &[&str] = &["from_json", "to_json"];
+
+struct Context<'a> {
+    ir: &'a Ir,
+    names: HashMap<TypeId, String>,
+    /// The base class, tag, and tag value of each discriminator variant's
+    /// type.
+    bases: HashMap<TypeId, (TypeId, &'a str, &'a str)>,
+    imports: BTreeSet<&'static str>,
+}
+
+impl<'a> Context<'a> {
+    /// Generate the class for an enum, struct, or union type.
+    fn class(&mut self, id: TypeId, named_type: &NamedType) -> String {
+        let name = self.names[&id].clone();
+        self.imports.insert("Any");
+
+        match &named_type.kind {
+            Kind::Alias(_) => unreachable!("aliases are not classes"),
+            Kind::Enum(values) => {
+                let mut members = HashSet::new();
+
+                self.imports.insert("Enum");
+                let mut out = format!("class {}(Enum):\n", name);
+                out.push_str(&docstring(&named_type.description, "    "));
+                if named_type.description.is_some() && !values.is_empty() {
+                    out.push('\n');
+                }
+
+                for value in values {
+                    let member = unique_name(&mut members, member_name(value));
+                    out.push_str(&format!("    {} = {}\n", member, string_literal(value)));
+                }
+
+                if values.is_empty() {
+                    out.push_str("    pass\n");
+                }
+
+                out
+            }
+            Kind::Struct { fields, .. } => {
+                let mut field_names = HashSet::new();
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|field| {
+                        (
+                            unique_name(&mut field_names, field_name(&field.name)),
+                            field,
+                        )
+                    })
+                    .collect();
+
+                self.imports.insert("dataclass");
+                self.imports.insert("Dict");
+
+                let mut out = "@dataclass\n".to_owned();
+                match self.bases.get(&id) {
+                    Some((base, _, _)) => {
+                        out.push_str(&format!("class {}({}):\n", name, self.names[base]))
+                    }
+                    None => out.push_str(&format!("class {}:\n", name)),
+                }
+
+                out.push_str(&docstring(&named_type.description, "    "));
+                if named_type.description.is_some() {
+                    out.push('\n');
+                }
+
+                for (field_name, field) in &fields {
+                    out.push_str(&comment(&field.description, "    "));
+                    if field.optional {
+                        self.imports.insert("Optional");
+                        out.push_str(&format!(
+                            "    {}: Optional[{}] = None\n",
+                            field_name,
+                            self.type_expr(&field.type_, false)
+                        ));
+                    } else {
+                        out.push_str(&format!(
+                            "    {}: {}\n",
+                            field_name,
+                            self.type_expr(&field.type_, false)
+                        ));
+                    }
+                }
+
+                if !fields.is_empty() {
+                    out.push('\n');
+                }
+
+                out.push_str(&self.decoder(&name, &fields));
+                out.push('\n');
+                out.push_str(&self.encoder(id, &fields));
+                out
+            }
+            Kind::Union { tag, variants } => {
+                let mut out = format!("class {}:\n", name);
+                out.push_str(&docstring(&named_type.description, "    "));
+                if named_type.description.is_some() {
+                    out.push('\n');
+                }
+
+                self.imports.insert("Dict");
+                out.push_str("    @classmethod\n");
+                out.push_str(&format!("    def from_json(cls, data: Any) -> {}:\n", name));
+                out.push_str("        variants: Dict[str, Any] = {\n");
+                for (value, variant_id) in variants {
+                    out.push_str(&format!(
+                        "            {}: {},\n",
+                        string_literal(value),
+                        self.names[variant_id]
+                    ));
+                }
+                out.push_str("        }\n\n");
+                out.push_str(&format!(
+                    "        return variants[data[{}]].from_json(data)\n\n",
+                    string_literal(tag)
+                ));
+                out.push_str("    def to_json(self) -> Any:\n");
+                out.push_str("        raise NotImplementedError\n");
+                out
+            }
+        }
+    }
+
+    /// Generate the `from_json` class method of a dataclass.
+    fn decoder(&mut self, name: &str, fields: &[(String, &Field)]) -> String {
+        let mut out = "    @classmethod\n".to_owned();
+        out.push_str(&format!("    def from_json(cls, data: Any) -> {}:\n", name));
+
+        if fields.is_empty() {
+            out.push_str("        return cls()\n");
+            return out;
+        }
+
+        out.push_str("        return cls(\n");
+        for (field_name, field) in fields {
+            let property = format!("data[{}]", string_literal(&field.name));
+            let value = self.decode(&field.type_, &property, 0);
+
+            if field.optional {
+                out.push_str(&format!(
+                    "            {}={} if {} in data else None,\n",
+                    field_name,
+                    value,
+                    string_literal(&field.name)
+                ));
+            } else {
+                out.push_str(&format!("            {}={},\n", field_name, value));
+            }
+        }
+        out.push_str("        )\n");
+        out
+    }
+
+    /// Generate the `to_json` method of a dataclass.
+    fn encoder(&mut self, id: TypeId, fields: &[(String, &Field)]) -> String {
+        let mut out = "    def to_json(self) -> Any:\n".to_owned();
+        out.push_str("        data: Dict[str, Any] = {\n");
+
+        if let Some((_, tag, value)) = self.bases.get(&id) {
+            out.push_str(&format!(
+                "            {}: {},\n",
+                string_literal(tag),
+                string_literal(value)
+            ));
+        }
+
+        for (field_name, field) in fields {
+            if !field.optional {
+                let value = self.encode(&field.type_, &format!("self.{}", field_name), 0);
+                out.push_str(&format!(
+                    "            {}: {},\n",
+                    string_literal(&field.name),
+                    value
+                ));
+            }
+        }
+        out.push_str("        }\n");
+
+        for (field_name, field) in fields {
+            if field.optional {
+                let value = self.encode(&field.type_, &format!("self.{}", field_name), 0);
+                out.push_str(&format!("        if self.{} is not None:\n", field_name));
+                out.push_str(&format!(
+                    "            data[{}] = {}\n",
+                    string_literal(&field.name),
+                    value
+                ));
+            }
+        }
+
+        out.push_str("        return data\n");
+        out
+    }
+
+    /// Generate a type alias, and the functions converting it to and from
+    /// JSON.
+    fn alias(&mut self, id: TypeId, named_type: &NamedType) -> String {
+        let name = self.names[&id].clone();
+        let expr = match &named_type.kind {
+            Kind::Alias(expr) => expr,
+            _ => unreachable!("only aliases are aliases"),
+        };
+
+        let functions = snake_case(&name);
+        self.imports.insert("Any");
+
+        let type_ = match expr {
+            Expr::Named(target) => self.names[target].clone(),
+            _ => self.type_expr(expr, true),
+        };
+
+        let mut out = comment(&named_type.description, "");
+        out.push_str(&format!("{} = {}\n\n\n", name, type_));
+        out.push_str(&format!(
+            "def {}_from_json(data: Any) -> {}:\n",
+            functions, name
+        ));
+        out.push_str(&format!(
+            "    return {}\n\n\n",
+            self.decode(expr, "data", 0)
+        ));
+        out.push_str(&format!(
+            "def {}_to_json(value: {}) -> Any:\n",
+            functions, name
+        ));
+        out.push_str(&format!("    return {}\n", self.encode(expr, "value", 0)));
+        out
+    }
+
+    /// The Python type for a type expression.
+    ///
+    /// `quoted` is whether references to named types need to be forward
+    /// references, because they're in an alias rather than an annotation.
+    fn type_expr(&mut self, expr: &Expr, quoted: bool) -> String {
+        match expr {
+            Expr::Any => {
+                self.imports.insert("Any");
+                "Any".to_owned()
+            }
+            Expr::Primitive(type_) => primitive(type_).to_owned(),
+            Expr::Named(id) if quoted => string_literal(&self.names[id]),
+            Expr::Named(id) => self.names[id].clone(),
+            Expr::Array(expr) => {
+                self.imports.insert("List");
+                format!("List[{}]", self.type_expr(expr, quoted))
+            }
+            Expr::Map(expr) => {
+                self.imports.insert("Dict");
+                format!("Dict[str, {}]", self.type_expr(expr, quoted))
+            }
+        }
+    }
+
+    /// An expression converting `value`, the output of `json.loads`, into a
+    /// type expression. `depth` is used to name comprehension variables.
+    fn decode(&self, expr: &Expr, value: &str, depth: usize) -> String {
+        match expr {
+            Expr::Any | Expr::Primitive(_) => value.to_owned(),
+            Expr::Named(id) => match &self.ir.get(*id).kind {
+                Kind::Alias(_) => format!("{}_from_json({})", snake_case(&self.names[id]), value),
+                Kind::Enum(_) => format!("{}({})", self.names[id], value),
+                Kind::Struct { .. } | Kind::Union { .. } => {
+                    format!("{}.from_json({})", self.names[id], value)
+                }
+            },
+            Expr::Array(expr) => {
+                let item = format!("v{}", depth);
+                let decoded = self.decode(expr, &item, depth + 1);
+                if decoded == item {
+                    value.to_owned()
+                } else {
+                    format!("[{} for {} in {}]", decoded, item, value)
+                }
+            }
+            Expr::Map(expr) => {
+                let item = format!("v{}", depth);
+                let decoded = self.decode(expr, &item, depth + 1);
+                if decoded == item {
+                    value.to_owned()
+                } else {
+                    format!(
+                        "{{k{}: {} for k{}, {} in {}.items()}}",
+                        depth, decoded, depth, item, value
+                    )
+                }
+            }
+        }
+    }
+
+    /// An expression converting `value`, of a type expression, into something
+    /// `json.dumps` accepts. The reverse of `decode`.
+    fn encode(&self, expr: &Expr, value: &str, depth: usize) -> String {
+        match expr {
+            Expr::Any | Expr::Primitive(_) => value.to_owned(),
+            Expr::Named(id) => match &self.ir.get(*id).kind {
+                Kind::Alias(_) => format!("{}_to_json({})", snake_case(&self.names[id]), value),
+                Kind::Enum(_) => format!("{}.value", value),
+                Kind::Struct { .. } | Kind::Union { .. } => format!("{}.to_json()", value),
+            },
+            Expr::Array(expr) => {
+                let item = format!("v{}", depth);
+                let encoded = self.encode(expr, &item, depth + 1);
+                if encoded == item {
+                    value.to_owned()
+                } else {
+                    format!("[{} for {} in {}]", encoded, item, value)
+                }
+            }
+            Expr::Map(expr) => {
+                let item = format!("v{}", depth);
+                let encoded = self.encode(expr, &item, depth + 1);
+                if encoded == item {
+                    value.to_owned()
+                } else {
+                    format!(
+                        "{{k{}: {} for k{}, {} in {}.items()}}",
+                        depth, encoded, depth, item, value
+                    )
+                }
+            }
+        }
+    }
+}
+
+fn primitive(type_: &Type) -> &'static str {
+    match type_ {
+        Type::Boolean => "bool",
+        Type::Float32 | Type::Float64 => "float",
+        Type::Int8 | Type::Uint8 | Type::Int16 | Type::Uint16 | Type::Int32 | Type::Uint32 => "int",
+        Type::String | Type::Timestamp => "str",
+    }
+}
+
+/// A docstring for a description, if any.
+fn docstring(description: &Option<String>, indent: &str) -> String {
+    match description {
+        Some(description) => {
+            let escaped = description
+                .replace('\\', "\\\\")
+                .replace("\"\"\"", "\\\"\\\"\\\"");
+            let mut lines = escaped.lines();
+            let mut out = format!("{}\"\"\"{}", indent, lines.next().unwrap_or(""));
+
+            let rest: Vec<_> = lines.collect();
+            if rest.is_empty() {
+                out.push_str("\"\"\"\n");
+            } else {
+                out.push('\n');
+                for line in rest {
+                    if line.is_empty() {
+                        out.push('\n');
+                    } else {
+                        out.push_str(&format!("{}{}\n", indent, line));
+                    }
+                }
+                out.push_str(&format!("{}\"\"\"\n", indent));
+            }
+
+            out
+        }
+        None => String::new(),
+    }
+}
+
+/// Comments for a description, if any.
+fn comment(description: &Option<String>, indent: &str) -> String {
+    match description {
+        Some(description) => description
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    format!("{}#\n", indent)
+                } else {
+                    format!("{}# {}\n", indent, line)
+                }
+            })
+            .collect(),
+        None => String::new(),
+    }
+}
+
+fn type_name(name: &Name) -> String {
+    let name = name.pascal_case();
+    if name.starts_with(|c: char| c.is_numeric()) {
+        format!("T{}", name)
+    } else {
+        name
+    }
+}
+
+fn member_name(value: &str) -> String {
+    let name = snake_case(value).to_uppercase();
+    if name.is_empty() || name.starts_with(|c: char| c.is_numeric()) {
+        format!("V_{}", name)
+    } else {
+        name
+    }
+}
+
+fn field_name(property: &str) -> String {
+    let name = snake_case(property);
+    if name.is_empty() {
+        "field".to_owned()
+    } else if name.starts_with(|c: char| c.is_numeric()) {
+        format!("_{}", name)
+    } else if KEYWORDS.contains(&name.as_str()) || METHODS.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_parses, schema, shapes, user};
+    use serde_json::json;
+
+    /// Parses Python from stdin, without running it.
+    const PARSE: &[&str] = &[
+        "python3",
+        "-c",
+        "import ast, sys; ast.parse(sys.stdin.read())",
+    ];
+
+    #[test]
+    fn generate_classes() {
+        let out = Generator::new().generate(&user());
+
+        assert_parses(&out, PARSE);
+        assert_eq!(
+            out,
+            r#"# This file was generated by jddf. Do not edit it by hand.
+
+from __future__ import annotations
+
+from dataclasses import dataclass
+from enum import Enum
+from typing import Any, Dict, List, Optional
+
+
+@dataclass
+class Root:
+    """A user."""
+
+    class_: int
+    created_at: str
+    id: str
+    role: RootRole
+    address: Optional[RootAddress] = None
+    friends: Optional[List[Any]] = None
+    labels: Optional[Dict[str, str]] = None
+
+    @classmethod
+    def from_json(cls, data: Any) -> Root:
+        return cls(
+            class_=data["class"],
+            created_at=data["createdAt"],
+            id=data["id"],
+            role=RootRole(data["role"]),
+            address=RootAddress.from_json(data["address"]) if "address" in data else None,
+            friends=data["friends"] if "friends" in data else None,
+            labels=data["labels"] if "labels" in data else None,
+        )
+
+    def to_json(self) -> Any:
+        data: Dict[str, Any] = {
+            "class": self.class_,
+            "createdAt": self.created_at,
+            "id": self.id,
+            "role": self.role.value,
+        }
+        if self.address is not None:
+            data["address"] = self.address.to_json()
+        if self.friends is not None:
+            data["friends"] = self.friends
+        if self.labels is not None:
+            data["labels"] = self.labels
+        return data
+
+
+class RootRole(Enum):
+    ADMIN = "admin"
+    READ_ONLY = "read-only"
+
+
+@dataclass
+class RootAddress:
+    # The street.
+    street: str
+
+    @classmethod
+    def from_json(cls, data: Any) -> RootAddress:
+        return cls(
+            street=data["street"],
+        )
+
+    def to_json(self) -> Any:
+        data: Dict[str, Any] = {
+            "street": self.street,
+        }
+        return data
+"#
+        );
+    }
+
+    #[test]
+    fn generate_unions() {
+        let out = Generator::new().generate(&shapes());
+
+        assert_parses(&out, PARSE);
+        assert_eq!(
+            out,
+            r#"# This file was generated by jddf. Do not edit it by hand.
+
+from __future__ import annotations
+
+from dataclasses import dataclass
+from typing import Any, Dict, List, Optional
+
+
+@dataclass
+class Node:
+    value: float
+    next: Optional[Node] = None
+
+    @classmethod
+    def from_json(cls, data: Any) -> Node:
+        return cls(
+            value=data["value"],
+            next=Node.from_json(data["next"]) if "next" in data else None,
+        )
+
+    def to_json(self) -> Any:
+        data: Dict[str, Any] = {
+            "value": self.value,
+        }
+        if self.next is not None:
+            data["next"] = self.next.to_json()
+        return data
+
+
+class Shape:
+    @classmethod
+    def from_json(cls, data: Any) -> Shape:
+        variants: Dict[str, Any] = {
+            "empty": ShapeEmpty,
+            "group": ShapeGroup,
+            "list": ShapeList,
+        }
+
+        return variants[data["kind"]].from_json(data)
+
+    def to_json(self) -> Any:
+        raise NotImplementedError
+
+
+@dataclass
+class ShapeEmpty(Shape):
+    @classmethod
+    def from_json(cls, data: Any) -> ShapeEmpty:
+        return cls()
+
+    def to_json(self) -> Any:
+        data: Dict[str, Any] = {
+            "kind": "empty",
+        }
+        return data
+
+
+@dataclass
+class ShapeGroup(Shape):
+    shapes: Shapes
+
+    @classmethod
+    def from_json(cls, data: Any) -> ShapeGroup:
+        return cls(
+            shapes=shapes_from_json(data["shapes"]),
+        )
+
+    def to_json(self) -> Any:
+        data: Dict[str, Any] = {
+            "kind": "group",
+            "shapes": shapes_to_json(self.shapes),
+        }
+        return data
+
+
+@dataclass
+class ShapeList(Shape):
+    head: Node
+
+    @classmethod
+    def from_json(cls, data: Any) -> ShapeList:
+        return cls(
+            head=Node.from_json(data["head"]),
+        )
+
+    def to_json(self) -> Any:
+        data: Dict[str, Any] = {
+            "kind": "list",
+            "head": self.head.to_json(),
+        }
+        return data
+
+
+Root = Shape
+
+
+def root_from_json(data: Any) -> Root:
+    return Shape.from_json(data)
+
+
+def root_to_json(value: Root) -> Any:
+    return value.to_json()
+
+
+Shapes = List["Shape"]
+
+
+def shapes_from_json(data: Any) -> Shapes:
+    return [Shape.from_json(v0) for v0 in data]
+
+
+def shapes_to_json(value: Shapes) -> Any:
+    return [v0.to_json() for v0 in value]
+"#
+        );
+    }
+
+    #[test]
+    fn generate_root_name() {
+        let mut config = Config::new();
+        config.root_name("optional");
+        let out = Generator::new_with_config(config).generate(&schema(
+            json!({ "properties": { "id": { "type": "string" } } }),
+        ));
+
+        assert_parses(&out, PARSE);
+        assert_eq!(
+            out,
+            r#"# This file was generated by jddf. Do not edit it by hand.
+
+from __future__ import annotations
+
+from dataclasses import dataclass
+from typing import Any, Dict
+
+
+@dataclass
+class Optional2:
+    id: str
+
+    @classmethod
+    def from_json(cls, data: Any) -> Optional2:
+        return cls(
+            id=data["id"],
+        )
+
+    def to_json(self) -> Any:
+        data: Dict[str, Any] = {
+            "id": self.id,
+        }
+        return data
+"#
+        );
+    }
+}
@@ -0,0 +1,568 @@
+//! Generate Rust types from schemas.
+//!
+//! The generated code derives `serde::Serialize` and `serde::Deserialize`, so
+//! the crate it ends up in needs to depend on `serde` (with the `derive`
+//! feature) and `serde_json`. Schemas using the `timestamp` type also need
+//! `chrono` with its `serde` feature.
+//!
+//! The most common way to use this module is from a build script, writing the
+//! generated code into `OUT_DIR` and including it from there:
+//!
+//! ```no_run
+//! use jddf::codegen::rust::Generator;
+//! use jddf::{Schema, SerdeSchema};
+//!
+//! fn main() -> Result<(), failure::Error> {
+//!     let file = std::fs::read_to_string("schemas/event.json")?;
+//!     let serde_schema: SerdeSchema = serde_json::from_str(&file)?;
+//!     let schema = Schema::from_serde(serde_schema)?;
+//!
+//!     let out_dir = std::env::var("OUT_DIR")?;
+//!     Generator::new().write(&schema, format!("{}/event.rs", out_dir))?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! And then, in the crate itself:
+//!
+//! ```ignore
+//! include!(concat!(env!("OUT_DIR"), "/event.rs"));
+//! ```
+//!
+//! See the docs for [`Generator`](struct.Generator.html) for how each form is
+//! converted.
+
+use super::ir::{self, Expr, Field, Ir, Kind, Name, NamedType, TypeId};
+use super::{pascal_case, snake_case, unique_name, write_fn};
+use crate::schema::{Schema, Type};
+use std::collections::{HashMap, HashSet};
+
+/// Generates Rust source code from schemas.
+///
+/// The forms are converted as follows:
+///
+/// * The empty form becomes `serde_json::Value`.
+/// * `type` becomes the corresponding primitive. `timestamp` becomes
+///   `chrono::DateTime<chrono::FixedOffset>`.
+/// * `enum` becomes an enum of unit variants, each renamed to its value.
+/// * `elements` becomes a `Vec`.
+/// * `properties` becomes a struct. Optional properties become `Option`
+///   fields, which are omitted when serializing `None`.
+/// * `values` becomes a `HashMap<String, _>`.
+/// * `discriminator` becomes an internally-tagged enum, with one struct
+///   variant per mapping value.
+/// * `ref` refers to the type generated for the definition. If the definition
+///   is recursive, the reference is boxed unless it's already inside a `Vec`
+///   or `HashMap`.
+///
+/// The root schema and each definition become a named type. Structs and enums
+/// nested within them are named after the path leading to them, so a struct
+/// in the `address` property of `User` becomes `UserAddress`; see
+/// [`ir::lower`](../ir/fn.lower.html) for the details. Names which would
+/// shadow a prelude type the generated code uses, such as `String` or
+/// `Option`, get a number appended. A `description` in a schema's `metadata`
+/// becomes a doc comment.
+///
+/// The generated types deserialize every valid instance of the schema, but
+/// they don't check everything a schema does. For instance, they ignore
+/// additional properties rather than rejecting them. Use a
+/// [`Validator`](../../struct.Validator.html) if that matters to you.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate Rust source code for a schema.
+    ///
+    /// If `schema` is a root schema, its definitions are generated as well.
+    pub fn generate(&self, schema: &Schema) -> String {
+        let mut ir_config = ir::Config::new();
+        ir_config
+            .root_name(&self.config.root_name)
+            .reserved(&[RESERVED, PRELUDE].concat());
+        let ir = ir::lower(schema, &ir_config);
+
+        let ctx = Context {
+            config: &self.config,
+            ir: &ir,
+            names: ir
+                .types()
+                .map(|(id, named_type)| (id, type_name(&named_type.name)))
+                .collect(),
+        };
+
+        // Discriminator variants become variants of the discriminator's enum,
+        // rather than types of their own.
+        let variants: HashSet<_> = ir
+            .types()
+            .flat_map(|(_, named_type)| match &named_type.kind {
+                Kind::Union { variants, .. } => variants.iter().map(|(_, id)| *id).collect(),
+                _ => vec![],
+            })
+            .collect();
+
+        let mut out = "// This file was generated by jddf. Do not edit it by hand.\n".to_owned();
+        for (id, named_type) in ir.types() {
+            if !variants.contains(&id) {
+                out.push('\n');
+                out.push_str(&ctx.item(id, named_type));
+            }
+        }
+
+        out
+    }
+
+    write_fn!("Rust source code");
+}
+
+/// Configuration for how code should be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    root_name: String,
+    derives: Vec<String>,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the type generated for the root schema. The default is
+    /// `Root`.
+    ///
+    /// The name is cased like any other type name, and given a number if it
+    /// clashes with a reserved name.
+    pub fn root_name(&mut self, root_name: &str) -> &mut Self {
+        self.root_name = root_name.to_owned();
+        self
+    }
+
+    /// Sets the traits derived by generated types, in addition to
+    /// `serde::Serialize` and `serde::Deserialize`. The default is `Debug`,
+    /// `Clone`, and `PartialEq`.
+    pub fn derives(&mut self, derives: &[&str]) -> &mut Self {
+        self.derives = derives.iter().map(|derive| (*derive).to_owned()).collect();
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            root_name: "Root".to_owned(),
+            derives: vec![
+                "Debug".to_owned(),
+                "Clone".to_owned(),
+                "PartialEq".to_owned(),
+            ],
+        }
+    }
+}
+
+struct Context<'a> {
+    config: &'a Config,
+    ir: &'a Ir,
+    names: HashMap<TypeId, String>,
+}
+
+impl<'a> Context<'a> {
+    /// Generate the item for a named type.
+    ///
+    /// Aliases become a type alias, except for recursive ones, which become a
+    /// newtype instead because type aliases can't be recursive.
+    fn item(&self, id: TypeId, named_type: &NamedType) -> String {
+        let name = &self.names[&id];
+        let mut out = doc(&named_type.description, "");
+
+        match &named_type.kind {
+            Kind::Alias(expr) => {
+                let type_ = self.type_expr(expr, named_type.recursive);
+                if named_type.recursive {
+                    out.push_str(&self.derive_attr());
+                    out.push_str("#[serde(transparent)]\n");
+                    out.push_str(&format!("pub struct {}(pub {});\n", name, type_));
+                } else {
+                    out.push_str(&format!("pub type {} = {};\n", name, type_));
+                }
+            }
+            Kind::Enum(values) => {
+                let mut variants = HashSet::new();
+
+                out.push_str(&self.derive_attr());
+                out.push_str(&format!("pub enum {} {{\n", name));
+                for value in values {
+                    let variant = unique_name(&mut variants, variant_name(value));
+                    out.push_str(&format!("    #[serde(rename = {:?})]\n", value));
+                    out.push_str(&format!("    {},\n", variant));
+                }
+                out.push_str("}\n");
+            }
+            Kind::Struct { fields, .. } => {
+                out.push_str(&self.derive_attr());
+                out.push_str(&format!("pub struct {} {{\n", name));
+                out.push_str(&self.fields(fields, "    ", "pub "));
+                out.push_str("}\n");
+            }
+            Kind::Union { tag, variants } => {
+                let mut variant_names = HashSet::new();
+
+                out.push_str(&self.derive_attr());
+                out.push_str(&format!("#[serde(tag = {:?})]\n", tag));
+                out.push_str(&format!("pub enum {} {{\n", name));
+                for (value, variant_id) in variants {
+                    let variant_type = self.ir.get(*variant_id);
+                    let variant = unique_name(&mut variant_names, variant_name(value));
+
+                    out.push_str(&doc(&variant_type.description, "    "));
+                    out.push_str(&format!("    #[serde(rename = {:?})]\n", value));
+
+                    match &variant_type.kind {
+                        Kind::Struct { fields, .. } if !fields.is_empty() => {
+                            out.push_str(&format!("    {} {{\n", variant));
+                            out.push_str(&self.fields(fields, "        ", ""));
+                            out.push_str("    },\n");
+                        }
+                        _ => out.push_str(&format!("    {},\n", variant)),
+                    }
+                }
+                out.push_str("}\n");
+            }
+        }
+
+        out
+    }
+
+    /// Generate the fields of a struct or struct variant.
+    fn fields(&self, fields: &[Field], indent: &str, visibility: &str) -> String {
+        let mut out = String::new();
+        let mut field_names = HashSet::new();
+
+        for field in fields {
+            let field_name = unique_name(&mut field_names, field_name(&field.name));
+            let type_ = self.type_expr(&field.type_, true);
+
+            let mut attrs = vec![];
+            if field_name.trim_start_matches("r#") != field.name {
+                attrs.push(format!("rename = {:?}", field.name));
+            }
+
+            if field.optional {
+                attrs.push("default".to_owned());
+                attrs.push("skip_serializing_if = \"Option::is_none\"".to_owned());
+            }
+
+            out.push_str(&doc(&field.description, indent));
+            if !attrs.is_empty() {
+                out.push_str(&format!("{}#[serde({})]\n", indent, attrs.join(", ")));
+            }
+
+            if field.optional {
+                out.push_str(&format!(
+                    "{}{}{}: Option<{}>,\n",
+                    indent, visibility, field_name, type_
+                ));
+            } else {
+                out.push_str(&format!(
+                    "{}{}{}: {},\n",
+                    indent, visibility, field_name, type_
+                ));
+            }
+        }
+
+        out
+    }
+
+    /// The Rust type for a type expression.
+    ///
+    /// `direct` is whether the type is stored inline in its parent, in which
+    /// case references to recursive types need to be boxed.
+    fn type_expr(&self, expr: &Expr, direct: bool) -> String {
+        match expr {
+            Expr::Any => "serde_json::Value".to_owned(),
+            Expr::Primitive(type_) => primitive(type_).to_owned(),
+            Expr::Named(id) => {
+                let name = self.names[id].clone();
+                if direct && self.ir.get(*id).recursive {
+                    format!("Box<{}>", name)
+                } else {
+                    name
+                }
+            }
+            Expr::Array(expr) => format!("Vec<{}>", self.type_expr(expr, false)),
+            Expr::Map(expr) => format!(
+                "std::collections::HashMap<String, {}>",
+                self.type_expr(expr, false)
+            ),
+        }
+    }
+
+    fn derive_attr(&self) -> String {
+        let derives: Vec<_> = self
+            .config
+            .derives
+            .iter()
+            .map(String::as_str)
+            .chain(vec!["serde::Serialize", "serde::Deserialize"])
+            .collect();
+
+        format!("#[derive({})]\n", derives.join(", "))
+    }
+}
+
+fn primitive(type_: &Type) -> &'static str {
+    match type_ {
+        Type::Boolean => "bool",
+        Type::Float32 => "f32",
+        Type::Float64 => "f64",
+        Type::Int8 => "i8",
+        Type::Uint8 => "u8",
+        Type::Int16 => "i16",
+        Type::Uint16 => "u16",
+        Type::Int32 => "i32",
+        Type::Uint32 => "u32",
+        Type::String => "String",
+        Type::Timestamp => "chrono::DateTime<chrono::FixedOffset>",
+    }
+}
+
+/// Doc comments for a description, if any.
+fn doc(description: &Option<String>, indent: &str) -> String {
+    match description {
+        Some(description) => description
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    format!("{}///\n", indent)
+                } else {
+                    format!("{}/// {}\n", indent, line)
+                }
+            })
+            .collect(),
+        None => String::new(),
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// Keywords which can't be used even as raw identifiers.
+const RESERVED: &[&str] = &["crate", "self", "Self", "super"];
+
+/// Prelude types which generated code refers to, and so can't be shadowed.
+const PRELUDE: &[&str] = &["Box", "Option", "String", "Vec"];
+
+fn type_name(name: &Name) -> String {
+    let name = name.pascal_case();
+    if name.starts_with(|c: char| c.is_numeric()) {
+        format!("T{}", name)
+    } else {
+        name
+    }
+}
+
+fn variant_name(value: &str) -> String {
+    let name = pascal_case(value);
+    if name.is_empty() || name.starts_with(|c: char| c.is_numeric()) {
+        format!("V{}", name)
+    } else if RESERVED.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+fn field_name(property: &str) -> String {
+    let name = snake_case(property);
+    if name.is_empty() {
+        "field".to_owned()
+    } else if name.starts_with(|c: char| c.is_numeric()) {
+        format!("_{}", name)
+    } else if RESERVED.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else if KEYWORDS.contains(&name.as_str()) {
+        format!("r#{}", name)
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::schema;
+    use serde_json::json;
+
+    #[test]
+    fn generate_forms() {
+        let mut config = Config::new();
+        config.root_name("User").derives(&["Debug"]);
+
+        let out = Generator::new_with_config(config).generate(&schema(json!({
+            "metadata": { "description": "A user." },
+            "properties": {
+                "id": { "type": "string" },
+                "createdAt": { "type": "timestamp" },
+                "role": { "enum": ["admin", "read-only"] },
+                "type": { "type": "uint8" },
+            },
+            "optionalProperties": {
+                "address": {
+                    "properties": {
+                        "street": { "type": "string" },
+                    },
+                },
+                "labels": { "values": { "type": "string" } },
+                "friends": { "elements": {} },
+            },
+        })));
+
+        assert_eq!(
+            out,
+            r#"// This file was generated by jddf. Do not edit it by hand.
+
+/// A user.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct User {
+    #[serde(rename = "createdAt")]
+    pub created_at: chrono::DateTime<chrono::FixedOffset>,
+    pub id: String,
+    pub role: UserRole,
+    pub r#type: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<UserAddress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub friends: Option<Vec<serde_json::Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub labels: Option<std::collections::HashMap<String, String>>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub enum UserRole {
+    #[serde(rename = "admin")]
+    Admin,
+    #[serde(rename = "read-only")]
+    ReadOnly,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct UserAddress {
+    pub street: String,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn generate_definitions() {
+        let out = Generator::new().generate(&schema(json!({
+            "definitions": {
+                "node": {
+                    "properties": {
+                        "value": { "type": "float64" },
+                    },
+                    "optionalProperties": {
+                        "next": { "ref": "node" },
+                        "children": { "elements": { "ref": "node" } },
+                    },
+                },
+                "tree": { "elements": { "ref": "tree" } },
+                "id": { "type": "string" },
+            },
+            "discriminator": {
+                "tag": "kind",
+                "mapping": {
+                    "list": {
+                        "properties": {
+                            "head": { "ref": "node" },
+                            "id": { "ref": "id" },
+                        },
+                    },
+                    "empty": { "properties": {} },
+                },
+            },
+        })));
+
+        assert_eq!(
+            out,
+            r#"// This file was generated by jddf. Do not edit it by hand.
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind")]
+pub enum Root {
+    #[serde(rename = "empty")]
+    Empty,
+    #[serde(rename = "list")]
+    List {
+        head: Box<Node>,
+        id: Id,
+    },
+}
+
+pub type Id = String;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Node {
+    pub value: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<Node>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<Box<Node>>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct Tree(pub Vec<Tree>);
+"#
+        );
+    }
+
+    #[test]
+    fn generate_root_name() {
+        let generate_root = |root_name| {
+            let mut config = Config::new();
+            config.root_name(root_name);
+            Generator::new_with_config(config).generate(&schema(
+                json!({ "properties": { "id": { "type": "string" } } }),
+            ))
+        };
+
+        assert_eq!(
+            generate_root("user account"),
+            r#"// This file was generated by jddf. Do not edit it by hand.
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct UserAccount {
+    pub id: String,
+}
+"#
+        );
+
+        assert_eq!(
+            generate_root("string"),
+            r#"// This file was generated by jddf. Do not edit it by hand.
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct String2 {
+    pub id: String,
+}
+"#
+        );
+    }
+}
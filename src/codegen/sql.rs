@@ -0,0 +1,611 @@
+//! Generate SQL `CREATE TABLE` statements from schemas.
+//!
+//! This is meant for loading instances into a relational database or data
+//! warehouse. Nested objects are flattened into columns of a single table,
+//! and arrays and maps are given tables of their own, which refer back to the
+//! table they came from:
+//!
+//! ```
+//! use jddf::codegen::sql::{Config, Dialect, Generator};
+//! use jddf::{Schema, SerdeSchema};
+//! use serde_json::json;
+//!
+//! let serde_schema: SerdeSchema = serde_json::from_value(json!({
+//!     "properties": {
+//!         "name": { "type": "string" },
+//!         "tags": { "elements": { "type": "string" } },
+//!     },
+//! }))
+//! .unwrap();
+//! let schema = Schema::from_serde(serde_schema).unwrap();
+//!
+//! let mut config = Config::new();
+//! config.table_name("users").dialect(Dialect::Sqlite);
+//! let sql = Generator::new_with_config(config).generate(&schema);
+//! assert!(sql.contains(r#"CREATE TABLE "users_tags" ("#));
+//! ```
+//!
+//! See the docs for [`Generator`](struct.Generator.html) for how each form is
+//! turned into columns and tables.
+
+use super::{unique_name, write_fn};
+use crate::schema::{Form, Schema, Type};
+use indexmap::IndexMap;
+use std::collections::HashSet;
+
+/// Generates SQL `CREATE TABLE` statements from schemas.
+///
+/// The root schema gets a table, named by
+/// [`Config::table_name`](struct.Config.html#method.table_name). Each form
+/// becomes columns of that table as follows:
+///
+/// * `properties` is flattened: each property becomes columns named after
+///   the path leading to them, joined by
+///   [`Config::separator`](struct.Config.html#method.separator). So with the
+///   default separator, the `city` property of the `address` property becomes
+///   the column `address_city`. Optional properties, and everything within
+///   them, are nullable.
+/// * `type` becomes a column of the closest SQL type. Integers get a type
+///   wide enough for their range.
+/// * `enum` becomes a text column, with a `CHECK` constraint that it's one
+///   of the values of the `enum`.
+/// * `discriminator` becomes a text column for the tag, with a `CHECK`
+///   constraint that it's one of the mapping's values. The properties of each
+///   mapping value are flattened after it, prefixed with the tag value, and
+///   are all nullable.
+/// * `elements` and `values` become a table of their own, named after the
+///   table and column they would otherwise be in. Each row refers to the row
+///   it belongs to by `_parent_id`, and records its position by `_index` or
+///   its key by `_key`.
+/// * The empty form, and `ref`s to a definition which is already being
+///   flattened, become a JSON column.
+/// * Any other `ref` is flattened like the definition it refers to.
+///
+/// Every table has a `_id` primary key, which the database fills in for rows
+/// inserted without one. A schema which isn't of the `properties` form, such
+/// as the elements of an array of strings, is stored in a column named
+/// `_value`.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate `CREATE TABLE` statements for a schema.
+    ///
+    /// Tables come before the tables which refer to them, so the statements
+    /// can be run in order.
+    pub fn generate(&self, schema: &Schema) -> String {
+        let empty = IndexMap::new();
+        let mut ctx = Context {
+            config: &self.config,
+            defs: schema.definitions().as_ref().unwrap_or(&empty),
+            table_names: HashSet::new(),
+            tables: vec![],
+        };
+
+        let name = unique_name(&mut ctx.table_names, self.config.table_name.clone());
+        ctx.table(name, None, schema, &mut vec![]);
+
+        let mut out = "-- This file was generated by jddf. Do not edit it by hand.\n".to_owned();
+        for table in &ctx.tables {
+            out.push('\n');
+            out.push_str(&table.to_string());
+        }
+
+        out
+    }
+
+    write_fn!("`CREATE TABLE` statements");
+}
+
+/// Configuration for how SQL should be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    table_name: String,
+    separator: String,
+    dialect: Dialect,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the table for the root schema. The default is `root`.
+    pub fn table_name(&mut self, table_name: &str) -> &mut Self {
+        self.table_name = table_name.to_owned();
+        self
+    }
+
+    /// Sets the separator placed between the parts of flattened column and
+    /// table names. The default is `_`.
+    pub fn separator(&mut self, separator: &str) -> &mut Self {
+        self.separator = separator.to_owned();
+        self
+    }
+
+    /// Sets the dialect of SQL to generate. The default is PostgreSQL.
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.dialect = dialect;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            table_name: "root".to_owned(),
+            separator: "_".to_owned(),
+            dialect: Dialect::Postgres,
+        }
+    }
+}
+
+/// The dialects of SQL which can be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Dialect {
+    /// PostgreSQL.
+    Postgres,
+
+    /// SQLite.
+    Sqlite,
+}
+
+/// The SQL types columns can have.
+#[derive(Debug, Eq, PartialEq, Clone)]
+enum ColumnType {
+    /// A table's own `_id`, which is filled in when rows are inserted.
+    PrimaryKey,
+    /// A reference to another table's `_id`.
+    Id,
+    Json,
+    Text,
+    Type(Type),
+}
+
+impl Dialect {
+    fn sql_type(self, column_type: &ColumnType) -> &'static str {
+        match (self, column_type) {
+            (Dialect::Postgres, ColumnType::PrimaryKey) => {
+                "BIGINT GENERATED BY DEFAULT AS IDENTITY"
+            }
+            (Dialect::Postgres, ColumnType::Id) => "BIGINT",
+            (Dialect::Postgres, ColumnType::Json) => "JSONB",
+            (Dialect::Postgres, ColumnType::Type(type_)) => match type_ {
+                Type::Boolean => "BOOLEAN",
+                Type::Float32 => "REAL",
+                Type::Float64 => "DOUBLE PRECISION",
+                Type::Int8 | Type::Uint8 | Type::Int16 => "SMALLINT",
+                Type::Uint16 | Type::Int32 => "INTEGER",
+                Type::Uint32 => "BIGINT",
+                Type::String => "TEXT",
+                Type::Timestamp => "TIMESTAMPTZ",
+            },
+            // In SQLite, an INTEGER PRIMARY KEY is filled in automatically.
+            (Dialect::Sqlite, ColumnType::PrimaryKey) | (Dialect::Sqlite, ColumnType::Id) => {
+                "INTEGER"
+            }
+            (Dialect::Sqlite, ColumnType::Json) => "TEXT",
+            (Dialect::Sqlite, ColumnType::Type(type_)) => match type_ {
+                Type::Float32 | Type::Float64 => "REAL",
+                Type::String | Type::Timestamp => "TEXT",
+                _ => "INTEGER",
+            },
+            (_, ColumnType::Text) => "TEXT",
+        }
+    }
+}
+
+struct Table {
+    name: String,
+    dialect: Dialect,
+    columns: Vec<Column>,
+    unique: Option<(String, String)>,
+}
+
+struct Column {
+    name: String,
+    column_type: ColumnType,
+    not_null: bool,
+    constraint: Option<String>,
+}
+
+impl std::fmt::Display for Table {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut lines: Vec<_> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let mut line = format!(
+                    "{} {}",
+                    quote(&column.name),
+                    self.dialect.sql_type(&column.column_type)
+                );
+
+                if column.not_null {
+                    line.push_str(" NOT NULL");
+                }
+
+                if let Some(constraint) = &column.constraint {
+                    line.push(' ');
+                    line.push_str(constraint);
+                }
+
+                line
+            })
+            .collect();
+
+        if let Some((a, b)) = &self.unique {
+            lines.push(format!("UNIQUE ({}, {})", quote(a), quote(b)));
+        }
+
+        writeln!(f, "CREATE TABLE {} (", quote(&self.name))?;
+        for (i, line) in lines.iter().enumerate() {
+            let comma = if i + 1 == lines.len() { "" } else { "," };
+            writeln!(f, "    {}{}", line, comma)?;
+        }
+        writeln!(f, ");")
+    }
+}
+
+/// How rows of a child table are told apart within their parent.
+enum ChildKey {
+    Index,
+    Key,
+}
+
+struct Context<'a> {
+    config: &'a Config,
+    defs: &'a IndexMap<String, Schema>,
+    table_names: HashSet<String>,
+    tables: Vec<Table>,
+}
+
+impl<'a> Context<'a> {
+    /// Add a table for `schema`, and any tables it needs in turn. `stack`
+    /// holds the definitions being flattened.
+    fn table(
+        &mut self,
+        name: String,
+        parent: Option<(&str, ChildKey)>,
+        schema: &'a Schema,
+        stack: &mut Vec<&'a str>,
+    ) {
+        let mut column_names = HashSet::new();
+        let mut columns = vec![Column {
+            name: unique_name(&mut column_names, "_id".to_owned()),
+            column_type: ColumnType::PrimaryKey,
+            not_null: false,
+            constraint: Some("PRIMARY KEY".to_owned()),
+        }];
+
+        let mut unique = None;
+        if let Some((parent, key)) = parent {
+            let parent_id = unique_name(&mut column_names, "_parent_id".to_owned());
+            columns.push(Column {
+                name: parent_id.clone(),
+                column_type: ColumnType::Id,
+                not_null: true,
+                constraint: Some(format!("REFERENCES {} ({})", quote(parent), quote("_id"))),
+            });
+
+            let (key, column_type) = match key {
+                ChildKey::Index => ("_index", ColumnType::Type(Type::Int32)),
+                ChildKey::Key => ("_key", ColumnType::Text),
+            };
+
+            let key = unique_name(&mut column_names, key.to_owned());
+            columns.push(Column {
+                name: key.clone(),
+                column_type,
+                not_null: true,
+                constraint: None,
+            });
+
+            unique = Some((parent_id, key));
+        }
+
+        // Reserve this table's place, so that it comes before its children.
+        let index = self.tables.len();
+        self.tables.push(Table {
+            name: name.clone(),
+            dialect: self.config.dialect,
+            columns: vec![],
+            unique,
+        });
+
+        let mut table = Columns {
+            table: &name,
+            names: column_names,
+            columns,
+        };
+
+        self.columns(&mut table, &[], schema, false, stack);
+        self.tables[index].columns = table.columns;
+    }
+
+    /// Add the columns for `schema`, found at `path` within the table.
+    fn columns(
+        &mut self,
+        table: &mut Columns,
+        path: &[&str],
+        schema: &'a Schema,
+        nullable: bool,
+        stack: &mut Vec<&'a str>,
+    ) {
+        match schema.form() {
+            Form::Empty => table.push(self.config, path, ColumnType::Json, nullable, None),
+            Form::Ref(name) => {
+                if stack.contains(&name.as_str()) {
+                    table.push(self.config, path, ColumnType::Json, nullable, None);
+                } else {
+                    stack.push(name);
+                    self.columns(table, path, &self.defs[name], nullable, stack);
+                    stack.pop();
+                }
+            }
+            Form::Type(type_) => table.push(
+                self.config,
+                path,
+                ColumnType::Type(type_.clone()),
+                nullable,
+                None,
+            ),
+            Form::Enum(values) => table.push(
+                self.config,
+                path,
+                ColumnType::Text,
+                nullable,
+                Some(values.iter().collect()),
+            ),
+            Form::Elements(sub_schema) => {
+                self.child_table(table, path, ChildKey::Index, sub_schema, stack)
+            }
+            Form::Values(sub_schema) => {
+                self.child_table(table, path, ChildKey::Key, sub_schema, stack)
+            }
+            Form::Properties {
+                required, optional, ..
+            } => {
+                for (optional, properties) in &[(false, required), (true, optional)] {
+                    for (name, sub_schema) in properties.iter() {
+                        let mut sub_path = path.to_vec();
+                        sub_path.push(name);
+                        self.columns(table, &sub_path, sub_schema, nullable || *optional, stack);
+                    }
+                }
+            }
+            Form::Discriminator(tag, mapping) => {
+                let mut tag_path = path.to_vec();
+                tag_path.push(tag);
+                table.push(
+                    self.config,
+                    &tag_path,
+                    ColumnType::Text,
+                    nullable,
+                    Some(mapping.keys().collect()),
+                );
+
+                for (value, sub_schema) in mapping {
+                    let mut sub_path = path.to_vec();
+                    sub_path.push(value);
+                    self.columns(table, &sub_path, sub_schema, true, stack);
+                }
+            }
+        }
+    }
+
+    fn child_table(
+        &mut self,
+        table: &Columns,
+        path: &[&str],
+        key: ChildKey,
+        schema: &'a Schema,
+        stack: &mut Vec<&'a str>,
+    ) {
+        let mut name = table.table.to_owned();
+        for part in path {
+            name.push_str(&self.config.separator);
+            name.push_str(part);
+        }
+
+        let name = unique_name(&mut self.table_names, name);
+        self.table(name, Some((table.table, key)), schema, stack);
+    }
+}
+
+/// The columns of a table being generated.
+struct Columns<'a> {
+    table: &'a str,
+    names: HashSet<String>,
+    columns: Vec<Column>,
+}
+
+impl<'a> Columns<'a> {
+    fn push(
+        &mut self,
+        config: &Config,
+        path: &[&str],
+        column_type: ColumnType,
+        nullable: bool,
+        values: Option<Vec<&String>>,
+    ) {
+        let name = if path.is_empty() {
+            "_value".to_owned()
+        } else {
+            path.join(&config.separator)
+        };
+
+        let name = unique_name(&mut self.names, name);
+        let constraint = values.map(|values| {
+            let values: Vec<_> = values.into_iter().map(|value| literal(value)).collect();
+            format!("CHECK ({} IN ({}))", quote(&name), values.join(", "))
+        });
+
+        self.columns.push(Column {
+            name,
+            column_type,
+            not_null: !nullable,
+            constraint,
+        });
+    }
+}
+
+/// Quote an identifier.
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Quote a string literal.
+fn literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::schema;
+    use serde_json::json;
+
+    fn users() -> Schema {
+        schema(json!({
+            "definitions": {
+                "node": {
+                    "properties": {
+                        "value": { "type": "int32" },
+                    },
+                    "optionalProperties": {
+                        "next": { "ref": "node" },
+                    },
+                },
+            },
+            "properties": {
+                "id": { "type": "uint32" },
+                "address": {
+                    "properties": {
+                        "city": { "type": "string" },
+                    },
+                    "optionalProperties": {
+                        "zip": { "type": "uint16" },
+                    },
+                },
+                "status": { "enum": ["active", "it's gone"] },
+                "tags": { "elements": { "type": "string" } },
+                "scores": {
+                    "values": {
+                        "properties": {
+                            "at": { "type": "timestamp" },
+                        },
+                    },
+                },
+                "shape": {
+                    "discriminator": {
+                        "tag": "kind",
+                        "mapping": {
+                            "circle": {
+                                "properties": {
+                                    "radius": { "type": "float64" },
+                                },
+                            },
+                        },
+                    },
+                },
+                "list": { "ref": "node" },
+            },
+            "optionalProperties": {
+                "extra": {},
+            },
+        }))
+    }
+
+    #[test]
+    fn generate_postgres() {
+        let mut config = Config::new();
+        config.table_name("users").separator("__");
+
+        assert_eq!(
+            Generator::new_with_config(config).generate(&users()),
+            r#"-- This file was generated by jddf. Do not edit it by hand.
+
+CREATE TABLE "users" (
+    "_id" BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "address__city" TEXT NOT NULL,
+    "address__zip" INTEGER,
+    "id" BIGINT NOT NULL,
+    "list__value" INTEGER NOT NULL,
+    "list__next" JSONB,
+    "shape__kind" TEXT NOT NULL CHECK ("shape__kind" IN ('circle')),
+    "shape__circle__radius" DOUBLE PRECISION,
+    "status" TEXT NOT NULL CHECK ("status" IN ('active', 'it''s gone')),
+    "extra" JSONB
+);
+
+CREATE TABLE "users__scores" (
+    "_id" BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "_parent_id" BIGINT NOT NULL REFERENCES "users" ("_id"),
+    "_key" TEXT NOT NULL,
+    "at" TIMESTAMPTZ NOT NULL,
+    UNIQUE ("_parent_id", "_key")
+);
+
+CREATE TABLE "users__tags" (
+    "_id" BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "_parent_id" BIGINT NOT NULL REFERENCES "users" ("_id"),
+    "_index" INTEGER NOT NULL,
+    "_value" TEXT NOT NULL,
+    UNIQUE ("_parent_id", "_index")
+);
+"#
+        );
+    }
+
+    #[test]
+    fn generate_sqlite() {
+        let mut config = Config::new();
+        config.dialect(Dialect::Sqlite);
+
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let sql = Generator::new_with_config(config).generate(&users());
+        conn.execute_batch(&sql).unwrap();
+
+        conn.execute(
+            r#"INSERT INTO "root" ("_id", "address_city", "id", "list_value", "shape_kind", "status")
+            VALUES (1, 'Paris', 7, 3, 'circle', 'active')"#,
+            [],
+        )
+        .unwrap();
+
+        conn.execute(
+            r#"INSERT INTO "root_tags" ("_parent_id", "_index", "_value") VALUES (1, 0, 'a')"#,
+            [],
+        )
+        .unwrap();
+
+        assert!(conn
+            .execute(
+                r#"INSERT INTO "root" ("_id", "address_city", "id", "list_value", "shape_kind", "status")
+                VALUES (2, 'Paris', 7, 3, 'circle', 'gone')"#,
+                [],
+            )
+            .is_err());
+
+        assert!(conn
+            .execute(
+                r#"INSERT INTO "root_tags" ("_parent_id", "_index", "_value") VALUES (1, 0, 'b')"#,
+                [],
+            )
+            .is_err());
+    }
+}
@@ -0,0 +1,659 @@
+//! Generate TypeScript types, and optionally type guards, from schemas.
+//!
+//! The generated code has no dependencies. Type guards are only generated if
+//! [`Config::guards`](struct.Config.html#method.guards) is enabled; they check
+//! an `unknown` value against the schema at runtime, following the same rules
+//! as a [`Validator`](../../struct.Validator.html).
+//!
+//! See the docs for [`Generator`](struct.Generator.html) for how each form is
+//! converted.
+
+use super::ir::{self, Expr, Field, Ir, Kind, Name, NamedType, TypeId};
+use super::{string_literal, write_fn};
+use crate::schema::{Schema, Type};
+use std::collections::{HashMap, HashSet};
+
+/// Generates TypeScript source code from schemas.
+///
+/// The forms are converted as follows:
+///
+/// * The empty form becomes `unknown`.
+/// * `type` becomes `boolean`, `number`, or `string`. Timestamps are strings.
+/// * `enum` becomes a union of string literals.
+/// * `elements` becomes an array.
+/// * `properties` becomes an interface. Optional properties are optional
+///   members of the interface.
+/// * `values` becomes a `Record<string, _>`.
+/// * `discriminator` becomes a discriminated union of interfaces, one per
+///   mapping value, each with the tag as a string literal.
+/// * `ref` refers to the type generated for the definition.
+///
+/// The root schema and each definition become an exported type. Interfaces
+/// and unions nested within them are named after the path leading to them,
+/// so an interface in the `address` property of `User` becomes
+/// `UserAddress`; see [`ir::lower`](../ir/fn.lower.html) for the details. A
+/// `description` in a schema's `metadata` becomes a doc comment.
+///
+/// If guards are enabled, every exported type `T` other than the interfaces
+/// of a discriminated union also gets a guard named `isT`.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate TypeScript source code for a schema.
+    ///
+    /// If `schema` is a root schema, its definitions are generated as well.
+    pub fn generate(&self, schema: &Schema) -> String {
+        let mut ir_config = ir::Config::new();
+        ir_config
+            .root_name(&self.config.root_name)
+            .reserved(GLOBALS);
+        let ir = ir::lower(schema, &ir_config);
+
+        let mut ctx = Context {
+            ir: &ir,
+            names: ir
+                .types()
+                .map(|(id, named_type)| (id, type_name(&named_type.name)))
+                .collect(),
+            variants: HashMap::new(),
+            helpers: HashSet::new(),
+        };
+
+        for (_, named_type) in ir.types() {
+            if let Kind::Union { tag, variants } = &named_type.kind {
+                for (value, id) in variants {
+                    ctx.variants.insert(*id, (tag.as_str(), value.as_str()));
+                }
+            }
+        }
+
+        let mut out = "// This file was generated by jddf. Do not edit it by hand.\n".to_owned();
+        for (id, named_type) in ir.types() {
+            out.push('\n');
+            out.push_str(&ctx.item(id, named_type));
+        }
+
+        if self.config.guards {
+            for (id, named_type) in ir.types() {
+                if !ctx.variants.contains_key(&id) {
+                    out.push('\n');
+                    out.push_str(&ctx.guard(id, named_type));
+                }
+            }
+
+            for (name, helper) in HELPERS {
+                if ctx.helpers.contains(name) {
+                    out.push('\n');
+                    out.push_str(helper);
+                }
+            }
+        }
+
+        out
+    }
+
+    write_fn!("TypeScript source code");
+}
+
+/// Configuration for how code should be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    root_name: String,
+    guards: bool,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the type generated for the root schema. The default is
+    /// `Root`.
+    ///
+    /// It's cased like any other type name, and given a number if it clashes
+    /// with a global such as `String`.
+    pub fn root_name(&mut self, root_name: &str) -> &mut Self {
+        self.root_name = root_name.to_owned();
+        self
+    }
+
+    /// Sets whether to generate a type guard for each type. The default is to
+    /// not generate them.
+    pub fn guards(&mut self, guards: bool) -> &mut Self {
+        self.guards = guards;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            root_name: "Root".to_owned(),
+            guards: false,
+        }
+    }
+}
+
+/// Global types the generated code may refer to, and so which must not be
+/// shadowed by generated types.
+const GLOBALS: &[&str] = &["Array", "Number", "Object", "Record", "String"];
+
+const HELPERS: &[(&str, &str)] = &[
+    (
+        "_isObject",
+        r#"function _isObject(value: unknown): value is Record<string, unknown> {
+  return typeof value === "object" && value !== null && !Array.isArray(value);
+}
+"#,
+    ),
+    (
+        "_has",
+        r#"function _has(value: Record<string, unknown>, key: string): boolean {
+  return Object.prototype.hasOwnProperty.call(value, key);
+}
+"#,
+    ),
+    (
+        "_isInt",
+        r#"function _isInt(value: unknown, min: number, max: number): boolean {
+  return typeof value === "number" && Number.isInteger(value) && value >= min && value <= max;
+}
+"#,
+    ),
+    (
+        "_isTimestamp",
+        r#"function _isTimestamp(value: unknown): boolean {
+  if (typeof value !== "string") {
+    return false;
+  }
+
+  const match = /^(\d{4})-(\d{2})-(\d{2})[Tt ](\d{2}):(\d{2}):(\d{2})(\.\d+)?([Zz]|[+-](\d{2}):(\d{2}))$/.exec(value);
+  if (match === null) {
+    return false;
+  }
+
+  const [year, month, day, hour, minute, second] = match.slice(1, 7).map(Number);
+  const leap = year % 4 === 0 && (year % 100 !== 0 || year % 400 === 0);
+  const days = [31, leap ? 29 : 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+  const offsetHour = match[9] === undefined ? 0 : Number(match[9]);
+  const offsetMinute = match[10] === undefined ? 0 : Number(match[10]);
+
+  return (
+    month >= 1 && month <= 12 &&
+    day >= 1 && day <= days[month - 1] &&
+    hour <= 23 && minute <= 59 && second <= 60 &&
+    offsetHour <= 23 && offsetMinute <= 59
+  );
+}
+"#,
+    ),
+];
+
+struct Context<'a> {
+    ir: &'a Ir,
+    names: HashMap<TypeId, String>,
+    /// The tag and tag value of each discriminator variant's type.
+    variants: HashMap<TypeId, (&'a str, &'a str)>,
+    helpers: HashSet<&'static str>,
+}
+
+impl<'a> Context<'a> {
+    /// Generate the exported type for a named type.
+    fn item(&self, id: TypeId, named_type: &NamedType) -> String {
+        let name = &self.names[&id];
+        let mut out = doc(&named_type.description, "");
+
+        match &named_type.kind {
+            Kind::Alias(expr) => {
+                out.push_str(&format!(
+                    "export type {} = {};\n",
+                    name,
+                    self.type_expr(expr)
+                ));
+            }
+            Kind::Enum(values) => {
+                let literals: Vec<_> = values.iter().map(|value| string_literal(value)).collect();
+                out.push_str(&format!(
+                    "export type {} = {};\n",
+                    name,
+                    literals.join(" | ")
+                ));
+            }
+            Kind::Struct { fields, .. } => {
+                out.push_str(&format!("export interface {} {{\n", name));
+                if let Some((tag, value)) = self.variants.get(&id) {
+                    out.push_str(&format!(
+                        "  {}: {};\n",
+                        member_name(tag),
+                        string_literal(value)
+                    ));
+                }
+
+                for field in fields {
+                    let mark = if field.optional { "?" } else { "" };
+                    out.push_str(&doc(&field.description, "  "));
+                    out.push_str(&format!(
+                        "  {}{}: {};\n",
+                        member_name(&field.name),
+                        mark,
+                        self.type_expr(&field.type_)
+                    ));
+                }
+                out.push_str("}\n");
+            }
+            Kind::Union { variants, .. } => {
+                if variants.is_empty() {
+                    out.push_str(&format!("export type {} = never;\n", name));
+                } else {
+                    let variants: Vec<_> = variants
+                        .iter()
+                        .map(|(_, id)| self.names[id].as_str())
+                        .collect();
+                    out.push_str(&format!(
+                        "export type {} = {};\n",
+                        name,
+                        variants.join(" | ")
+                    ));
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Generate the type guard for a named type.
+    fn guard(&mut self, id: TypeId, named_type: &NamedType) -> String {
+        let body = match &named_type.kind {
+            Kind::Alias(expr) => format!("  return {};\n", self.check(expr, "value", 0)),
+            Kind::Enum(values) => {
+                let literals: Vec<_> = values.iter().map(|value| string_literal(value)).collect();
+                format!(
+                    "  return typeof value === \"string\" && [{}].includes(value);\n",
+                    literals.join(", ")
+                )
+            }
+            Kind::Struct {
+                fields,
+                allow_additional,
+            } => format!(
+                "  return (\n    {}\n  );\n",
+                self.conditions(fields, *allow_additional, None)
+                    .join(" &&\n    ")
+            ),
+            Kind::Union { tag, variants } => {
+                let mut cases = String::new();
+                for (value, variant_id) in variants {
+                    let conditions = match &self.ir.get(*variant_id).kind {
+                        Kind::Struct {
+                            fields,
+                            allow_additional,
+                        } => self.conditions(fields, *allow_additional, Some(tag)),
+                        _ => vec!["true".to_owned()],
+                    };
+
+                    cases.push_str(&format!(
+                        "    case {}:\n      return (\n        {}\n      );\n",
+                        string_literal(value),
+                        conditions.join(" &&\n        ")
+                    ));
+                }
+
+                self.helpers.insert("_isObject");
+                format!(
+                    "  if (!_isObject(value)) {{\n    return false;\n  }}\n\n  switch (value[{}]) {{\n{}    default:\n      return false;\n  }}\n",
+                    string_literal(tag),
+                    cases
+                )
+            }
+        };
+
+        let name = &self.names[&id];
+        format!(
+            "export function is{}(value: unknown): value is {} {{\n{}}}\n",
+            name, name, body
+        )
+    }
+
+    /// The conditions an object must satisfy to match a struct.
+    ///
+    /// `parent_tag` is the tag of the discriminator the struct is a variant
+    /// of, which is exempt from the check for additional properties.
+    fn conditions(
+        &mut self,
+        fields: &[Field],
+        allow_additional: bool,
+        parent_tag: Option<&str>,
+    ) -> Vec<String> {
+        let mut out = vec![];
+
+        // Variants are only checked once the discriminator has checked for an
+        // object.
+        if parent_tag.is_none() {
+            self.helpers.insert("_isObject");
+            out.push("_isObject(value)".to_owned());
+        }
+
+        for field in fields {
+            let property = string_literal(&field.name);
+            let access = format!("value[{}]", property);
+            let check = self.check(&field.type_, &access, 0);
+
+            if field.optional {
+                if check == "true" {
+                    continue;
+                }
+
+                let check = if check.contains(" && ") {
+                    format!("({})", check)
+                } else {
+                    check
+                };
+
+                self.helpers.insert("_has");
+                out.push(format!("(!_has(value, {}) || {})", property, check));
+            } else {
+                self.helpers.insert("_has");
+                out.push(format!("_has(value, {})", property));
+                if check != "true" {
+                    out.push(check);
+                }
+            }
+        }
+
+        if !allow_additional {
+            let keys: Vec<_> = parent_tag
+                .into_iter()
+                .chain(fields.iter().map(|field| field.name.as_str()))
+                .map(string_literal)
+                .collect();
+
+            out.push(format!(
+                "Object.keys(value).every((key) => [{}].includes(key))",
+                keys.join(", ")
+            ));
+        }
+
+        if out.is_empty() {
+            out.push("true".to_owned());
+        }
+
+        out
+    }
+
+    /// The TypeScript type for a type expression.
+    fn type_expr(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Any => "unknown".to_owned(),
+            Expr::Primitive(Type::Boolean) => "boolean".to_owned(),
+            Expr::Primitive(Type::String) | Expr::Primitive(Type::Timestamp) => "string".to_owned(),
+            Expr::Primitive(_) => "number".to_owned(),
+            Expr::Named(id) => self.names[id].clone(),
+            Expr::Array(expr) => format!("{}[]", self.type_expr(expr)),
+            Expr::Map(expr) => format!("Record<string, {}>", self.type_expr(expr)),
+        }
+    }
+
+    /// A boolean expression checking whether `value` satisfies `expr`.
+    ///
+    /// Named types are checked by calling their guard. `depth` is used to name
+    /// the parameters of nested callbacks.
+    fn check(&mut self, expr: &Expr, value: &str, depth: usize) -> String {
+        match expr {
+            Expr::Any => "true".to_owned(),
+            Expr::Named(id) => format!("is{}({})", self.names[id], value),
+            Expr::Primitive(type_) => {
+                let range = match type_ {
+                    Type::Boolean => return format!("typeof {} === \"boolean\"", value),
+                    Type::Float32 | Type::Float64 => {
+                        return format!("typeof {} === \"number\"", value)
+                    }
+                    Type::String => return format!("typeof {} === \"string\"", value),
+                    Type::Timestamp => {
+                        self.helpers.insert("_isTimestamp");
+                        return format!("_isTimestamp({})", value);
+                    }
+                    Type::Int8 => (-128i64, 127i64),
+                    Type::Uint8 => (0, 255),
+                    Type::Int16 => (-32768, 32767),
+                    Type::Uint16 => (0, 65535),
+                    Type::Int32 => (-2147483648, 2147483647),
+                    Type::Uint32 => (0, 4294967295),
+                };
+
+                self.helpers.insert("_isInt");
+                format!("_isInt({}, {}, {})", value, range.0, range.1)
+            }
+            Expr::Array(expr) => {
+                let param = format!("v{}", depth);
+                format!(
+                    "Array.isArray({}) && {}.every(({}) => {})",
+                    value,
+                    value,
+                    param,
+                    self.check(expr, &param, depth + 1)
+                )
+            }
+            Expr::Map(expr) => {
+                self.helpers.insert("_isObject");
+                let param = format!("v{}", depth);
+                format!(
+                    "_isObject({}) && Object.values({}).every(({}) => {})",
+                    value,
+                    value,
+                    param,
+                    self.check(expr, &param, depth + 1)
+                )
+            }
+        }
+    }
+}
+
+/// Doc comments for a description, if any.
+fn doc(description: &Option<String>, indent: &str) -> String {
+    match description {
+        Some(description) => {
+            let mut out = format!("{}/**\n", indent);
+            for line in description.lines() {
+                if line.is_empty() {
+                    out.push_str(&format!("{} *\n", indent));
+                } else {
+                    out.push_str(&format!("{} * {}\n", indent, line.replace("*/", "*\\/")));
+                }
+            }
+            out.push_str(&format!("{} */\n", indent));
+            out
+        }
+        None => String::new(),
+    }
+}
+
+/// An interface member name, quoted unless it's a valid identifier.
+fn member_name(name: &str) -> String {
+    let mut chars = name.chars();
+    let is_identifier = match chars.next() {
+        Some(first) => {
+            (first.is_ascii_alphabetic() || first == '_' || first == '$')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        }
+        None => false,
+    };
+
+    if is_identifier {
+        name.to_owned()
+    } else {
+        string_literal(name)
+    }
+}
+
+fn type_name(name: &Name) -> String {
+    let name = name.pascal_case();
+    if name.starts_with(|c: char| c.is_numeric()) {
+        format!("T{}", name)
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::schema;
+    use serde_json::json;
+
+    #[test]
+    fn generate_types() {
+        let mut config = Config::new();
+        config.root_name("User");
+
+        let out = Generator::new_with_config(config).generate(&schema(json!({
+            "definitions": {
+                "record": { "values": { "ref": "record" } },
+            },
+            "metadata": { "description": "A user." },
+            "properties": {
+                "id": { "type": "string" },
+                "role": { "enum": ["admin", "read-only"] },
+                "login-count": { "type": "uint32" },
+            },
+            "optionalProperties": {
+                "history": { "elements": { "ref": "record" } },
+                "event": {
+                    "discriminator": {
+                        "tag": "type",
+                        "mapping": {
+                            "created": {
+                                "properties": {
+                                    "at": { "type": "timestamp" },
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+        })));
+
+        assert_eq!(
+            out,
+            r#"// This file was generated by jddf. Do not edit it by hand.
+
+/**
+ * A user.
+ */
+export interface User {
+  id: string;
+  "login-count": number;
+  role: UserRole;
+  event?: UserEvent;
+  history?: Record2[];
+}
+
+export type UserRole = "admin" | "read-only";
+
+export type UserEvent = UserEventCreated;
+
+export interface UserEventCreated {
+  type: "created";
+  at: string;
+}
+
+export type Record2 = Record<string, Record2>;
+"#
+        );
+    }
+
+    #[test]
+    fn generate_guards() {
+        let mut config = Config::new();
+        config.guards(true);
+
+        let out = Generator::new_with_config(config).generate(&schema(json!({
+            "properties": {
+                "count": { "type": "int8" },
+                "tags": { "elements": { "type": "string" } },
+            },
+            "optionalProperties": {
+                "extra": {},
+            },
+            "additionalProperties": true,
+        })));
+
+        assert_eq!(
+            out,
+            r#"// This file was generated by jddf. Do not edit it by hand.
+
+export interface Root {
+  count: number;
+  tags: string[];
+  extra?: unknown;
+}
+
+export function isRoot(value: unknown): value is Root {
+  return (
+    _isObject(value) &&
+    _has(value, "count") &&
+    _isInt(value["count"], -128, 127) &&
+    _has(value, "tags") &&
+    Array.isArray(value["tags"]) && value["tags"].every((v0) => typeof v0 === "string")
+  );
+}
+
+function _isObject(value: unknown): value is Record<string, unknown> {
+  return typeof value === "object" && value !== null && !Array.isArray(value);
+}
+
+function _has(value: Record<string, unknown>, key: string): boolean {
+  return Object.prototype.hasOwnProperty.call(value, key);
+}
+
+function _isInt(value: unknown, min: number, max: number): boolean {
+  return typeof value === "number" && Number.isInteger(value) && value >= min && value <= max;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn generate_root_name() {
+        let generate_root = |root_name| {
+            let mut config = Config::new();
+            config.root_name(root_name);
+            Generator::new_with_config(config).generate(&schema(
+                json!({ "properties": { "id": { "type": "string" } } }),
+            ))
+        };
+
+        assert_eq!(
+            generate_root("user account"),
+            r#"// This file was generated by jddf. Do not edit it by hand.
+
+export interface UserAccount {
+  id: string;
+}
+"#
+        );
+
+        assert_eq!(
+            generate_root("string"),
+            r#"// This file was generated by jddf. Do not edit it by hand.
+
+export interface String2 {
+  id: string;
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,813 @@
+//! Check whether changes to a schema are compatible.
+//!
+//! When a schema is shared between the producers and consumers of some data,
+//! changing the schema may break one or the other. This module compares an old
+//! and a new version of a schema, and classifies each difference between them:
+//!
+//! * A change is *backward-compatible* if data valid against the old schema is
+//!   still valid against the new one. Consumers can upgrade to the new schema
+//!   before producers do.
+//! * A change is *forward-compatible* if data valid against the new schema is
+//!   also valid against the old one. Producers can upgrade to the new schema
+//!   before consumers do.
+//! * A change that is neither is *breaking*.
+//!
+//! See the docs for [`compare`](fn.compare.html) for more.
+
+use crate::schema::{Form, Schema, Type};
+use json_pointer::JsonPointer;
+use std::collections::HashSet;
+
+/// Compare an old and a new version of a root schema.
+///
+/// Both schemas are walked in parallel, following `ref`s into definitions as
+/// they are encountered. Definitions which aren't reachable from the root
+/// aren't compared, as they have no bearing on validation.
+pub fn compare(old: &Schema, new: &Schema) -> Report {
+    let mut cmp = Comparer {
+        old_root: old,
+        new_root: new,
+        tokens: vec![],
+        seen_refs: HashSet::new(),
+        seen_old_refs: HashSet::new(),
+        seen_new_refs: HashSet::new(),
+        changes: vec![],
+    };
+
+    cmp.compare(old, new);
+    Report {
+        changes: cmp.changes,
+    }
+}
+
+/// The result of comparing two versions of a schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    changes: Vec<Change>,
+}
+
+impl Report {
+    /// The differences found between the two schemas.
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// Whether every change is backward-compatible.
+    ///
+    /// If this is true, then every instance which is valid against the old
+    /// schema is valid against the new one.
+    pub fn is_backward_compatible(&self) -> bool {
+        self.changes
+            .iter()
+            .all(|change| change.compatibility().is_backward())
+    }
+
+    /// Whether every change is forward-compatible.
+    ///
+    /// If this is true, then every instance which is valid against the new
+    /// schema is valid against the old one.
+    pub fn is_forward_compatible(&self) -> bool {
+        self.changes
+            .iter()
+            .all(|change| change.compatibility().is_forward())
+    }
+
+    /// Whether every change is both backward- and forward-compatible.
+    pub fn is_fully_compatible(&self) -> bool {
+        self.is_backward_compatible() && self.is_forward_compatible()
+    }
+}
+
+/// A single difference between two versions of a schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    schema_path: JsonPointer<String, Vec<String>>,
+    kind: ChangeKind,
+    compatibility: Compatibility,
+}
+
+impl Change {
+    /// A pointer to the part of the schema which changed.
+    ///
+    /// The pointer is into the new schema, except for things which were
+    /// removed, in which case it points to where they were in the old one. When
+    /// the change is within a definition reached through a `ref`, the pointer
+    /// starts from that definition, such as `/definitions/foo/type`.
+    pub fn schema_path(&self) -> &JsonPointer<String, Vec<String>> {
+        &self.schema_path
+    }
+
+    /// What changed.
+    pub fn kind(&self) -> &ChangeKind {
+        &self.kind
+    }
+
+    /// How the change affects compatibility.
+    pub fn compatibility(&self) -> Compatibility {
+        self.compatibility
+    }
+}
+
+/// The kinds of changes that can be made to a schema.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChangeKind {
+    /// The schema took on a different form altogether, for example going from
+    /// `elements` to `values`.
+    FormChanged,
+
+    /// The `type` of the schema changed.
+    TypeChanged { old: Type, new: Type },
+
+    /// A value was added to an `enum`.
+    EnumValueAdded { value: String },
+
+    /// A value was removed from an `enum`.
+    EnumValueRemoved { value: String },
+
+    /// A property was added.
+    PropertyAdded { property: String, required: bool },
+
+    /// A property was removed.
+    PropertyRemoved { property: String, required: bool },
+
+    /// A required property became optional.
+    PropertyMadeOptional { property: String },
+
+    /// An optional property became required.
+    PropertyMadeRequired { property: String },
+
+    /// `additionalProperties` was changed. `allowed` is its new value.
+    AdditionalPropertiesChanged { allowed: bool },
+
+    /// The `tag` of a discriminator changed.
+    DiscriminatorTagChanged { old: String, new: String },
+
+    /// A value was added to the `mapping` of a discriminator.
+    DiscriminatorMappingAdded { value: String },
+
+    /// A value was removed from the `mapping` of a discriminator.
+    DiscriminatorMappingRemoved { value: String },
+}
+
+/// How a change affects compatibility between two versions of a schema.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Compatibility {
+    /// The change is both backward- and forward-compatible.
+    Full,
+
+    /// Data valid against the old schema remains valid against the new one.
+    Backward,
+
+    /// Data valid against the new schema is valid against the old one.
+    Forward,
+
+    /// The change is neither backward- nor forward-compatible.
+    Breaking,
+}
+
+impl Compatibility {
+    /// Whether this is `Full` or `Backward`.
+    pub fn is_backward(self) -> bool {
+        self == Compatibility::Full || self == Compatibility::Backward
+    }
+
+    /// Whether this is `Full` or `Forward`.
+    pub fn is_forward(self) -> bool {
+        self == Compatibility::Full || self == Compatibility::Forward
+    }
+
+    fn from_parts(backward: bool, forward: bool) -> Self {
+        match (backward, forward) {
+            (true, true) => Compatibility::Full,
+            (true, false) => Compatibility::Backward,
+            (false, true) => Compatibility::Forward,
+            (false, false) => Compatibility::Breaking,
+        }
+    }
+}
+
+struct Comparer<'a> {
+    old_root: &'a Schema,
+    new_root: &'a Schema,
+    tokens: Vec<String>,
+    seen_refs: HashSet<(&'a str, &'a str)>,
+    /// Definitions of the old schema followed while at a schema of the new
+    /// one which isn't a `ref`, and vice versa. Without these, a definition
+    /// which refers to itself would be followed forever.
+    seen_old_refs: HashSet<(&'a str, *const Schema)>,
+    seen_new_refs: HashSet<(*const Schema, &'a str)>,
+    changes: Vec<Change>,
+}
+
+impl<'a> Comparer<'a> {
+    fn compare(&mut self, old: &'a Schema, new: &'a Schema) {
+        match (old.form(), new.form()) {
+            (Form::Ref(old_def), Form::Ref(new_def)) => {
+                if self.seen_refs.insert((old_def, new_def)) {
+                    let tokens = std::mem::replace(
+                        &mut self.tokens,
+                        vec!["definitions".to_owned(), new_def.clone()],
+                    );
+                    self.compare(self.old_def(old_def), self.new_def(new_def));
+                    self.tokens = tokens;
+                }
+            }
+            (Form::Ref(old_def), _) => {
+                if self.seen_old_refs.insert((old_def, new)) {
+                    self.compare(self.old_def(old_def), new);
+                }
+            }
+            (_, Form::Ref(new_def)) => {
+                if self.seen_new_refs.insert((old, new_def)) {
+                    let tokens = std::mem::replace(
+                        &mut self.tokens,
+                        vec!["definitions".to_owned(), new_def.clone()],
+                    );
+                    self.compare(old, self.new_def(new_def));
+                    self.tokens = tokens;
+                }
+            }
+            (Form::Empty, Form::Empty) => {}
+            (_, Form::Empty) => {
+                self.push_change(ChangeKind::FormChanged, Compatibility::Backward);
+            }
+            (Form::Empty, _) => {
+                self.push_change(ChangeKind::FormChanged, Compatibility::Forward);
+            }
+            (Form::Type(old_type), Form::Type(new_type)) => {
+                if old_type != new_type {
+                    let (backward, forward) = compare_types(old_type, new_type);
+                    self.tokens.push("type".to_owned());
+                    self.push_change(
+                        ChangeKind::TypeChanged {
+                            old: old_type.clone(),
+                            new: new_type.clone(),
+                        },
+                        Compatibility::from_parts(backward, forward),
+                    );
+                    self.tokens.pop();
+                }
+            }
+            (Form::Type(Type::String), Form::Enum(_)) => {
+                self.push_change(ChangeKind::FormChanged, Compatibility::Forward);
+            }
+            (Form::Enum(_), Form::Type(Type::String)) => {
+                self.push_change(ChangeKind::FormChanged, Compatibility::Backward);
+            }
+            (Form::Enum(old_values), Form::Enum(new_values)) => {
+                self.tokens.push("enum".to_owned());
+                for value in old_values.difference(new_values) {
+                    self.push_change(
+                        ChangeKind::EnumValueRemoved {
+                            value: value.clone(),
+                        },
+                        Compatibility::Forward,
+                    );
+                }
+
+                for value in new_values.difference(old_values) {
+                    self.push_change(
+                        ChangeKind::EnumValueAdded {
+                            value: value.clone(),
+                        },
+                        Compatibility::Backward,
+                    );
+                }
+                self.tokens.pop();
+            }
+            (Form::Elements(old_sub), Form::Elements(new_sub)) => {
+                self.tokens.push("elements".to_owned());
+                self.compare(old_sub, new_sub);
+                self.tokens.pop();
+            }
+            (
+                Form::Properties {
+                    required: old_required,
+                    optional: old_optional,
+                    allow_additional: old_additional,
+                    ..
+                },
+                Form::Properties {
+                    required: new_required,
+                    optional: new_optional,
+                    allow_additional: new_additional,
+                    ..
+                },
+            ) => {
+                self.compare_properties(
+                    (old_required, old_optional, *old_additional),
+                    (new_required, new_optional, *new_additional),
+                    None,
+                );
+            }
+            (Form::Values(old_sub), Form::Values(new_sub)) => {
+                self.tokens.push("values".to_owned());
+                self.compare(old_sub, new_sub);
+                self.tokens.pop();
+            }
+            (
+                Form::Discriminator(old_tag, old_mapping),
+                Form::Discriminator(new_tag, new_mapping),
+            ) => {
+                self.tokens.push("discriminator".to_owned());
+
+                if old_tag != new_tag {
+                    self.tokens.push("tag".to_owned());
+                    self.push_change(
+                        ChangeKind::DiscriminatorTagChanged {
+                            old: old_tag.clone(),
+                            new: new_tag.clone(),
+                        },
+                        Compatibility::Breaking,
+                    );
+                    self.tokens.pop();
+                }
+
+                self.tokens.push("mapping".to_owned());
+                for (value, old_sub) in old_mapping {
+                    self.tokens.push(value.clone());
+                    match new_mapping.get(value) {
+                        Some(new_sub) => self.compare_mapping(old_sub, new_sub, old_tag, new_tag),
+                        None => self.push_change(
+                            ChangeKind::DiscriminatorMappingRemoved {
+                                value: value.clone(),
+                            },
+                            Compatibility::Forward,
+                        ),
+                    }
+                    self.tokens.pop();
+                }
+
+                for value in new_mapping.keys() {
+                    if !old_mapping.contains_key(value) {
+                        self.tokens.push(value.clone());
+                        self.push_change(
+                            ChangeKind::DiscriminatorMappingAdded {
+                                value: value.clone(),
+                            },
+                            Compatibility::Backward,
+                        );
+                        self.tokens.pop();
+                    }
+                }
+                self.tokens.pop();
+
+                self.tokens.pop();
+            }
+            _ => {
+                self.push_change(ChangeKind::FormChanged, Compatibility::Breaking);
+            }
+        }
+    }
+
+    fn compare_mapping(&mut self, old: &'a Schema, new: &'a Schema, old_tag: &str, new_tag: &str) {
+        // Discriminator mappings are always of the properties form, and the tag
+        // property is implicitly allowed by each of them. If the tag changed,
+        // that's already been reported, so the old tag is simply ignored here.
+        match (old.form(), new.form()) {
+            (
+                Form::Properties {
+                    required: old_required,
+                    optional: old_optional,
+                    allow_additional: old_additional,
+                    ..
+                },
+                Form::Properties {
+                    required: new_required,
+                    optional: new_optional,
+                    allow_additional: new_additional,
+                    ..
+                },
+            ) => {
+                let tags = if old_tag == new_tag {
+                    None
+                } else {
+                    Some((old_tag, new_tag))
+                };
+
+                self.compare_properties(
+                    (old_required, old_optional, *old_additional),
+                    (new_required, new_optional, *new_additional),
+                    tags,
+                );
+            }
+            _ => self.compare(old, new),
+        }
+    }
+
+    fn compare_properties(
+        &mut self,
+        old: (&'a PropertyMap, &'a PropertyMap, bool),
+        new: (&'a PropertyMap, &'a PropertyMap, bool),
+        tags: Option<(&str, &str)>,
+    ) {
+        let (old_required, old_optional, old_additional) = old;
+        let (new_required, new_optional, new_additional) = new;
+
+        let is_tag = |name: &str| {
+            tags.map(|(old_tag, new_tag)| name == old_tag || name == new_tag)
+                .unwrap_or(false)
+        };
+
+        for (name, old_sub) in old_required.iter().chain(old_optional) {
+            if is_tag(name) {
+                continue;
+            }
+
+            let was_required = old_required.contains_key(name);
+            let (keyword, new_sub) = match (new_required.get(name), new_optional.get(name)) {
+                (Some(new_sub), _) => ("properties", Some(new_sub)),
+                (_, Some(new_sub)) => ("optionalProperties", Some(new_sub)),
+                _ => {
+                    if was_required {
+                        ("properties", None)
+                    } else {
+                        ("optionalProperties", None)
+                    }
+                }
+            };
+
+            self.tokens.push(keyword.to_owned());
+            self.tokens.push(name.clone());
+
+            match new_sub {
+                Some(new_sub) => {
+                    let is_required = new_required.contains_key(name);
+                    if was_required && !is_required {
+                        self.push_change(
+                            ChangeKind::PropertyMadeOptional {
+                                property: name.clone(),
+                            },
+                            Compatibility::Backward,
+                        );
+                    } else if !was_required && is_required {
+                        self.push_change(
+                            ChangeKind::PropertyMadeRequired {
+                                property: name.clone(),
+                            },
+                            Compatibility::Forward,
+                        );
+                    }
+
+                    self.compare(old_sub, new_sub);
+                }
+                None => {
+                    // Old data with this property is accepted by the new
+                    // schema only if it allows additional properties. New
+                    // data omits the property, which the old schema accepts
+                    // only if it was optional and new data can't slip it back
+                    // in as an additional property.
+                    self.push_change(
+                        ChangeKind::PropertyRemoved {
+                            property: name.clone(),
+                            required: was_required,
+                        },
+                        Compatibility::from_parts(new_additional, !was_required && !new_additional),
+                    );
+                }
+            }
+
+            self.tokens.pop();
+            self.tokens.pop();
+        }
+
+        for (name, _) in new_required.iter().chain(new_optional) {
+            if is_tag(name) || old_required.contains_key(name) || old_optional.contains_key(name) {
+                continue;
+            }
+
+            let is_required = new_required.contains_key(name);
+            let keyword = if is_required {
+                "properties"
+            } else {
+                "optionalProperties"
+            };
+
+            self.tokens.push(keyword.to_owned());
+            self.tokens.push(name.clone());
+
+            // Mirror image of removing a property: old data may only contain
+            // the property if the old schema allowed additional properties,
+            // and new data with the property is accepted by the old schema only
+            // if it did.
+            self.push_change(
+                ChangeKind::PropertyAdded {
+                    property: name.clone(),
+                    required: is_required,
+                },
+                Compatibility::from_parts(!is_required && !old_additional, old_additional),
+            );
+
+            self.tokens.pop();
+            self.tokens.pop();
+        }
+
+        if old_additional != new_additional {
+            self.tokens.push("additionalProperties".to_owned());
+            self.push_change(
+                ChangeKind::AdditionalPropertiesChanged {
+                    allowed: new_additional,
+                },
+                Compatibility::from_parts(new_additional, old_additional),
+            );
+            self.tokens.pop();
+        }
+    }
+
+    fn old_def(&self, name: &str) -> &'a Schema {
+        &self.old_root.definitions().as_ref().unwrap()[name]
+    }
+
+    fn new_def(&self, name: &str) -> &'a Schema {
+        &self.new_root.definitions().as_ref().unwrap()[name]
+    }
+
+    fn push_change(&mut self, kind: ChangeKind, compatibility: Compatibility) {
+        self.changes.push(Change {
+            schema_path: JsonPointer::new(self.tokens.clone()),
+            kind,
+            compatibility,
+        });
+    }
+}
+
+type PropertyMap = indexmap::IndexMap<String, Schema>;
+
+/// Whether changing `old` to `new` is (backward, forward) compatible.
+fn compare_types(old: &Type, new: &Type) -> (bool, bool) {
+    (new.includes(old), old.includes(new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::schema;
+    use serde_json::{json, Value};
+
+    fn changes(old: Value, new: Value) -> Vec<(String, ChangeKind, Compatibility)> {
+        let old = schema(old);
+        let new = schema(new);
+
+        compare(&old, &new)
+            .changes()
+            .iter()
+            .map(|c| {
+                (
+                    c.schema_path().to_string(),
+                    c.kind().clone(),
+                    c.compatibility(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identical() {
+        let schema = json!({
+            "definitions": { "a": { "elements": { "ref": "a" } } },
+            "properties": { "a": { "ref": "a" }, "b": { "type": "string" } },
+        });
+
+        assert_eq!(changes(schema.clone(), schema), vec![]);
+    }
+
+    #[test]
+    fn types() {
+        let cases = vec![
+            (Type::Uint8, Type::Uint16, Compatibility::Backward),
+            (Type::Int16, Type::Int8, Compatibility::Forward),
+            (Type::Int8, Type::Uint8, Compatibility::Breaking),
+            (Type::Uint32, Type::Float64, Compatibility::Backward),
+            (Type::Float64, Type::Int32, Compatibility::Forward),
+            (Type::Float32, Type::Float64, Compatibility::Full),
+            (Type::String, Type::Timestamp, Compatibility::Forward),
+            (Type::Timestamp, Type::String, Compatibility::Backward),
+            (Type::Boolean, Type::String, Compatibility::Breaking),
+        ];
+
+        for (old, new, compatibility) in cases {
+            let old_schema = Schema::from_parts(
+                Some(Default::default()),
+                Box::new(Form::Type(old.clone())),
+                Default::default(),
+            );
+            let new_schema = Schema::from_parts(
+                Some(Default::default()),
+                Box::new(Form::Type(new.clone())),
+                Default::default(),
+            );
+
+            let report = compare(&old_schema, &new_schema);
+            assert_eq!(report.changes().len(), 1);
+            assert_eq!(report.changes()[0].schema_path().to_string(), "/type");
+            assert_eq!(
+                report.changes()[0].kind(),
+                &ChangeKind::TypeChanged { old, new }
+            );
+            assert_eq!(report.changes()[0].compatibility(), compatibility);
+        }
+    }
+
+    #[test]
+    fn enums() {
+        assert_eq!(
+            changes(json!({ "enum": ["A", "B"] }), json!({ "enum": ["B", "C"] })),
+            vec![
+                (
+                    "/enum".to_owned(),
+                    ChangeKind::EnumValueRemoved {
+                        value: "A".to_owned()
+                    },
+                    Compatibility::Forward
+                ),
+                (
+                    "/enum".to_owned(),
+                    ChangeKind::EnumValueAdded {
+                        value: "C".to_owned()
+                    },
+                    Compatibility::Backward
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn properties() {
+        assert_eq!(
+            changes(
+                json!({
+                    "properties": { "a": {}, "b": {}, "c": {} },
+                    "optionalProperties": { "d": {} },
+                }),
+                json!({
+                    "properties": { "a": {}, "d": {}, "e": {} },
+                    "optionalProperties": { "b": {}, "f": {} },
+                }),
+            ),
+            vec![
+                (
+                    "/optionalProperties/b".to_owned(),
+                    ChangeKind::PropertyMadeOptional {
+                        property: "b".to_owned()
+                    },
+                    Compatibility::Backward
+                ),
+                (
+                    "/properties/c".to_owned(),
+                    ChangeKind::PropertyRemoved {
+                        property: "c".to_owned(),
+                        required: true,
+                    },
+                    Compatibility::Breaking
+                ),
+                (
+                    "/properties/d".to_owned(),
+                    ChangeKind::PropertyMadeRequired {
+                        property: "d".to_owned()
+                    },
+                    Compatibility::Forward
+                ),
+                (
+                    "/properties/e".to_owned(),
+                    ChangeKind::PropertyAdded {
+                        property: "e".to_owned(),
+                        required: true,
+                    },
+                    Compatibility::Breaking
+                ),
+                (
+                    "/optionalProperties/f".to_owned(),
+                    ChangeKind::PropertyAdded {
+                        property: "f".to_owned(),
+                        required: false,
+                    },
+                    Compatibility::Backward
+                ),
+            ]
+        );
+
+        assert_eq!(
+            changes(
+                json!({ "properties": { "a": {} }, "additionalProperties": true }),
+                json!({ "properties": { "b": {} } }),
+            ),
+            vec![
+                (
+                    "/properties/a".to_owned(),
+                    ChangeKind::PropertyRemoved {
+                        property: "a".to_owned(),
+                        required: true,
+                    },
+                    Compatibility::Breaking
+                ),
+                (
+                    "/properties/b".to_owned(),
+                    ChangeKind::PropertyAdded {
+                        property: "b".to_owned(),
+                        required: true,
+                    },
+                    Compatibility::Forward
+                ),
+                (
+                    "/additionalProperties".to_owned(),
+                    ChangeKind::AdditionalPropertiesChanged { allowed: false },
+                    Compatibility::Forward
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn discriminators() {
+        assert_eq!(
+            changes(
+                json!({
+                    "discriminator": {
+                        "tag": "kind",
+                        "mapping": {
+                            "a": { "properties": { "x": { "type": "uint8" } } },
+                            "b": { "properties": {} },
+                        },
+                    },
+                }),
+                json!({
+                    "discriminator": {
+                        "tag": "kind",
+                        "mapping": {
+                            "a": { "properties": { "x": { "type": "uint16" } } },
+                            "c": { "properties": {} },
+                        },
+                    },
+                }),
+            ),
+            vec![
+                (
+                    "/discriminator/mapping/a/properties/x/type".to_owned(),
+                    ChangeKind::TypeChanged {
+                        old: Type::Uint8,
+                        new: Type::Uint16,
+                    },
+                    Compatibility::Backward
+                ),
+                (
+                    "/discriminator/mapping/b".to_owned(),
+                    ChangeKind::DiscriminatorMappingRemoved {
+                        value: "b".to_owned()
+                    },
+                    Compatibility::Forward
+                ),
+                (
+                    "/discriminator/mapping/c".to_owned(),
+                    ChangeKind::DiscriminatorMappingAdded {
+                        value: "c".to_owned()
+                    },
+                    Compatibility::Backward
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn refs() {
+        assert_eq!(
+            changes(
+                json!({
+                    "definitions": { "a": { "properties": { "next": { "ref": "a" } } } },
+                    "ref": "a",
+                }),
+                json!({
+                    "definitions": {
+                        "b": {
+                            "properties": { "next": { "ref": "b" } },
+                            "additionalProperties": true,
+                        },
+                    },
+                    "ref": "b",
+                }),
+            ),
+            vec![(
+                "/definitions/b/additionalProperties".to_owned(),
+                ChangeKind::AdditionalPropertiesChanged { allowed: true },
+                Compatibility::Backward
+            )]
+        );
+
+        assert_eq!(
+            changes(
+                json!({ "elements": { "type": "string" } }),
+                json!({ "values": { "type": "string" } }),
+            ),
+            vec![(
+                "".to_owned(),
+                ChangeKind::FormChanged,
+                Compatibility::Breaking
+            )]
+        );
+
+        // Definitions which refer to themselves, compared against schemas
+        // which aren't refs, once in each direction.
+        let cyclic = json!({ "definitions": { "a": { "ref": "a" } }, "ref": "a" });
+        assert_eq!(changes(cyclic.clone(), json!({ "type": "string" })), vec![]);
+        assert_eq!(changes(json!({ "type": "string" }), cyclic), vec![]);
+    }
+}
@@ -0,0 +1,409 @@
+//! Structural differences between schemas.
+//!
+//! Unlike [`compat`](../compat/index.html), which judges whether a change to a
+//! schema is safe, this module simply lists what changed, as a sequence of
+//! additions, removals, and changes keyed by their path within the schema. It's
+//! meant for tools that display changes to people, such as code review bots.
+//!
+//! See the docs for [`diff`](fn.diff.html) for more.
+
+use crate::schema::{Form, Schema};
+use indexmap::IndexMap;
+use json_pointer::JsonPointer;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Compute the structural differences between two schemas.
+///
+/// Definitions are matched up by name, and `ref`s are compared by the name of
+/// the definition they refer to, rather than by what that definition contains;
+/// changes to a definition are reported once, under `/definitions`, rather
+/// than at every place it is referred to.
+///
+/// Within `properties` and `optionalProperties`, properties are matched up by
+/// name. A property which moved from one to the other is reported as removed
+/// from one and added to the other. Non-keyword data, such as `metadata`, is
+/// compared by value, one key at a time.
+pub fn diff(old: &Schema, new: &Schema) -> Diff {
+    let mut differ = Differ {
+        tokens: vec![],
+        entries: vec![],
+    };
+
+    let empty = IndexMap::new();
+    let old_defs = old.definitions().as_ref().unwrap_or(&empty);
+    let new_defs = new.definitions().as_ref().unwrap_or(&empty);
+    differ.diff_maps("definitions", old_defs, new_defs);
+    differ.diff(old, new);
+
+    Diff {
+        entries: differ.entries,
+    }
+}
+
+/// The structural differences between two schemas.
+///
+/// A `Diff` serializes as a JSON array of its entries.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Diff {
+    entries: Vec<Entry>,
+}
+
+impl Diff {
+    /// The differences between the two schemas, in the order they appear in
+    /// the schemas.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Whether the two schemas are structurally identical.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// A single structural difference between two schemas.
+///
+/// When serialized, `old` and `new` are omitted if they are `None`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Entry {
+    op: Op,
+    path: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old: Option<Value>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new: Option<Value>,
+}
+
+impl Entry {
+    /// The kind of difference.
+    pub fn op(&self) -> Op {
+        self.op
+    }
+
+    /// A JSON Pointer to the part of the schema which differs.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The old value at `path`. This is `None` for additions.
+    pub fn old_value(&self) -> Option<&Value> {
+        self.old.as_ref()
+    }
+
+    /// The new value at `path`. This is `None` for removals.
+    pub fn new_value(&self) -> Option<&Value> {
+        self.new.as_ref()
+    }
+}
+
+/// The kinds of structural differences between schemas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Op {
+    /// Something is present in the new schema, but not the old one.
+    Add,
+
+    /// Something is present in the old schema, but not the new one.
+    Remove,
+
+    /// Something is present in both schemas, but with different values.
+    Change,
+}
+
+struct Differ {
+    tokens: Vec<String>,
+    entries: Vec<Entry>,
+}
+
+impl Differ {
+    fn diff(&mut self, old: &Schema, new: &Schema) {
+        match (old.form(), new.form()) {
+            (Form::Empty, Form::Empty) => {}
+            (Form::Ref(old_def), Form::Ref(new_def)) => {
+                if old_def != new_def {
+                    self.tokens.push("ref".to_owned());
+                    self.push(Op::Change, Some(old_def.clone()), Some(new_def.clone()));
+                    self.tokens.pop();
+                }
+            }
+            (Form::Type(old_type), Form::Type(new_type)) => {
+                if old_type != new_type {
+                    self.tokens.push("type".to_owned());
+                    self.push(Op::Change, Some(type_value(old)), Some(type_value(new)));
+                    self.tokens.pop();
+                }
+            }
+            (Form::Enum(old_values), Form::Enum(new_values)) => {
+                if old_values != new_values {
+                    self.tokens.push("enum".to_owned());
+                    self.push(Op::Change, Some(old_values), Some(new_values));
+                    self.tokens.pop();
+                }
+            }
+            (Form::Elements(old_sub), Form::Elements(new_sub)) => {
+                self.tokens.push("elements".to_owned());
+                self.diff(old_sub, new_sub);
+                self.tokens.pop();
+            }
+            (
+                Form::Properties {
+                    required: old_required,
+                    optional: old_optional,
+                    allow_additional: old_additional,
+                    ..
+                },
+                Form::Properties {
+                    required: new_required,
+                    optional: new_optional,
+                    allow_additional: new_additional,
+                    ..
+                },
+            ) => {
+                self.diff_maps("properties", old_required, new_required);
+                self.diff_maps("optionalProperties", old_optional, new_optional);
+
+                if old_additional != new_additional {
+                    self.tokens.push("additionalProperties".to_owned());
+                    self.push(Op::Change, Some(old_additional), Some(new_additional));
+                    self.tokens.pop();
+                }
+            }
+            (Form::Values(old_sub), Form::Values(new_sub)) => {
+                self.tokens.push("values".to_owned());
+                self.diff(old_sub, new_sub);
+                self.tokens.pop();
+            }
+            (
+                Form::Discriminator(old_tag, old_mapping),
+                Form::Discriminator(new_tag, new_mapping),
+            ) => {
+                self.tokens.push("discriminator".to_owned());
+
+                if old_tag != new_tag {
+                    self.tokens.push("tag".to_owned());
+                    self.push(Op::Change, Some(old_tag), Some(new_tag));
+                    self.tokens.pop();
+                }
+
+                self.diff_maps("mapping", old_mapping, new_mapping);
+                self.tokens.pop();
+            }
+            _ => {
+                self.push(Op::Change, Some(schema_value(old)), Some(schema_value(new)));
+                return;
+            }
+        }
+
+        for (key, old_value) in old.extra() {
+            self.tokens.push(key.clone());
+            match new.extra().get(key) {
+                Some(new_value) => {
+                    if old_value != new_value {
+                        self.push(Op::Change, Some(old_value), Some(new_value));
+                    }
+                }
+                None => self.push::<_, Value>(Op::Remove, Some(old_value), None),
+            }
+            self.tokens.pop();
+        }
+
+        for (key, new_value) in new.extra() {
+            if !old.extra().contains_key(key) {
+                self.tokens.push(key.clone());
+                self.push::<Value, _>(Op::Add, None, Some(new_value));
+                self.tokens.pop();
+            }
+        }
+    }
+
+    fn diff_maps(
+        &mut self,
+        keyword: &str,
+        old: &IndexMap<String, Schema>,
+        new: &IndexMap<String, Schema>,
+    ) {
+        self.tokens.push(keyword.to_owned());
+
+        for (name, old_sub) in old {
+            self.tokens.push(name.clone());
+            match new.get(name) {
+                Some(new_sub) => self.diff(old_sub, new_sub),
+                None => self.push::<_, Value>(Op::Remove, Some(schema_value(old_sub)), None),
+            }
+            self.tokens.pop();
+        }
+
+        for (name, new_sub) in new {
+            if !old.contains_key(name) {
+                self.tokens.push(name.clone());
+                self.push::<Value, _>(Op::Add, None, Some(schema_value(new_sub)));
+                self.tokens.pop();
+            }
+        }
+
+        self.tokens.pop();
+    }
+
+    fn push<O: Serialize, N: Serialize>(&mut self, op: Op, old: Option<O>, new: Option<N>) {
+        self.entries.push(Entry {
+            op,
+            path: JsonPointer::new(self.tokens.clone()).to_string(),
+            old: old.map(to_value),
+            new: new.map(to_value),
+        });
+    }
+}
+
+fn schema_value(schema: &Schema) -> Value {
+    // Definitions are diffed on their own, so they're left out here.
+    let mut serde = schema.clone().into_serde();
+    serde.defs = None;
+    to_value(serde)
+}
+
+fn type_value(schema: &Schema) -> Value {
+    to_value(schema.clone().into_serde().typ)
+}
+
+fn to_value<T: Serialize>(value: T) -> Value {
+    serde_json::to_value(value).expect("unreachable: schemas always serialize to JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::schema;
+    use serde_json::json;
+
+    fn diff_json(old: Value, new: Value) -> Value {
+        let old = schema(old);
+        let new = schema(new);
+        serde_json::to_value(diff(&old, &new)).unwrap()
+    }
+
+    #[test]
+    fn identical() {
+        let schema = json!({
+            "definitions": { "a": { "type": "string" } },
+            "properties": { "a": { "ref": "a" } },
+            "metadata": { "description": "foo" },
+        });
+
+        assert_eq!(diff_json(schema.clone(), schema), json!([]));
+    }
+
+    #[test]
+    fn definitions_and_refs() {
+        assert_eq!(
+            diff_json(
+                json!({
+                    "definitions": {
+                        "a": { "type": "string" },
+                        "b": { "type": "string" },
+                    },
+                    "ref": "a",
+                }),
+                json!({
+                    "definitions": {
+                        "a": { "type": "timestamp" },
+                        "c": { "type": "string" },
+                    },
+                    "ref": "c",
+                }),
+            ),
+            json!([
+                { "op": "change", "path": "/definitions/a/type", "old": "string", "new": "timestamp" },
+                { "op": "remove", "path": "/definitions/b", "old": { "type": "string" } },
+                { "op": "add", "path": "/definitions/c", "new": { "type": "string" } },
+                { "op": "change", "path": "/ref", "old": "a", "new": "c" },
+            ])
+        );
+    }
+
+    #[test]
+    fn properties() {
+        assert_eq!(
+            diff_json(
+                json!({
+                    "properties": {
+                        "a": { "enum": ["X", "Y"] },
+                        "b": {},
+                    },
+                    "optionalProperties": {
+                        "c/d": { "elements": { "type": "uint8" } },
+                    },
+                }),
+                json!({
+                    "properties": {
+                        "a": { "enum": ["Y", "X"] },
+                    },
+                    "optionalProperties": {
+                        "b": {},
+                        "c/d": { "elements": { "type": "uint16" } },
+                    },
+                    "additionalProperties": true,
+                }),
+            ),
+            json!([
+                { "op": "remove", "path": "/properties/b", "old": {} },
+                { "op": "change", "path": "/optionalProperties/c~1d/elements/type", "old": "uint8", "new": "uint16" },
+                { "op": "add", "path": "/optionalProperties/b", "new": {} },
+                { "op": "change", "path": "/additionalProperties", "old": false, "new": true },
+            ])
+        );
+    }
+
+    #[test]
+    fn forms_and_extra() {
+        assert_eq!(
+            diff_json(
+                json!({
+                    "elements": { "type": "string", "metadata": { "a": 1 } },
+                    "x": true,
+                }),
+                json!({
+                    "values": { "type": "string" },
+                    "y": true,
+                }),
+            ),
+            json!([
+                {
+                    "op": "change",
+                    "path": "",
+                    "old": { "elements": { "type": "string", "metadata": { "a": 1 } }, "x": true },
+                    "new": { "values": { "type": "string" }, "y": true },
+                },
+            ])
+        );
+
+        assert_eq!(
+            diff_json(
+                json!({
+                    "discriminator": {
+                        "tag": "kind",
+                        "mapping": { "a": { "properties": {} } },
+                    },
+                    "metadata": { "description": "old" },
+                    "x": true,
+                }),
+                json!({
+                    "discriminator": {
+                        "tag": "type",
+                        "mapping": { "a": { "properties": {}, "metadata": {} } },
+                    },
+                    "metadata": { "description": "new" },
+                }),
+            ),
+            json!([
+                { "op": "change", "path": "/discriminator/tag", "old": "kind", "new": "type" },
+                { "op": "add", "path": "/discriminator/mapping/a/metadata", "new": {} },
+                { "op": "change", "path": "/metadata", "old": { "description": "old" }, "new": { "description": "new" } },
+                { "op": "remove", "path": "/x", "old": true },
+            ])
+        );
+    }
+}
@@ -31,6 +31,93 @@ pub enum JddfError {
     #[fail(display = "no such definition: {}", definition)]
     NoSuchDefinition { definition: String },
 
+    /// A schema refers to a document which could not be found.
+    ///
+    /// When schemas are split across several documents, a `ref` of the form
+    /// `document#name` refers to the definition `name` in another document.
+    /// If that document can't be loaded, this error is returned.
+    #[fail(display = "no such document: {}", document)]
+    NoSuchDocument { document: String },
+
+    /// A definition's name could be mistaken for a reference to another
+    /// document.
+    ///
+    /// When schemas are split across several documents, any `ref` containing a
+    /// `#` refers to a definition in another document. A definition whose own
+    /// name contains a `#` therefore can't be referred to, and is rejected.
+    #[fail(display = "ambiguous definition: {}", definition)]
+    AmbiguousDefinition { definition: String },
+
+    /// A schema registry was given a name it can't store.
+    ///
+    /// Registered schemas are stored in a directory named after the schema, so
+    /// names must be non-empty, and may not contain path separators or be `.`
+    /// or `..`.
+    #[fail(display = "invalid schema name: {}", name)]
+    InvalidSchemaName { name: String },
+
+    /// A schema registry already has a schema with the given name and version.
+    ///
+    /// Registered versions are immutable. To change a schema, register it again
+    /// under a new version.
+    #[fail(display = "version {} of {} already exists", version, name)]
+    DuplicateVersion { name: String, version: String },
+
+    /// A schema registry rejected a new version of a schema, because it breaks
+    /// the registry's compatibility policy with respect to an existing
+    /// version.
+    #[fail(
+        display = "version {} of {} is incompatible with version {}",
+        version, name, other
+    )]
+    IncompatibleVersion {
+        name: String,
+        version: String,
+        other: String,
+    },
+
+    /// An instance could not be encoded, because it does not match its schema.
+    ///
+    /// The binary encoding relies on instances being valid against their
+    /// schema. The path is a JSON Pointer to the part of the instance which
+    /// doesn't match.
+    #[fail(display = "instance does not match schema at: {}", path)]
+    InvalidInstance { path: String },
+
+    /// An instance could not be encoded, because it has a number too large for
+    /// its binary encoding.
+    ///
+    /// The binary encoding stores `float32` values as 32-bit floats, which
+    /// can't hold numbers as large as JSON can, even though such numbers are
+    /// valid. The path is a JSON Pointer to the number.
+    #[fail(display = "number out of range for binary encoding at: {}", path)]
+    NumberOutOfRange { path: String },
+
+    /// Data could not be decoded, because it is not a valid binary encoding
+    /// of an instance of its schema.
+    ///
+    /// This is the case for data which is truncated, has bytes left over, or
+    /// was encoded using a different schema.
+    #[fail(display = "invalid binary encoding")]
+    InvalidEncoding,
+
+    /// Data could not be decoded, because it has more elements than allowed.
+    ///
+    /// Elements of some schemas take up no space in the binary encoding, so
+    /// data a few bytes long could otherwise decode into an arbitrarily large
+    /// array. This likely means that the data was crafted, or that your
+    /// configured `max_elements` is too small.
+    #[fail(display = "maximum number of elements exceeded during decoding")]
+    MaxElementsExceeded,
+
+    /// A schema can't be converted into another format, because that format
+    /// can't express what the schema describes.
+    ///
+    /// For example, Apache Arrow schemas can't be recursive, so a schema with
+    /// a recursive definition can't be converted into one.
+    #[fail(display = "unsupported schema: {}", reason)]
+    UnsupportedSchema { reason: String },
+
     /// The maximum depth during evaluating was exceeded.
     ///
     /// This likely means that your configured `max_depth` is too small, or that
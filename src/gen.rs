@@ -0,0 +1,520 @@
+//! Generate random instances of schemas.
+//!
+//! This is useful for testing code which consumes data described by a schema:
+//! every instance generated is valid against the schema it was generated
+//! from. Generation is driven by a [`rand::Rng`][rng], so seeding the RNG
+//! makes it reproducible:
+//!
+//! ```
+//! use jddf::gen::Generator;
+//! use jddf::{Schema, SerdeSchema, Validator};
+//! use rand::rngs::StdRng;
+//! use rand::SeedableRng;
+//! use serde_json::json;
+//!
+//! let serde_schema: SerdeSchema = serde_json::from_value(json!({
+//!     "properties": {
+//!         "name": { "type": "string" },
+//!         "age": { "type": "uint8" },
+//!     },
+//! }))
+//! .unwrap();
+//! let schema = Schema::from_serde(serde_schema).unwrap();
+//!
+//! let mut rng = StdRng::seed_from_u64(42);
+//! let instance = Generator::new().generate(&schema, &mut rng);
+//! assert!(Validator::new().validate(&schema, &instance).unwrap().is_empty());
+//! ```
+//!
+//! To generate instances which are *not* valid, see [`mutate`](mutate/index.html).
+//! For property-based testing with `proptest`, see `strategy`, which is
+//! available with the `proptest` feature.
+//!
+//! [rng]: https://docs.rs/rand/0.8/rand/trait.Rng.html
+
+use crate::schema::{Form, Schema, Type};
+use chrono::{FixedOffset, TimeZone};
+use indexmap::IndexMap;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+pub mod mutate;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+
+/// Generates random instances of schemas.
+///
+/// The forms are generated as follows:
+///
+/// * The empty form generates any JSON value.
+/// * `type` generates a value of that type. Integers are within the type's
+///   range, and timestamps are RFC3339 strings with a random offset.
+/// * `enum` generates one of its values.
+/// * `elements` and `values` generate up to
+///   [`Config::max_elements`](struct.Config.html#method.max_elements)
+///   elements or members.
+/// * `properties` generates every required property, and each optional
+///   property with a configurable probability. If additional properties are
+///   allowed, some may be generated too.
+/// * `discriminator` picks one of its mapping values, and generates the
+///   properties for it along with the tag.
+/// * `ref` generates an instance of the definition.
+///
+/// Recursive schemas could generate arbitrarily large instances, so once
+/// [`Config::max_depth`](struct.Config.html#method.max_depth) references
+/// have been followed, the generator only generates the smallest instances
+/// it can: empty arrays and objects, no optional properties, and the
+/// discriminator mapping values which lead out of the recursion the soonest.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate a random instance of a schema.
+    ///
+    /// `schema` must be a root schema, so that its references can be
+    /// resolved.
+    ///
+    /// Panics if the schema has no instances, which happens if it requires a
+    /// property which leads back to the schema itself, with no way out of
+    /// the recursion.
+    pub fn generate<R: Rng + ?Sized>(&self, schema: &Schema, rng: &mut R) -> Value {
+        let no_defs = IndexMap::new();
+        let defs = schema.definitions().as_ref().unwrap_or(&no_defs);
+
+        let ctx = Context {
+            config: &self.config,
+            defs,
+            ranks: ranks(defs),
+        };
+
+        if ctx.rank(schema).is_none() {
+            panic!("jddf: schema has no finite instances");
+        }
+
+        ctx.generate(schema, rng, 0, None)
+    }
+}
+
+/// Configuration for how instances should be generated.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Config {
+    max_depth: usize,
+    max_elements: usize,
+    optional_probability: f64,
+    additional_probability: f64,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of references to follow before only generating the
+    /// smallest instances possible. The default is 4.
+    pub fn max_depth(&mut self, max_depth: usize) -> &mut Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the maximum number of elements of an array, or members of an
+    /// object, to generate. The default is 4.
+    pub fn max_elements(&mut self, max_elements: usize) -> &mut Self {
+        self.max_elements = max_elements;
+        self
+    }
+
+    /// Sets the probability of generating each optional property. The default
+    /// is 0.5.
+    ///
+    /// Panics if `probability` isn't between 0 and 1.
+    pub fn optional_probability(&mut self, probability: f64) -> &mut Self {
+        assert!((0.0..=1.0).contains(&probability));
+        self.optional_probability = probability;
+        self
+    }
+
+    /// Sets the probability of generating an additional property, where
+    /// they're allowed. After each one generated, another is generated with
+    /// the same probability. The default is 0.1.
+    ///
+    /// Panics if `probability` isn't between 0 and 1.
+    pub fn additional_probability(&mut self, probability: f64) -> &mut Self {
+        assert!((0.0..=1.0).contains(&probability));
+        self.additional_probability = probability;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            max_elements: 4,
+            optional_probability: 0.5,
+            additional_probability: 0.1,
+        }
+    }
+}
+
+/// The number of references a definition needs to follow, at the least,
+/// before its instances no longer need to refer to anything. Definitions
+/// with no finite instances are left out.
+fn ranks(defs: &IndexMap<String, Schema>) -> HashMap<&str, usize> {
+    let mut ranks = HashMap::new();
+
+    // Each pass can only lower a rank, or give one to a definition which had
+    // none, so this settles within as many passes as there are definitions.
+    loop {
+        let mut changed = false;
+        for (name, schema) in defs {
+            if let Some(new) = rank(schema, &ranks) {
+                if !matches!(ranks.get(name.as_str()), Some(prev) if *prev <= new) {
+                    ranks.insert(name.as_str(), new);
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            return ranks;
+        }
+    }
+}
+
+/// The rank of a schema, given the ranks of definitions, or `None` if it has
+/// no finite instances.
+fn rank(schema: &Schema, ranks: &HashMap<&str, usize>) -> Option<usize> {
+    match schema.form() {
+        Form::Ref(name) => ranks.get(name.as_str()).map(|rank| rank + 1),
+        Form::Properties { required, .. } => required
+            .values()
+            .map(|sub_schema| rank(sub_schema, ranks))
+            .try_fold(0, |max, rank| rank.map(|rank| max.max(rank))),
+        Form::Discriminator(_, mapping) => mapping
+            .values()
+            .filter_map(|sub_schema| rank(sub_schema, ranks))
+            .min(),
+        _ => Some(0),
+    }
+}
+
+struct Context<'a> {
+    config: &'a Config,
+    defs: &'a IndexMap<String, Schema>,
+    ranks: HashMap<&'a str, usize>,
+}
+
+impl<'a> Context<'a> {
+    fn rank(&self, schema: &Schema) -> Option<usize> {
+        rank(schema, &self.ranks)
+    }
+
+    /// Generate an instance of `schema`, having followed `depth` references.
+    /// `parent_tag` is the tag of the discriminator `schema` is a mapping
+    /// value of, if any.
+    fn generate<R: Rng + ?Sized>(
+        &self,
+        schema: &Schema,
+        rng: &mut R,
+        depth: usize,
+        parent_tag: Option<&str>,
+    ) -> Value {
+        let minimal = depth >= self.config.max_depth;
+
+        match schema.form() {
+            Form::Empty => {
+                if minimal {
+                    Value::Null
+                } else {
+                    self.any(rng, 0)
+                }
+            }
+            Form::Ref(name) => self.generate(&self.defs[name], rng, depth + 1, None),
+            Form::Type(type_) => primitive(type_, rng),
+            Form::Enum(values) => {
+                let values: Vec<_> = values.iter().collect();
+                Value::String(values.choose(rng).unwrap().to_string())
+            }
+            Form::Elements(sub_schema) => {
+                let len = if minimal || self.rank(sub_schema).is_none() {
+                    0
+                } else {
+                    rng.gen_range(0..=self.config.max_elements)
+                };
+
+                Value::Array(
+                    (0..len)
+                        .map(|_| self.generate(sub_schema, rng, depth, None))
+                        .collect(),
+                )
+            }
+            Form::Properties {
+                required,
+                optional,
+                allow_additional,
+                ..
+            } => {
+                let mut out = Map::new();
+                for (name, sub_schema) in required {
+                    out.insert(name.clone(), self.generate(sub_schema, rng, depth, None));
+                }
+
+                if !minimal {
+                    for (name, sub_schema) in optional {
+                        if self.rank(sub_schema).is_some()
+                            && rng.gen_bool(self.config.optional_probability)
+                        {
+                            out.insert(name.clone(), self.generate(sub_schema, rng, depth, None));
+                        }
+                    }
+
+                    if *allow_additional {
+                        let mut taken: HashSet<_> =
+                            required.keys().chain(optional.keys()).cloned().collect();
+                        taken.extend(parent_tag.map(str::to_owned));
+
+                        while rng.gen_bool(self.config.additional_probability) {
+                            let key = unique_key(rng, &mut taken);
+                            out.insert(key, self.any(rng, 1));
+                        }
+                    }
+                }
+
+                Value::Object(out)
+            }
+            Form::Values(sub_schema) => {
+                let len = if minimal || self.rank(sub_schema).is_none() {
+                    0
+                } else {
+                    rng.gen_range(0..=self.config.max_elements)
+                };
+
+                let mut taken = HashSet::new();
+                let mut out = Map::new();
+                for _ in 0..len {
+                    let key = unique_key(rng, &mut taken);
+                    out.insert(key, self.generate(sub_schema, rng, depth, None));
+                }
+
+                Value::Object(out)
+            }
+            Form::Discriminator(tag, mapping) => {
+                // Mapping values with no finite instances are never picked.
+                let min = mapping
+                    .values()
+                    .filter_map(|sub_schema| self.rank(sub_schema))
+                    .min();
+
+                let choices: Vec<_> = mapping
+                    .iter()
+                    .filter(|(_, sub_schema)| match self.rank(sub_schema) {
+                        Some(rank) => !minimal || Some(rank) == min,
+                        None => false,
+                    })
+                    .collect();
+
+                let (value, sub_schema) = choices.choose(rng).unwrap();
+                let mut out = self.generate(sub_schema, rng, depth, Some(tag));
+                out.as_object_mut()
+                    .unwrap()
+                    .insert(tag.clone(), Value::String((*value).clone()));
+
+                out
+            }
+        }
+    }
+
+    /// Generate any JSON value. Arrays and objects nest no further than two
+    /// levels below `level`.
+    fn any<R: Rng + ?Sized>(&self, rng: &mut R, level: usize) -> Value {
+        let choice = if level >= 2 {
+            rng.gen_range(0..4)
+        } else {
+            rng.gen_range(0..6)
+        };
+
+        match choice {
+            0 => Value::Null,
+            1 => Value::Bool(rng.gen()),
+            2 => primitive(&Type::Float64, rng),
+            3 => primitive(&Type::String, rng),
+            4 => {
+                let len = rng.gen_range(0..=self.config.max_elements);
+                Value::Array((0..len).map(|_| self.any(rng, level + 1)).collect())
+            }
+            _ => {
+                let len = rng.gen_range(0..=self.config.max_elements);
+                let mut taken = HashSet::new();
+                let mut out = Map::new();
+                for _ in 0..len {
+                    let key = unique_key(rng, &mut taken);
+                    out.insert(key, self.any(rng, level + 1));
+                }
+
+                Value::Object(out)
+            }
+        }
+    }
+}
+
+fn primitive<R: Rng + ?Sized>(type_: &Type, rng: &mut R) -> Value {
+    match type_ {
+        Type::Boolean => Value::Bool(rng.gen()),
+        Type::Float32 | Type::Float64 => {
+            // Half of the time, generate a number which happens to be an
+            // integer, since those are easy to mishandle.
+            if rng.gen() {
+                Value::from(rng.gen_range(-1000..=1000))
+            } else {
+                Value::from(rng.gen_range(-1e6..1e6))
+            }
+        }
+        Type::Int8 => Value::from(rng.gen::<i8>()),
+        Type::Uint8 => Value::from(rng.gen::<u8>()),
+        Type::Int16 => Value::from(rng.gen::<i16>()),
+        Type::Uint16 => Value::from(rng.gen::<u16>()),
+        Type::Int32 => Value::from(rng.gen::<i32>()),
+        Type::Uint32 => Value::from(rng.gen::<u32>()),
+        Type::String => Value::String(string(rng)),
+        Type::Timestamp => {
+            // Between 1900 and 2100, with an offset of up to a day either way.
+            let secs = rng.gen_range(-2_208_988_800..4_102_444_800);
+            let nanos = if rng.gen() {
+                0
+            } else {
+                rng.gen_range(0..1_000_000_000)
+            };
+            let offset =
+                FixedOffset::east_opt(rng.gen_range(-(24 * 60 - 1)..24 * 60) * 60).unwrap();
+
+            Value::String(offset.timestamp_opt(secs, nanos).unwrap().to_rfc3339())
+        }
+    }
+}
+
+/// Characters strings are made of. Some are outside of ASCII, or need to be
+/// escaped in JSON.
+const CHARS: &[char] = &[
+    'a', 'b', 'c', 'x', 'y', 'z', 'A', 'Z', '0', '9', ' ', '_', '-', '"', '\\', '\n', 'é', 'ß',
+    '中', '😀',
+];
+
+fn string<R: Rng + ?Sized>(rng: &mut R) -> String {
+    let len = rng.gen_range(0..=8);
+    (0..len).map(|_| *CHARS.choose(rng).unwrap()).collect()
+}
+
+/// Generate a string which isn't in `taken`, and add it to `taken`.
+fn unique_key<R: Rng + ?Sized>(rng: &mut R, taken: &mut HashSet<String>) -> String {
+    loop {
+        let key = string(rng);
+        if taken.insert(key.clone()) {
+            return key;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, schema};
+    use crate::Validator;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use serde_json::json;
+
+    #[test]
+    fn generate_valid() {
+        let schema = schema(json!({
+            "definitions": test_util::recursive_definitions(),
+            "properties": {
+                "node": { "ref": "node" },
+                "list": { "ref": "list" },
+                "role": { "enum": ["admin", "member"] },
+                "createdAt": { "type": "timestamp" },
+                "scores": { "values": { "type": "float32" } },
+                "flags": { "elements": { "type": "boolean" } },
+                "counts": {
+                    "properties": {
+                        "a": { "type": "uint8" },
+                        "b": { "type": "int16" },
+                        "c": { "type": "uint16" },
+                        "d": { "type": "int32" },
+                        "e": { "type": "uint32" },
+                        "f": { "type": "float64" },
+                    },
+                },
+                "name": { "type": "string" },
+            },
+            "additionalProperties": true,
+        }));
+
+        let mut config = Config::new();
+        config.additional_probability(0.5);
+        let generator = Generator::new_with_config(config);
+        let validator = Validator::new();
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..500 {
+            let instance = generator.generate(&schema, &mut rng);
+            let errors = validator.validate(&schema, &instance).unwrap();
+            assert!(errors.is_empty(), "{} is invalid", instance);
+        }
+    }
+
+    #[test]
+    fn generate_seeded() {
+        let schema = schema(json!({
+            "values": { "elements": { "type": "string" } },
+        }));
+
+        let generate = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            Generator::new().generate(&schema, &mut rng)
+        };
+
+        assert_eq!(generate(1), generate(1));
+        assert_ne!(generate(1), generate(2));
+    }
+
+    #[test]
+    fn generate_minimal() {
+        let schema = schema(json!({
+            "definitions": {
+                "tree": {
+                    "properties": {
+                        "children": { "elements": { "ref": "tree" } },
+                    },
+                    "optionalProperties": {
+                        "label": { "type": "string" },
+                    },
+                },
+            },
+            "ref": "tree",
+        }));
+
+        let mut config = Config::new();
+        config.max_depth(0);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        assert_eq!(
+            Generator::new_with_config(config).generate(&schema, &mut rng),
+            json!({ "children": [] })
+        );
+    }
+}
@@ -0,0 +1,686 @@
+//! Generate invalid instances of schemas from valid ones.
+//!
+//! Where [`Generator`](../struct.Generator.html) produces instances which
+//! pass validation, this module produces instances which fail it in a known
+//! way. Each [`Mutation`](struct.Mutation.html) breaks a valid instance in a
+//! single place, and comes with the exact errors that
+//! [`Validator::validate`](../../struct.Validator.html#method.validate) must
+//! report for it.
+//!
+//! Mutations can be gathered into a [`TestSuite`](struct.TestSuite.html),
+//! which serializes into the same format as the validation tests of the JDDF
+//! specification:
+//!
+//! ```
+//! use jddf::gen::mutate::TestSuite;
+//! use jddf::{Schema, SerdeSchema};
+//! use serde_json::json;
+//!
+//! let serde_schema: SerdeSchema = serde_json::from_value(json!({
+//!     "properties": {
+//!         "age": { "type": "uint8" },
+//!     },
+//! }))
+//! .unwrap();
+//! let schema = Schema::from_serde(serde_schema).unwrap();
+//!
+//! let suite = TestSuite::new("age", &schema, &json!({ "age": 42 }));
+//! assert_eq!(
+//!     serde_json::to_value(&suite.instances[2]).unwrap(),
+//!     json!({
+//!         "instance": {},
+//!         "errors": [{ "instancePath": "", "schemaPath": "/properties/age" }],
+//!     }),
+//! );
+//! ```
+
+use crate::schema::{Form, Schema, Serde, Type};
+use json_pointer::JsonPointer;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The kinds of mutation which can be made to an instance.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Kind {
+    /// A required property, or a discriminator tag, was removed.
+    DropRequired,
+
+    /// A value was replaced with one of the wrong type.
+    WrongType,
+
+    /// An integer was replaced with one just past the top of its range.
+    OutOfRange,
+
+    /// A string was replaced with one not among the values of its `enum`.
+    UnknownEnum,
+
+    /// A discriminator tag was replaced with one not in its `mapping`.
+    BadTag,
+
+    /// A property was added which the schema does not allow.
+    ExtraProperty,
+}
+
+/// An invalid instance, along with the errors it produces.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Mutation {
+    /// The kind of mutation made.
+    pub kind: Kind,
+
+    /// The mutated instance.
+    pub instance: Value,
+
+    /// The errors validating `instance` produces, in the order the validator
+    /// produces them.
+    pub errors: Vec<TestCaseError>,
+}
+
+/// A schema, and a set of instances to validate against it.
+///
+/// This is the format of the validation tests in the JDDF specification.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TestSuite {
+    /// A name describing the suite.
+    pub name: String,
+
+    /// The schema the instances are validated against.
+    pub schema: Serde,
+
+    /// The instances, along with their expected errors.
+    pub instances: Vec<TestCase>,
+}
+
+impl TestSuite {
+    /// Constructs a test suite from a valid instance of a schema.
+    ///
+    /// The first test case is `instance` itself, with no errors. The rest
+    /// are every mutation of it returned by [`mutate`](fn.mutate.html).
+    pub fn new(name: &str, schema: &Schema, instance: &Value) -> Self {
+        let mut instances = vec![TestCase {
+            instance: instance.clone(),
+            errors: vec![],
+        }];
+
+        instances.extend(mutate(schema, instance).into_iter().map(TestCase::from));
+
+        Self {
+            name: name.to_owned(),
+            schema: schema.clone().into_serde(),
+            instances,
+        }
+    }
+}
+
+/// An instance, and the errors validating it should produce.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TestCase {
+    /// The instance to validate.
+    pub instance: Value,
+
+    /// The expected errors.
+    pub errors: Vec<TestCaseError>,
+}
+
+impl From<Mutation> for TestCase {
+    fn from(mutation: Mutation) -> Self {
+        Self {
+            instance: mutation.instance,
+            errors: mutation.errors,
+        }
+    }
+}
+
+/// An expected validation error, with its paths as JSON Pointers.
+#[derive(Debug, Eq, PartialEq, Clone, Hash, Serialize, Deserialize)]
+pub struct TestCaseError {
+    /// The path to the part of the instance which was rejected.
+    #[serde(rename = "instancePath")]
+    pub instance_path: String,
+
+    /// The path to the part of the schema which rejected the instance.
+    #[serde(rename = "schemaPath")]
+    pub schema_path: String,
+}
+
+/// Generate every mutation of a valid instance of a schema.
+///
+/// `schema` must be a root schema, so that its references can be resolved,
+/// and `instance` must be valid against it. If it isn't, the errors returned
+/// alongside each mutation will not be accurate.
+///
+/// Mutations are returned in the order their locations appear in `instance`.
+/// Each one changes the instance in just one place, so each comes with just
+/// one error:
+///
+/// * Each required property present, and each discriminator tag, is
+///   dropped.
+/// * Each value is replaced with one of the wrong type. Numbers become
+///   strings, other primitives become numbers, and objects and arrays become
+///   `null`.
+/// * Each integer is replaced with one greater than its type allows.
+/// * Each `enum` value is replaced with a string outside the `enum`.
+/// * Each discriminator tag is replaced with a value outside the `mapping`.
+/// * Each object which does not allow additional properties gets one.
+pub fn mutate(schema: &Schema, instance: &Value) -> Vec<Mutation> {
+    let mut ctx = Context {
+        root: schema,
+        instance_tokens: vec![],
+        schema_tokens: vec![],
+        edits: vec![],
+    };
+
+    ctx.walk(schema, instance, None);
+
+    ctx.edits
+        .into_iter()
+        .map(|edit| Mutation {
+            kind: edit.kind,
+            instance: edit.apply(instance),
+            errors: vec![edit.error],
+        })
+        .collect()
+}
+
+/// A change to make at some point in an instance.
+struct Edit {
+    kind: Kind,
+    path: Vec<String>,
+    change: Change,
+    error: TestCaseError,
+}
+
+enum Change {
+    Replace(Value),
+    Remove(String),
+    Insert(String, Value),
+}
+
+impl Edit {
+    fn apply(&self, instance: &Value) -> Value {
+        let mut out = instance.clone();
+        let mut target = &mut out;
+        for token in &self.path {
+            target = match target {
+                Value::Array(arr) => &mut arr[token.parse::<usize>().unwrap()],
+                Value::Object(obj) => obj.get_mut(token).unwrap(),
+                _ => unreachable!("edit path does not exist"),
+            };
+        }
+
+        match &self.change {
+            Change::Replace(value) => *target = value.clone(),
+            Change::Remove(key) => {
+                target.as_object_mut().unwrap().remove(key);
+            }
+            Change::Insert(key, value) => {
+                target
+                    .as_object_mut()
+                    .unwrap()
+                    .insert(key.clone(), value.clone());
+            }
+        }
+
+        out
+    }
+}
+
+struct Context<'a> {
+    root: &'a Schema,
+    instance_tokens: Vec<String>,
+    schema_tokens: Vec<String>,
+    edits: Vec<Edit>,
+}
+
+impl<'a> Context<'a> {
+    /// Walk `instance` alongside `schema`, recording edits to make along the
+    /// way. `parent_tag` is the tag of the discriminator `schema` is a
+    /// mapping value of, if any.
+    fn walk(&mut self, schema: &'a Schema, instance: &'a Value, parent_tag: Option<&'a str>) {
+        match schema.form() {
+            Form::Empty => {}
+            Form::Ref(name) => {
+                let refd_schema = &self.root.definitions().as_ref().unwrap()[name];
+
+                // Like the validator, errors within a definition are
+                // reported relative to the definition, not the reference.
+                let schema_tokens = std::mem::replace(
+                    &mut self.schema_tokens,
+                    vec!["definitions".to_owned(), name.clone()],
+                );
+                self.walk(refd_schema, instance, None);
+                self.schema_tokens = schema_tokens;
+            }
+            Form::Type(type_) => {
+                let wrong = match type_ {
+                    Type::Boolean | Type::String | Type::Timestamp => Value::from(0),
+                    _ => Value::from("0"),
+                };
+                self.replace(Kind::WrongType, wrong, &["type"]);
+
+                if let Some(max) = int_max(type_) {
+                    self.replace(Kind::OutOfRange, Value::from(max + 1), &["type"]);
+                }
+            }
+            Form::Enum(values) => {
+                self.replace(Kind::WrongType, Value::from(0), &["enum"]);
+
+                let unknown = unused("unknown", |s| values.contains(s));
+                self.replace(Kind::UnknownEnum, Value::from(unknown), &["enum"]);
+            }
+            Form::Elements(sub_schema) => {
+                self.replace(Kind::WrongType, Value::Null, &["elements"]);
+
+                if let Some(arr) = instance.as_array() {
+                    self.schema_tokens.push("elements".to_owned());
+                    for (i, elem) in arr.iter().enumerate() {
+                        self.instance_tokens.push(i.to_string());
+                        self.walk(sub_schema, elem, None);
+                        self.instance_tokens.pop();
+                    }
+                    self.schema_tokens.pop();
+                }
+            }
+            Form::Properties {
+                required,
+                optional,
+                has_required,
+                allow_additional,
+            } => {
+                // The object of a discriminator mapping value is checked by
+                // the discriminator first, so it's the discriminator which
+                // would reject it for being of the wrong type.
+                if parent_tag.is_none() {
+                    let keyword = if *has_required {
+                        "properties"
+                    } else {
+                        "optionalProperties"
+                    };
+
+                    self.replace(Kind::WrongType, Value::Null, &[keyword]);
+                }
+
+                let obj = match instance.as_object() {
+                    Some(obj) => obj,
+                    None => return,
+                };
+
+                for (keyword, properties) in
+                    &[("properties", required), ("optionalProperties", optional)]
+                {
+                    self.schema_tokens.push((*keyword).to_owned());
+                    for (name, sub_schema) in properties.iter() {
+                        if let Some(sub_instance) = obj.get(name) {
+                            self.schema_tokens.push(name.clone());
+                            if *keyword == "properties" {
+                                self.edit(Kind::DropRequired, Change::Remove(name.clone()), &[]);
+                            }
+
+                            self.instance_tokens.push(name.clone());
+                            self.walk(sub_schema, sub_instance, None);
+                            self.instance_tokens.pop();
+                            self.schema_tokens.pop();
+                        }
+                    }
+                    self.schema_tokens.pop();
+                }
+
+                if !allow_additional {
+                    let extra = unused("extra", |s| {
+                        obj.contains_key(s)
+                            || required.contains_key(s)
+                            || optional.contains_key(s)
+                            || parent_tag == Some(s)
+                    });
+
+                    // The error is reported at the new property, but against
+                    // the schema as a whole.
+                    self.instance_tokens.push(extra.clone());
+                    let error = self.error(&[]);
+                    self.instance_tokens.pop();
+
+                    self.edits.push(Edit {
+                        kind: Kind::ExtraProperty,
+                        path: self.instance_tokens.clone(),
+                        change: Change::Insert(extra, Value::Null),
+                        error,
+                    });
+                }
+            }
+            Form::Values(sub_schema) => {
+                self.replace(Kind::WrongType, Value::Null, &["values"]);
+
+                if let Some(obj) = instance.as_object() {
+                    self.schema_tokens.push("values".to_owned());
+                    for (name, sub_instance) in obj {
+                        self.instance_tokens.push(name.clone());
+                        self.walk(sub_schema, sub_instance, None);
+                        self.instance_tokens.pop();
+                    }
+                    self.schema_tokens.pop();
+                }
+            }
+            Form::Discriminator(tag, mapping) => {
+                self.replace(Kind::WrongType, Value::Null, &["discriminator"]);
+
+                let obj = match instance.as_object() {
+                    Some(obj) => obj,
+                    None => return,
+                };
+
+                let (instance_tag, sub_schema) = match obj
+                    .get(tag)
+                    .and_then(Value::as_str)
+                    .and_then(|t| mapping.get_key_value(t))
+                {
+                    Some(entry) => entry,
+                    None => return,
+                };
+
+                self.schema_tokens.push("discriminator".to_owned());
+                self.edit(Kind::DropRequired, Change::Remove(tag.clone()), &["tag"]);
+
+                self.instance_tokens.push(tag.clone());
+                let error = self.error(&["mapping"]);
+                self.instance_tokens.pop();
+
+                let unknown = unused("unknown", |s| mapping.contains_key(s));
+                self.edits.push(Edit {
+                    kind: Kind::BadTag,
+                    path: self.instance_tokens.clone(),
+                    change: Change::Insert(tag.clone(), Value::from(unknown)),
+                    error,
+                });
+
+                self.schema_tokens.push("mapping".to_owned());
+                self.schema_tokens.push(instance_tag.clone());
+                self.walk(sub_schema, instance, Some(tag));
+                self.schema_tokens.pop();
+                self.schema_tokens.pop();
+                self.schema_tokens.pop();
+            }
+        }
+    }
+
+    /// Record replacing the current value, rejected by the schema keyword at
+    /// `schema_tokens`.
+    fn replace(&mut self, kind: Kind, value: Value, schema_tokens: &[&str]) {
+        self.edit(kind, Change::Replace(value), schema_tokens);
+    }
+
+    /// Record a change to the current value, rejected by the schema keyword
+    /// at `schema_tokens`, with the error reported at the current value.
+    fn edit(&mut self, kind: Kind, change: Change, schema_tokens: &[&str]) {
+        let error = self.error(schema_tokens);
+        self.edits.push(Edit {
+            kind,
+            path: self.instance_tokens.clone(),
+            change,
+            error,
+        });
+    }
+
+    fn error(&self, schema_tokens: &[&str]) -> TestCaseError {
+        let schema_path: Vec<_> = self
+            .schema_tokens
+            .iter()
+            .map(String::as_str)
+            .chain(schema_tokens.iter().cloned())
+            .collect();
+
+        TestCaseError {
+            instance_path: JsonPointer::new(self.instance_tokens.clone()).to_string(),
+            schema_path: JsonPointer::new(schema_path).to_string(),
+        }
+    }
+}
+
+/// The largest value of an integer type, or `None` if the type isn't an
+/// integer type.
+fn int_max(type_: &Type) -> Option<u64> {
+    match type_ {
+        Type::Int8 => Some(i8::MAX as u64),
+        Type::Uint8 => Some(u8::MAX as u64),
+        Type::Int16 => Some(i16::MAX as u64),
+        Type::Uint16 => Some(u16::MAX as u64),
+        Type::Int32 => Some(i32::MAX as u64),
+        Type::Uint32 => Some(u32::MAX as u64),
+        _ => None,
+    }
+}
+
+/// `base`, with a numeric suffix added if needed to make it not `taken`.
+fn unused(base: &str, taken: impl Fn(&str) -> bool) -> String {
+    let mut out = base.to_owned();
+    let mut i = 0;
+    while taken(&out) {
+        i += 1;
+        out = format!("{}{}", base, i);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::Generator;
+    use crate::test_util::schema;
+    use crate::Validator;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use serde_json::json;
+
+    fn validate(schema: &Schema, instance: &Value) -> Vec<TestCaseError> {
+        Validator::new()
+            .validate(schema, instance)
+            .unwrap()
+            .into_iter()
+            .map(|error| TestCaseError {
+                instance_path: error.instance_path().to_string(),
+                schema_path: error.schema_path().to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mutate_paths() {
+        let schema = schema(json!({
+            "definitions": {
+                "shape": {
+                    "discriminator": {
+                        "tag": "type",
+                        "mapping": {
+                            "circle": {
+                                "properties": {
+                                    "r": { "type": "uint8" },
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+            "properties": {
+                "color": { "enum": ["red"] },
+                "shapes": { "elements": { "ref": "shape" } },
+            },
+        }));
+
+        let instance = json!({
+            "color": "red",
+            "shapes": [{ "type": "circle", "r": 1 }],
+        });
+
+        let mutations: Vec<_> = mutate(&schema, &instance)
+            .into_iter()
+            .map(|m| (m.kind, m.instance, m.errors[0].clone()))
+            .collect();
+
+        let error = |instance_path: &str, schema_path: &str| TestCaseError {
+            instance_path: instance_path.to_owned(),
+            schema_path: schema_path.to_owned(),
+        };
+
+        assert_eq!(
+            vec![
+                (Kind::WrongType, Value::Null, error("", "/properties"),),
+                (
+                    Kind::DropRequired,
+                    json!({ "shapes": [{ "type": "circle", "r": 1 }] }),
+                    error("", "/properties/color"),
+                ),
+                (
+                    Kind::WrongType,
+                    json!({ "color": 0, "shapes": [{ "type": "circle", "r": 1 }] }),
+                    error("/color", "/properties/color/enum"),
+                ),
+                (
+                    Kind::UnknownEnum,
+                    json!({ "color": "unknown", "shapes": [{ "type": "circle", "r": 1 }] }),
+                    error("/color", "/properties/color/enum"),
+                ),
+                (
+                    Kind::DropRequired,
+                    json!({ "color": "red" }),
+                    error("", "/properties/shapes"),
+                ),
+                (
+                    Kind::WrongType,
+                    json!({ "color": "red", "shapes": null }),
+                    error("/shapes", "/properties/shapes/elements"),
+                ),
+                (
+                    Kind::WrongType,
+                    json!({ "color": "red", "shapes": [null] }),
+                    error("/shapes/0", "/definitions/shape/discriminator"),
+                ),
+                (
+                    Kind::DropRequired,
+                    json!({ "color": "red", "shapes": [{ "r": 1 }] }),
+                    error("/shapes/0", "/definitions/shape/discriminator/tag"),
+                ),
+                (
+                    Kind::BadTag,
+                    json!({ "color": "red", "shapes": [{ "type": "unknown", "r": 1 }] }),
+                    error("/shapes/0/type", "/definitions/shape/discriminator/mapping"),
+                ),
+                (
+                    Kind::DropRequired,
+                    json!({ "color": "red", "shapes": [{ "type": "circle" }] }),
+                    error(
+                        "/shapes/0",
+                        "/definitions/shape/discriminator/mapping/circle/properties/r"
+                    ),
+                ),
+                (
+                    Kind::WrongType,
+                    json!({ "color": "red", "shapes": [{ "type": "circle", "r": "0" }] }),
+                    error(
+                        "/shapes/0/r",
+                        "/definitions/shape/discriminator/mapping/circle/properties/r/type"
+                    ),
+                ),
+                (
+                    Kind::OutOfRange,
+                    json!({ "color": "red", "shapes": [{ "type": "circle", "r": 256 }] }),
+                    error(
+                        "/shapes/0/r",
+                        "/definitions/shape/discriminator/mapping/circle/properties/r/type"
+                    ),
+                ),
+                (
+                    Kind::ExtraProperty,
+                    json!({
+                        "color": "red",
+                        "shapes": [{ "type": "circle", "r": 1, "extra": null }],
+                    }),
+                    error(
+                        "/shapes/0/extra",
+                        "/definitions/shape/discriminator/mapping/circle"
+                    ),
+                ),
+                (
+                    Kind::ExtraProperty,
+                    json!({
+                        "color": "red",
+                        "shapes": [{ "type": "circle", "r": 1 }],
+                        "extra": null,
+                    }),
+                    error("/extra", ""),
+                ),
+            ],
+            mutations
+        );
+    }
+
+    #[test]
+    fn mutate_matches_validator() {
+        let schema = schema(json!({
+            "definitions": {
+                "node": {
+                    "properties": {
+                        "value": { "type": "int32" },
+                        "children": { "elements": { "ref": "node" } },
+                    },
+                    "optionalProperties": {
+                        "label": { "type": "timestamp" },
+                    },
+                },
+                "event": {
+                    "discriminator": {
+                        "tag": "kind",
+                        "mapping": {
+                            "a": {
+                                "optionalProperties": {
+                                    "x": { "type": "float64" },
+                                    "y": { "type": "boolean" },
+                                    "extra": { "type": "string" },
+                                },
+                            },
+                            "b": {
+                                "properties": {
+                                    "kind1": { "enum": ["kind", "unknown"] },
+                                },
+                                "additionalProperties": true,
+                            },
+                        },
+                    },
+                },
+            },
+            "properties": {
+                "node": { "ref": "node" },
+                "events": { "values": { "ref": "event" } },
+                "counts": {
+                    "properties": {
+                        "a": { "type": "uint8" },
+                        "b": { "type": "int16" },
+                        "c": { "type": "uint16" },
+                        "d": { "type": "int8" },
+                        "e": { "type": "uint32" },
+                    },
+                },
+                "name": { "type": "string" },
+                "extra": {},
+            },
+            "optionalProperties": {
+                "anything": {},
+            },
+        }));
+
+        let generator = Generator::new();
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..50 {
+            let instance = generator.generate(&schema, &mut rng);
+            let suite = TestSuite::new("generated", &schema, &instance);
+
+            // Round-trip through JSON, as a spec test would.
+            let suite: TestSuite =
+                serde_json::from_value(serde_json::to_value(&suite).unwrap()).unwrap();
+            let suite_schema = Schema::from_serde(suite.schema).unwrap();
+
+            assert!(suite.instances.len() > 1);
+            for case in suite.instances {
+                assert_eq!(case.errors, validate(&suite_schema, &case.instance));
+            }
+        }
+    }
+}
@@ -0,0 +1,474 @@
+//! Generate instances of schemas with [`proptest`][proptest].
+//!
+//! This module is only available with the `proptest` feature enabled.
+//!
+//! [`Generator::strategy`](../struct.Generator.html#method.strategy) turns a
+//! schema into a proptest strategy, so property-based tests of code which
+//! consumes a schema's data can be driven by the schema itself:
+//!
+//! ```
+//! use jddf::gen::strategy;
+//! use jddf::{Schema, SerdeSchema, Validator};
+//! use proptest::test_runner::TestRunner;
+//! use serde_json::json;
+//!
+//! let serde_schema: SerdeSchema = serde_json::from_value(json!({
+//!     "properties": {
+//!         "name": { "type": "string" },
+//!         "tags": { "elements": { "type": "string" } },
+//!     },
+//! }))
+//! .unwrap();
+//! let schema = Schema::from_serde(serde_schema).unwrap();
+//!
+//! TestRunner::default()
+//!     .run(&strategy::arbitrary(&schema), |instance| {
+//!         assert!(Validator::new().validate(&schema, &instance).unwrap().is_empty());
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! ```
+//!
+//! Instances are generated the same way as
+//! [`Generator::generate`](../struct.Generator.html#method.generate) does,
+//! and with the same [`Config`](../struct.Config.html). Unlike `generate`,
+//! instances from a strategy can be shrunk when a test fails. Shrinking only
+//! ever produces valid instances: arrays and maps lose elements, optional
+//! and additional properties are dropped, and primitives move towards
+//! simpler values of the same type.
+//!
+//! [proptest]: https://docs.rs/proptest/1
+
+use super::{rank, ranks, Config, Generator, CHARS};
+use crate::schema::{Form, Schema, Type};
+use chrono::{FixedOffset, TimeZone};
+use indexmap::IndexMap;
+use proptest::prelude::*;
+use proptest::strategy::Union;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+impl Generator {
+    /// Constructs a proptest strategy which generates instances of a schema.
+    ///
+    /// `schema` must be a root schema, so that its references can be
+    /// resolved.
+    ///
+    /// Panics if the schema has no instances, for the same reasons as
+    /// [`generate`](#method.generate).
+    pub fn strategy(&self, schema: &Schema) -> BoxedStrategy<Value> {
+        let no_defs = IndexMap::new();
+        let defs = schema.definitions().as_ref().unwrap_or(&no_defs);
+
+        let mut ctx = Context {
+            config: &self.config,
+            defs,
+            ranks: ranks(defs),
+            refs: HashMap::new(),
+        };
+
+        if rank(schema, &ctx.ranks).is_none() {
+            panic!("jddf: schema has no finite instances");
+        }
+
+        ctx.strategy(schema, 0, None)
+    }
+}
+
+/// Constructs a proptest strategy which generates instances of a schema,
+/// using the default configuration.
+///
+/// This is the counterpart to proptest's `any`, for data described by a
+/// schema rather than by a Rust type.
+pub fn arbitrary(schema: &Schema) -> BoxedStrategy<Value> {
+    Generator::new().strategy(schema)
+}
+
+struct Context<'a> {
+    config: &'a Config,
+    defs: &'a IndexMap<String, Schema>,
+    ranks: HashMap<&'a str, usize>,
+
+    /// Strategies for definitions, by name and the number of references
+    /// followed to reach them. Past `max_depth`, only the smallest instances
+    /// are generated, so all greater depths share a strategy.
+    refs: HashMap<(&'a str, usize), BoxedStrategy<Value>>,
+}
+
+impl<'a> Context<'a> {
+    fn rank(&self, schema: &Schema) -> Option<usize> {
+        rank(schema, &self.ranks)
+    }
+
+    /// Construct a strategy for `schema`, having followed `depth` references.
+    /// `parent_tag` is the tag of the discriminator `schema` is a mapping
+    /// value of, if any.
+    fn strategy(
+        &mut self,
+        schema: &'a Schema,
+        depth: usize,
+        parent_tag: Option<&str>,
+    ) -> BoxedStrategy<Value> {
+        let minimal = depth >= self.config.max_depth;
+
+        match schema.form() {
+            Form::Empty => {
+                if minimal {
+                    Just(Value::Null).boxed()
+                } else {
+                    self.any()
+                }
+            }
+            Form::Ref(name) => {
+                let depth = (depth + 1).min(self.config.max_depth);
+                if let Some(strategy) = self.refs.get(&(name.as_str(), depth)) {
+                    return strategy.clone();
+                }
+
+                let defs = self.defs;
+                let strategy = self.strategy(&defs[name], depth, None);
+                self.refs.insert((name.as_str(), depth), strategy.clone());
+                strategy
+            }
+            Form::Type(type_) => primitive(type_),
+            Form::Enum(values) => {
+                let values: Vec<_> = values.iter().cloned().collect();
+                prop::sample::select(values).prop_map(Value::String).boxed()
+            }
+            Form::Elements(sub_schema) => {
+                if minimal || self.rank(sub_schema).is_none() {
+                    return Just(Value::Array(vec![])).boxed();
+                }
+
+                let sub_strategy = self.strategy(sub_schema, depth, None);
+                prop::collection::vec(sub_strategy, 0..=self.config.max_elements)
+                    .prop_map(Value::Array)
+                    .boxed()
+            }
+            Form::Properties {
+                required,
+                optional,
+                allow_additional,
+                ..
+            } => {
+                let mut required_strategies = vec![];
+                for (name, sub_schema) in required {
+                    let name = name.clone();
+                    required_strategies.push(
+                        self.strategy(sub_schema, depth, None)
+                            .prop_map(move |value| (name.clone(), value)),
+                    );
+                }
+
+                let mut optional_strategies = vec![];
+                if !minimal {
+                    for (name, sub_schema) in optional {
+                        if self.rank(sub_schema).is_some() {
+                            let name = name.clone();
+                            let sub_strategy = self
+                                .strategy(sub_schema, depth, None)
+                                .prop_map(move |value| (name.clone(), value));
+
+                            optional_strategies
+                                .push(maybe(self.config.optional_probability, sub_strategy));
+                        }
+                    }
+                }
+
+                let additional = if minimal || !allow_additional {
+                    Just(None).boxed()
+                } else {
+                    let members =
+                        prop::collection::vec((key(), self.any()), 1..=self.config.max_elements);
+                    maybe(self.config.additional_probability, members)
+                };
+
+                let mut taken: Vec<_> = required.keys().chain(optional.keys()).cloned().collect();
+                taken.extend(parent_tag.map(str::to_owned));
+
+                (required_strategies, optional_strategies, additional)
+                    .prop_map(move |(required, optional, additional)| {
+                        let mut out: Map<_, _> = required.into_iter().collect();
+                        out.extend(optional.into_iter().flatten());
+
+                        // Additional properties which collide with declared
+                        // ones are dropped, rather than regenerated, so that
+                        // shrinking never turns them into something invalid.
+                        for (key, value) in additional.into_iter().flatten() {
+                            if !taken.contains(&key) {
+                                out.insert(key, value);
+                            }
+                        }
+
+                        Value::Object(out)
+                    })
+                    .boxed()
+            }
+            Form::Values(sub_schema) => {
+                if minimal || self.rank(sub_schema).is_none() {
+                    return Just(Value::Object(Map::new())).boxed();
+                }
+
+                let sub_strategy = self.strategy(sub_schema, depth, None);
+                prop::collection::vec((key(), sub_strategy), 0..=self.config.max_elements)
+                    .prop_map(|members| Value::Object(members.into_iter().collect()))
+                    .boxed()
+            }
+            Form::Discriminator(tag, mapping) => {
+                // Mapping values with no finite instances are never picked.
+                let min = mapping
+                    .values()
+                    .filter_map(|sub_schema| self.rank(sub_schema))
+                    .min();
+
+                let mut choices = vec![];
+                for (value, sub_schema) in mapping {
+                    let picked = match self.rank(sub_schema) {
+                        Some(rank) => !minimal || Some(rank) == min,
+                        None => false,
+                    };
+
+                    if picked {
+                        let tag = tag.clone();
+                        let value = value.clone();
+                        choices.push(self.strategy(sub_schema, depth, Some(&tag)).prop_map(
+                            move |mut out| {
+                                out.as_object_mut()
+                                    .unwrap()
+                                    .insert(tag.clone(), Value::String(value.clone()));
+                                out
+                            },
+                        ));
+                    }
+                }
+
+                Union::new(choices).boxed()
+            }
+        }
+    }
+
+    /// Construct a strategy for any JSON value. Arrays and objects nest no
+    /// more than two levels deep.
+    fn any(&self) -> BoxedStrategy<Value> {
+        let max_elements = self.config.max_elements;
+
+        prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::Bool),
+            primitive(&Type::Float64),
+            primitive(&Type::String),
+        ]
+        .prop_recursive(2, 16, max_elements as u32, move |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..=max_elements).prop_map(Value::Array),
+                prop::collection::vec((key(), inner), 0..=max_elements)
+                    .prop_map(|members| Value::Object(members.into_iter().collect())),
+            ]
+        })
+        .boxed()
+    }
+}
+
+fn primitive(type_: &Type) -> BoxedStrategy<Value> {
+    match type_ {
+        Type::Boolean => any::<bool>().prop_map(Value::Bool).boxed(),
+        Type::Float32 | Type::Float64 => {
+            // As with Generator::generate, half of the time generate a number
+            // which happens to be an integer.
+            prop_oneof![
+                (-1000..=1000).prop_map(Value::from),
+                (-1e6..1e6).prop_map(Value::from),
+            ]
+            .boxed()
+        }
+        Type::Int8 => any::<i8>().prop_map(Value::from).boxed(),
+        Type::Uint8 => any::<u8>().prop_map(Value::from).boxed(),
+        Type::Int16 => any::<i16>().prop_map(Value::from).boxed(),
+        Type::Uint16 => any::<u16>().prop_map(Value::from).boxed(),
+        Type::Int32 => any::<i32>().prop_map(Value::from).boxed(),
+        Type::Uint32 => any::<u32>().prop_map(Value::from).boxed(),
+        Type::String => string().prop_map(Value::String).boxed(),
+        Type::Timestamp => {
+            // Between 1900 and 2100, with an offset of up to a day either way.
+            let secs = -2_208_988_800..4_102_444_800i64;
+            let nanos = prop_oneof![Just(0), 0..1_000_000_000u32];
+            let offset = -(24 * 60 - 1)..24 * 60;
+
+            (secs, nanos, offset)
+                .prop_map(|(secs, nanos, offset)| {
+                    let offset = FixedOffset::east_opt(offset * 60).unwrap();
+                    Value::String(offset.timestamp_opt(secs, nanos).unwrap().to_rfc3339())
+                })
+                .boxed()
+        }
+    }
+}
+
+fn string() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(CHARS), 0..=8)
+        .prop_map(|chars| chars.into_iter().collect())
+}
+
+fn key() -> impl Strategy<Value = String> {
+    string()
+}
+
+/// A strategy which is `Some` with probability `p`.
+///
+/// Unlike `prop::option::weighted`, `p` may be zero or one.
+fn maybe<S>(p: f64, strategy: S) -> BoxedStrategy<Option<S::Value>>
+where
+    S: Strategy + 'static,
+    S::Value: Clone,
+{
+    if p == 0.0 {
+        Just(None).boxed()
+    } else if p == 1.0 {
+        strategy.prop_map(Some).boxed()
+    } else {
+        prop::option::weighted(p, strategy).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::schema;
+    use crate::Validator;
+    use proptest::test_runner::{
+        Config as RunnerConfig, RngAlgorithm, TestError, TestRng, TestRunner,
+    };
+    use serde_json::json;
+
+    /// Shapes, where each variant of the union has optional properties, and
+    /// one of them recurses back into the union.
+    fn shapes() -> Schema {
+        schema(json!({
+            "definitions": {
+                "shape": {
+                    "discriminator": {
+                        "tag": "type",
+                        "mapping": {
+                            "circle": {
+                                "properties": {
+                                    "radius": { "type": "float32" },
+                                },
+                                "optionalProperties": {
+                                    "label": { "type": "string" },
+                                },
+                            },
+                            "group": {
+                                "properties": {
+                                    "shapes": { "elements": { "ref": "shape" } },
+                                },
+                                "optionalProperties": {
+                                    "style": { "enum": ["solid", "dashed"] },
+                                    "createdAt": { "type": "timestamp" },
+                                },
+                            },
+                            "point": {
+                                "properties": {},
+                                "additionalProperties": true,
+                            },
+                        },
+                    },
+                },
+            },
+            "properties": {
+                "shape": { "ref": "shape" },
+            },
+            "optionalProperties": {
+                "layers": { "values": { "ref": "shape" } },
+            },
+            "additionalProperties": true,
+        }))
+    }
+
+    #[test]
+    fn strategy_valid() {
+        let schema = shapes();
+
+        let mut config = Config::new();
+        config.additional_probability(0.5);
+        let strategy = Generator::new_with_config(config).strategy(&schema);
+
+        // Check every value tried, including the ones tried while shrinking,
+        // by failing on groups with members.
+        let invalid = std::cell::Cell::new(0);
+        let mut runner = TestRunner::new_with_rng(
+            RunnerConfig::with_cases(200),
+            TestRng::deterministic_rng(RngAlgorithm::ChaCha),
+        );
+        let result = runner.run(&strategy, |instance| {
+            if !Validator::new()
+                .validate(&schema, &instance)
+                .unwrap()
+                .is_empty()
+            {
+                invalid.set(invalid.get() + 1);
+            }
+
+            let members = instance["shape"]["shapes"].as_array().map_or(0, Vec::len);
+            prop_assert!(members == 0);
+            Ok(())
+        });
+
+        let instance = match result {
+            Err(TestError::Fail(_, instance)) => instance,
+            result => panic!("unexpected result: {:?}", result),
+        };
+
+        assert_eq!(0, invalid.get());
+        assert_eq!(
+            json!({
+                "shape": {
+                    "type": "group",
+                    "shapes": [{ "type": "circle", "radius": 0 }],
+                },
+            }),
+            instance
+        );
+    }
+
+    #[test]
+    fn strategy_shrinks_valid() {
+        let schema = schema(json!({
+            "properties": {
+                "id": { "type": "uint16" },
+                "events": {
+                    "elements": {
+                        "properties": {
+                            "at": { "type": "timestamp" },
+                        },
+                        "optionalProperties": {
+                            "note": { "type": "string" },
+                        },
+                    },
+                },
+            },
+        }));
+
+        // Fails for any instance with at least two events, so shrinking ought
+        // to settle on the simplest such instance.
+        let mut runner = TestRunner::deterministic();
+        let result = runner.run(&arbitrary(&schema), |instance| {
+            prop_assert!(instance["events"].as_array().unwrap().len() < 2);
+            Ok(())
+        });
+
+        let instance = match result {
+            Err(TestError::Fail(_, instance)) => instance,
+            result => panic!("unexpected result: {:?}", result),
+        };
+
+        assert!(Validator::new()
+            .validate(&schema, &instance)
+            .unwrap()
+            .is_empty());
+
+        let event = &instance["events"][0];
+        assert_eq!(json!(0), instance["id"]);
+        assert_eq!(2, instance["events"].as_array().unwrap().len());
+        assert_eq!(None, event.get("note"));
+    }
+}
@@ -0,0 +1,1148 @@
+//! Convert schemas to and from JSON Schema.
+//!
+//! [JSON Schema](https://json-schema.org) is more widely supported than JDDF,
+//! but much more complex. Every JDDF schema can be expressed as a JSON Schema
+//! which accepts exactly the same instances, which lets you keep using JDDF
+//! for your own schemas while handing JSON Schema to tools that need it.
+//!
+//! The reverse isn't true: only a subset of JSON Schema can be expressed in
+//! JDDF. When importing a JSON Schema, anything outside of that subset is
+//! dropped or approximated, and reported as such.
+//!
+//! See the docs for [`export`](fn.export.html) and
+//! [`import`](fn.import.html) for more.
+
+use crate::schema::{Form, Schema, Type};
+use indexmap::{IndexMap, IndexSet};
+use json_pointer::JsonPointer;
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+
+/// The `$schema` URI for the version of JSON Schema this module targets.
+pub const DRAFT_2020_12: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Convert a root schema into a JSON Schema (draft 2020-12) document.
+///
+/// The forms are converted as follows:
+///
+/// * `type` becomes a JSON Schema `type`. Integer types are given a `minimum`
+///   and `maximum`, and `timestamp` becomes a string with `format: date-time`.
+/// * `enum` becomes an `enum` of the same strings.
+/// * `elements` becomes an `array` with `items`.
+/// * `properties` becomes an `object`, with `additionalProperties: false`
+///   unless the schema allows additional properties.
+/// * `values` becomes an `object` with `additionalProperties`.
+/// * `discriminator` becomes a `oneOf`, where each alternative requires the
+///   tag to be a `const` value.
+/// * Definitions become `$defs`, and `ref`s become `$ref`s to them.
+///
+/// A `description` in a schema's `metadata` is carried over as the JSON
+/// Schema `description`. Other non-keyword data is dropped.
+///
+/// JSON Schema formats like `date-time` are only annotations by default, so
+/// not every JSON Schema validator will check timestamps.
+pub fn export(schema: &Schema) -> Value {
+    let mut out = Map::new();
+    out.insert("$schema".to_owned(), DRAFT_2020_12.into());
+    out.extend(export_schema(schema, None));
+
+    if let Some(defs) = schema.definitions() {
+        if !defs.is_empty() {
+            let defs = defs
+                .iter()
+                .map(|(name, sub_schema)| {
+                    (name.clone(), Value::Object(export_schema(sub_schema, None)))
+                })
+                .collect();
+
+            out.insert("$defs".to_owned(), Value::Object(defs));
+        }
+    }
+
+    Value::Object(out)
+}
+
+/// Convert a single schema. `tag` is set when converting one of the mapping
+/// values of a discriminator, and is the tag and its value.
+fn export_schema(schema: &Schema, tag: Option<(&str, &str)>) -> Map<String, Value> {
+    let mut out = match schema.form() {
+        Form::Empty => Map::new(),
+        Form::Ref(def) => object(json!({ "$ref": def_ref(def) })),
+        Form::Type(typ) => export_type(typ),
+        Form::Enum(values) => object(json!({ "enum": values })),
+        Form::Elements(sub_schema) => object(json!({
+            "type": "array",
+            "items": export_schema(sub_schema, None),
+        })),
+        Form::Properties {
+            required,
+            optional,
+            allow_additional,
+            ..
+        } => {
+            let mut properties = Map::new();
+            let mut required_names = vec![];
+
+            if let Some((tag, value)) = tag {
+                properties.insert(tag.to_owned(), json!({ "const": value }));
+                required_names.push(tag.to_owned());
+            }
+
+            for (name, sub_schema) in required {
+                properties.insert(name.clone(), Value::Object(export_schema(sub_schema, None)));
+                required_names.push(name.clone());
+            }
+
+            for (name, sub_schema) in optional {
+                properties.insert(name.clone(), Value::Object(export_schema(sub_schema, None)));
+            }
+
+            let mut out = object(json!({ "type": "object" }));
+            if !properties.is_empty() {
+                out.insert("properties".to_owned(), Value::Object(properties));
+            }
+
+            if !required_names.is_empty() {
+                out.insert("required".to_owned(), required_names.into());
+            }
+
+            if !allow_additional {
+                out.insert("additionalProperties".to_owned(), false.into());
+            }
+
+            out
+        }
+        Form::Values(sub_schema) => object(json!({
+            "type": "object",
+            "additionalProperties": export_schema(sub_schema, None),
+        })),
+        Form::Discriminator(tag, mapping) => export_discriminator(tag, mapping),
+    };
+
+    let description = schema
+        .extra()
+        .get("metadata")
+        .and_then(|metadata| metadata.get("description"))
+        .and_then(Value::as_str);
+
+    if let Some(description) = description {
+        out.insert("description".to_owned(), description.into());
+    }
+
+    out
+}
+
+pub(crate) fn export_type(typ: &Type) -> Map<String, Value> {
+    if let Some((min, max)) = integer_range(typ) {
+        return object(json!({ "type": "integer", "minimum": min, "maximum": max }));
+    }
+
+    match typ {
+        Type::Boolean => object(json!({ "type": "boolean" })),
+        Type::Timestamp => object(json!({ "type": "string", "format": "date-time" })),
+        Type::String => object(json!({ "type": "string" })),
+        _ => object(json!({ "type": "number" })),
+    }
+}
+
+/// The integer types, from narrowest to widest.
+const INTEGER_TYPES: &[Type] = &[
+    Type::Uint8,
+    Type::Int8,
+    Type::Uint16,
+    Type::Int16,
+    Type::Uint32,
+    Type::Int32,
+];
+
+/// The smallest and largest values of an integer type.
+fn integer_range(typ: &Type) -> Option<(i64, i64)> {
+    match typ {
+        Type::Int8 => Some((i64::from(i8::MIN), i64::from(i8::MAX))),
+        Type::Uint8 => Some((0, i64::from(u8::MAX))),
+        Type::Int16 => Some((i64::from(i16::MIN), i64::from(i16::MAX))),
+        Type::Uint16 => Some((0, i64::from(u16::MAX))),
+        Type::Int32 => Some((i64::from(i32::MIN), i64::from(i32::MAX))),
+        Type::Uint32 => Some((0, i64::from(u32::MAX))),
+        _ => None,
+    }
+}
+
+fn export_discriminator(tag: &str, mapping: &IndexMap<String, Schema>) -> Map<String, Value> {
+    // JSON Schema requires oneOf to be non-empty. A discriminator with no
+    // mapping accepts nothing.
+    if mapping.is_empty() {
+        return object(json!({ "not": {} }));
+    }
+
+    let variants: Vec<_> = mapping
+        .iter()
+        .map(|(value, sub_schema)| Value::Object(export_schema(sub_schema, Some((tag, value)))))
+        .collect();
+
+    object(json!({
+        "type": "object",
+        "properties": { tag: { "type": "string" } },
+        "required": [tag],
+        "oneOf": variants,
+    }))
+}
+
+/// The `$ref` for a definition, as a URI fragment containing a JSON Pointer.
+fn def_ref(def: &str) -> String {
+    let pointer = JsonPointer::new(vec!["$defs".to_owned(), def.to_owned()]).to_string();
+
+    let mut out = "#".to_owned();
+    for byte in pointer.bytes() {
+        // RFC 3986 allows these characters in a fragment as-is.
+        let allowed = byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/?".contains(&byte);
+        if allowed {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    out
+}
+
+pub(crate) fn object(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(map) => map,
+        _ => unreachable!("object called with non-object"),
+    }
+}
+
+/// Convert a JSON Schema document into a root schema.
+///
+/// Only the parts of JSON Schema which JDDF can express are converted. Beyond
+/// the obvious mappings between types, a few common patterns are recognized:
+///
+/// * `oneOf` or `anyOf`, where every alternative is an object whose properties
+///   share a tag property with a distinct `const` value, becomes a
+///   `discriminator`. If there's an OpenAPI-style `discriminator` keyword, its
+///   `propertyName` is preferred as the tag.
+/// * An `object` with `additionalProperties` as a schema, and no
+///   `properties`, becomes `values`.
+/// * An `integer` with a `minimum` and `maximum` becomes the narrowest integer
+///   type which covers that range.
+/// * A `string` with `format: date-time` becomes a `timestamp`.
+/// * `$ref`s to `$defs` or `definitions` in the same document become `ref`s.
+///
+/// A `title` or `description` is kept in the schema's `metadata`.
+///
+/// Everything else is reported in the returned
+/// [`Import`](struct.Import.html). A keyword which is ignored is reported as
+/// [`Dropped`](enum.IssueKind.html#variant.Dropped). A construct which is
+/// replaced with something that accepts more instances, such as an integer
+/// whose bounds are widened, is reported as
+/// [`Approximated`](enum.IssueKind.html#variant.Approximated).
+pub fn import(json_schema: &Value) -> Import {
+    let mut def_schemas = vec![];
+    if let Value::Object(root) = json_schema {
+        for keyword in &["$defs", "definitions"] {
+            if let Some(Value::Object(defs)) = root.get(*keyword) {
+                for (name, sub_schema) in defs {
+                    def_schemas.push((*keyword, name, sub_schema));
+                }
+            }
+        }
+    }
+
+    let mut importer = Importer {
+        defs: def_schemas
+            .iter()
+            .map(|(_, name, _)| name.to_string())
+            .collect(),
+        tokens: vec![],
+        issues: vec![],
+    };
+
+    let mut defs = IndexMap::new();
+    for (keyword, name, sub_schema) in def_schemas {
+        importer.tokens.push(keyword.to_owned());
+        importer.tokens.push(name.clone());
+
+        if defs.contains_key(name) {
+            importer.push(IssueKind::Dropped, "duplicate definition".to_owned());
+        } else {
+            let (form, extra) = importer.convert(sub_schema, false);
+            defs.insert(
+                name.clone(),
+                Schema::from_parts(None, Box::new(form), extra),
+            );
+        }
+
+        importer.tokens.pop();
+        importer.tokens.pop();
+    }
+
+    let (form, extra) = importer.convert(json_schema, true);
+    Import {
+        schema: Schema::from_parts(Some(defs), Box::new(form), extra),
+        issues: importer.issues,
+    }
+}
+
+/// The result of importing a JSON Schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Import {
+    pub(crate) schema: Schema,
+    pub(crate) issues: Vec<Issue>,
+}
+
+impl Import {
+    /// The imported schema.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Consume the import, returning the imported schema.
+    pub fn into_schema(self) -> Schema {
+        self.schema
+    }
+
+    /// The parts of the JSON Schema which couldn't be imported exactly, in the
+    /// order they were found.
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+
+    /// Whether the imported schema accepts exactly the same instances as the
+    /// JSON Schema.
+    ///
+    /// Keywords which don't affect validation, such as `examples`, are
+    /// reported as dropped, and so also make this false.
+    pub fn is_exact(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// A part of a JSON Schema which couldn't be imported exactly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Issue {
+    pub(crate) kind: IssueKind,
+    pub(crate) path: JsonPointer<String, Vec<String>>,
+    pub(crate) message: String,
+}
+
+impl Issue {
+    /// What was done to the part of the JSON Schema.
+    pub fn kind(&self) -> IssueKind {
+        self.kind
+    }
+
+    /// A pointer into the part of the JSON Schema the issue is about.
+    pub fn path(&self) -> &JsonPointer<String, Vec<String>> {
+        &self.path
+    }
+
+    /// A human-readable description of the issue.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// The ways a part of a JSON Schema can fail to be imported exactly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IssueKind {
+    /// The keyword was ignored.
+    Dropped,
+
+    /// The construct was replaced with something which accepts more
+    /// instances.
+    Approximated,
+}
+
+struct Importer {
+    defs: HashSet<String>,
+    tokens: Vec<String>,
+    issues: Vec<Issue>,
+}
+
+impl Importer {
+    fn convert(&mut self, value: &Value, is_root: bool) -> (Form, IndexMap<String, Value>) {
+        let obj = match value {
+            Value::Bool(true) => return (Form::Empty, IndexMap::new()),
+            Value::Bool(false) => {
+                self.push(
+                    IssueKind::Approximated,
+                    "the false schema was replaced with the empty schema".to_owned(),
+                );
+                return (Form::Empty, IndexMap::new());
+            }
+            Value::Object(obj) => obj,
+            _ => {
+                self.push(IssueKind::Dropped, "not a valid schema".to_owned());
+                return (Form::Empty, IndexMap::new());
+            }
+        };
+
+        let mut used = HashSet::new();
+        let form = self.convert_form(obj, &mut used);
+
+        let mut metadata = Map::new();
+        for keyword in &["title", "description"] {
+            if let Some(value) = obj.get(*keyword) {
+                used.insert(*keyword);
+                metadata.insert((*keyword).to_owned(), value.clone());
+            }
+        }
+
+        let mut extra = IndexMap::new();
+        if !metadata.is_empty() {
+            extra.insert("metadata".to_owned(), Value::Object(metadata));
+        }
+
+        if is_root {
+            used.extend(&["$schema", "$id", "$defs", "definitions"]);
+        }
+
+        for keyword in obj.keys() {
+            if !used.contains(keyword.as_str()) {
+                self.push_at(
+                    keyword,
+                    IssueKind::Dropped,
+                    format!("unsupported keyword {:?}", keyword),
+                );
+            }
+        }
+
+        (form, extra)
+    }
+
+    fn convert_form(&mut self, obj: &Map<String, Value>, used: &mut HashSet<&'static str>) -> Form {
+        if let Some(rxf) = obj.get("$ref") {
+            used.insert("$ref");
+            if let Some(def) = rxf.as_str().and_then(|rxf| self.parse_ref(rxf)) {
+                return Form::Ref(def);
+            }
+
+            self.push_at(
+                "$ref",
+                IssueKind::Approximated,
+                "only references to definitions in the same document are supported; replaced with the empty schema".to_owned(),
+            );
+            return Form::Empty;
+        }
+
+        for keyword in &["enum", "const"] {
+            if let Some(value) = obj.get(*keyword) {
+                used.insert(*keyword);
+                if obj.get("type") == Some(&json!("string")) {
+                    used.insert("type");
+                }
+
+                let values: Option<IndexSet<String>> = match value {
+                    Value::Array(values) => values
+                        .iter()
+                        .map(|value| value.as_str().map(str::to_owned))
+                        .collect(),
+                    Value::String(value) => Some(vec![value.clone()].into_iter().collect()),
+                    _ => None,
+                };
+
+                match values {
+                    Some(ref values) if !values.is_empty() => {
+                        return Form::Enum(values.clone());
+                    }
+                    _ => {
+                        self.push_at(
+                            keyword,
+                            IssueKind::Approximated,
+                            "only strings are supported; replaced with the empty schema".to_owned(),
+                        );
+                        return Form::Empty;
+                    }
+                }
+            }
+        }
+
+        for keyword in &["oneOf", "anyOf"] {
+            if let Some(alternatives) = obj.get(*keyword) {
+                used.insert(*keyword);
+                if let Some(form) = self.convert_discriminator(obj, keyword, alternatives, used) {
+                    return form;
+                }
+
+                self.push_at(
+                    keyword,
+                    IssueKind::Approximated,
+                    "alternatives without a common tag aren't supported; replaced with the empty schema".to_owned(),
+                );
+                return Form::Empty;
+            }
+        }
+
+        let typ = match obj.get("type") {
+            Some(Value::String(typ)) => {
+                used.insert("type");
+                typ.as_str()
+            }
+            Some(_) => {
+                used.insert("type");
+                self.push_at(
+                    "type",
+                    IssueKind::Approximated,
+                    "multiple types aren't supported; replaced with the empty schema".to_owned(),
+                );
+                return Form::Empty;
+            }
+            None => {
+                if ["properties", "required", "additionalProperties"]
+                    .iter()
+                    .any(|keyword| obj.contains_key(*keyword))
+                {
+                    "object"
+                } else if obj.contains_key("items") {
+                    "array"
+                } else {
+                    return Form::Empty;
+                }
+            }
+        };
+
+        match typ {
+            "boolean" => Form::Type(Type::Boolean),
+            "number" => Form::Type(Type::Float64),
+            "integer" => self.convert_integer(obj, used),
+            "string" => {
+                if obj.get("format") == Some(&json!("date-time")) {
+                    used.insert("format");
+                    Form::Type(Type::Timestamp)
+                } else {
+                    Form::Type(Type::String)
+                }
+            }
+            "array" => match obj.get("items") {
+                Some(items) => {
+                    used.insert("items");
+                    Form::Elements(self.convert_at("items", items))
+                }
+                None => Form::Elements(empty()),
+            },
+            "object" => self.convert_object(obj, used),
+            _ => {
+                self.push_at(
+                    "type",
+                    IssueKind::Approximated,
+                    format!(
+                        "type {:?} isn't supported; replaced with the empty schema",
+                        typ
+                    ),
+                );
+                Form::Empty
+            }
+        }
+    }
+
+    fn convert_integer(
+        &mut self,
+        obj: &Map<String, Value>,
+        used: &mut HashSet<&'static str>,
+    ) -> Form {
+        let mut bound = |keyword: &'static str| {
+            let value = obj.get(keyword).and_then(Value::as_f64);
+            if value.is_some() {
+                used.insert(keyword);
+            }
+
+            value
+        };
+
+        let min = max_of(
+            bound("minimum").map(f64::ceil),
+            bound("exclusiveMinimum").map(|min| min.floor() + 1.0),
+        );
+        let max = min_of(
+            bound("maximum").map(f64::floor),
+            bound("exclusiveMaximum").map(|max| max.ceil() - 1.0),
+        );
+
+        if let (Some(min), Some(max)) = (min, max) {
+            for typ in INTEGER_TYPES {
+                let (lo, hi) = integer_range(typ).unwrap();
+                if lo as f64 <= min && max <= hi as f64 {
+                    if lo as f64 != min || max != hi as f64 {
+                        self.push(
+                            IssueKind::Approximated,
+                            format!(
+                                "bounds were widened to those of {}",
+                                format!("{:?}", typ).to_lowercase()
+                            ),
+                        );
+                    }
+
+                    return Form::Type(typ.clone());
+                }
+            }
+        }
+
+        self.push(
+            IssueKind::Approximated,
+            "no integer type covers the range of this integer; replaced with float64".to_owned(),
+        );
+        Form::Type(Type::Float64)
+    }
+
+    fn convert_object(
+        &mut self,
+        obj: &Map<String, Value>,
+        used: &mut HashSet<&'static str>,
+    ) -> Form {
+        let props = obj.get("properties").and_then(Value::as_object);
+        let required_names: Vec<&str> = obj
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|names| names.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        if props.is_some() {
+            used.insert("properties");
+        }
+
+        if obj.contains_key("required") {
+            used.insert("required");
+        }
+
+        let additional = obj.get("additionalProperties");
+        if additional.is_some() {
+            used.insert("additionalProperties");
+        }
+
+        let allow_additional = match additional {
+            None | Some(Value::Bool(true)) => true,
+            Some(Value::Bool(false)) => false,
+            Some(values) => {
+                if props.is_none() && required_names.is_empty() {
+                    return Form::Values(self.convert_at("additionalProperties", values));
+                }
+
+                self.push_at(
+                    "additionalProperties",
+                    IssueKind::Approximated,
+                    "a schema for additional properties alongside other properties isn't supported; any additional properties are allowed".to_owned(),
+                );
+                true
+            }
+        };
+
+        let mut required = IndexMap::new();
+        let mut optional = IndexMap::new();
+
+        self.tokens.push("properties".to_owned());
+        for (name, sub_schema) in props.into_iter().flatten() {
+            let sub_schema = self.convert_at(name, sub_schema);
+            if required_names.contains(&name.as_str()) {
+                required.insert(name.clone(), sub_schema);
+            } else {
+                optional.insert(name.clone(), sub_schema);
+            }
+        }
+        self.tokens.pop();
+
+        // In JSON Schema, a required property needn't have a schema.
+        for name in required_names {
+            if !required.contains_key(name) {
+                required.insert(name.to_owned(), empty());
+            }
+        }
+
+        Form::Properties {
+            has_required: !required.is_empty() || optional.is_empty(),
+            required,
+            optional,
+            allow_additional,
+        }
+    }
+
+    /// Convert `oneOf` or `anyOf` into a discriminator, if every alternative
+    /// has a common property with a distinct `const` string value.
+    fn convert_discriminator(
+        &mut self,
+        obj: &Map<String, Value>,
+        keyword: &str,
+        alternatives: &Value,
+        used: &mut HashSet<&'static str>,
+    ) -> Option<Form> {
+        let alternatives: Vec<&Map<String, Value>> = alternatives
+            .as_array()?
+            .iter()
+            .map(Value::as_object)
+            .collect::<Option<_>>()?;
+
+        let is_tag = |tag: &str| {
+            let values: Option<HashSet<&str>> = alternatives
+                .iter()
+                .map(|alternative| tag_value(alternative, tag))
+                .collect();
+
+            values.map(|values| values.len()) == Some(alternatives.len())
+        };
+
+        let property_name = obj
+            .get("discriminator")
+            .and_then(|discriminator| discriminator.get("propertyName"))
+            .and_then(Value::as_str);
+
+        let tag = match property_name {
+            Some(tag) if is_tag(tag) => tag.to_owned(),
+            _ => alternatives
+                .first()?
+                .get("properties")?
+                .as_object()?
+                .keys()
+                .find(|tag| is_tag(tag))?
+                .clone(),
+        };
+
+        // Keywords which only restate the tag are implied by the
+        // discriminator, as is OpenAPI's discriminator keyword.
+        if obj.get("type") == Some(&json!("object")) {
+            used.insert("type");
+        }
+
+        if let Some(Value::Object(props)) = obj.get("properties") {
+            if props.keys().all(|name| *name == tag) {
+                used.insert("properties");
+            }
+        }
+
+        if let Some(Value::Array(names)) = obj.get("required") {
+            if names.iter().all(|name| *name == *tag) {
+                used.insert("required");
+            }
+        }
+
+        if obj.get("discriminator").and_then(|d| d.get("propertyName")) == Some(&json!(tag)) {
+            used.insert("discriminator");
+        }
+
+        let mut mapping = IndexMap::new();
+        self.tokens.push(keyword.to_owned());
+        for (i, alternative) in alternatives.iter().enumerate() {
+            let value = tag_value(alternative, &tag).unwrap().to_owned();
+
+            let mut alternative = (*alternative).clone();
+            if let Some(Value::Object(props)) = alternative.get_mut("properties") {
+                props.remove(&tag);
+            }
+
+            if let Some(Value::Array(names)) = alternative.get_mut("required") {
+                names.retain(|name| *name != *tag);
+            }
+
+            self.tokens.push(i.to_string());
+            let (form, extra) = self.convert(&Value::Object(alternative), false);
+            let form = match form {
+                Form::Properties { .. } => form,
+                _ => {
+                    self.push(
+                        IssueKind::Approximated,
+                        "alternatives must be objects with properties; replaced with one allowing any properties".to_owned(),
+                    );
+
+                    Form::Properties {
+                        required: IndexMap::new(),
+                        optional: IndexMap::new(),
+                        allow_additional: true,
+                        has_required: true,
+                    }
+                }
+            };
+            self.tokens.pop();
+
+            mapping.insert(value, Schema::from_parts(None, Box::new(form), extra));
+        }
+        self.tokens.pop();
+
+        Some(Form::Discriminator(tag, mapping))
+    }
+
+    fn convert_at(&mut self, token: &str, value: &Value) -> Schema {
+        self.tokens.push(token.to_owned());
+        let (form, extra) = self.convert(value, false);
+        self.tokens.pop();
+
+        Schema::from_parts(None, Box::new(form), extra)
+    }
+
+    /// The name of the definition `rxf` refers to, if it refers to one.
+    fn parse_ref(&self, rxf: &str) -> Option<String> {
+        let pointer = percent_decode(rxf.strip_prefix('#')?)?;
+        let name = pointer
+            .strip_prefix("/$defs/")
+            .or_else(|| pointer.strip_prefix("/definitions/"))?;
+
+        if name.contains('/') {
+            return None;
+        }
+
+        let name = name.replace("~1", "/").replace("~0", "~");
+        if self.defs.contains(&name) {
+            Some(name)
+        } else {
+            None
+        }
+    }
+
+    fn push(&mut self, kind: IssueKind, message: String) {
+        self.issues.push(Issue {
+            kind,
+            path: JsonPointer::new(self.tokens.clone()),
+            message,
+        });
+    }
+
+    fn push_at(&mut self, token: &str, kind: IssueKind, message: String) {
+        self.tokens.push(token.to_owned());
+        self.push(kind, message);
+        self.tokens.pop();
+    }
+}
+
+/// The `const` value of `tag` in an alternative of a `oneOf` or `anyOf`.
+fn tag_value<'a>(alternative: &'a Map<String, Value>, tag: &str) -> Option<&'a str> {
+    let prop = alternative.get("properties")?.get(tag)?;
+    let value = match prop.get("const") {
+        Some(value) => value,
+        None => match prop.get("enum")?.as_array()?.as_slice() {
+            [value] => value,
+            _ => return None,
+        },
+    };
+
+    value.as_str()
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let mut out = vec![];
+    let mut bytes = s.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            out.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            out.push(byte);
+        }
+    }
+
+    String::from_utf8(out).ok()
+}
+
+fn max_of(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        _ => a.or(b),
+    }
+}
+
+fn min_of(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        _ => a.or(b),
+    }
+}
+
+fn empty() -> Schema {
+    Schema::from_parts(None, Box::new(Form::Empty), IndexMap::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::schema;
+
+    fn export_json(value: Value) -> Value {
+        export(&schema(value))
+    }
+
+    fn import_json(json_schema: Value) -> (Value, Vec<(IssueKind, String)>) {
+        let import = import(&json_schema);
+        let issues = import
+            .issues()
+            .iter()
+            .map(|issue| (issue.kind(), issue.path().to_string()))
+            .collect();
+
+        let schema = import.into_schema().into_serde();
+
+        // The imported schema must itself be valid.
+        Schema::from_serde(schema.clone()).unwrap();
+        (serde_json::to_value(schema).unwrap(), issues)
+    }
+
+    #[test]
+    fn types() {
+        let cases = vec![
+            ("boolean", json!({ "type": "boolean" })),
+            ("float32", json!({ "type": "number" })),
+            ("float64", json!({ "type": "number" })),
+            (
+                "int8",
+                json!({ "type": "integer", "minimum": -128, "maximum": 127 }),
+            ),
+            (
+                "uint8",
+                json!({ "type": "integer", "minimum": 0, "maximum": 255 }),
+            ),
+            (
+                "int16",
+                json!({ "type": "integer", "minimum": -32768, "maximum": 32767 }),
+            ),
+            (
+                "uint16",
+                json!({ "type": "integer", "minimum": 0, "maximum": 65535 }),
+            ),
+            (
+                "int32",
+                json!({ "type": "integer", "minimum": -2147483648i64, "maximum": 2147483647 }),
+            ),
+            (
+                "uint32",
+                json!({ "type": "integer", "minimum": 0, "maximum": 4294967295u32 }),
+            ),
+            ("string", json!({ "type": "string" })),
+            (
+                "timestamp",
+                json!({ "type": "string", "format": "date-time" }),
+            ),
+        ];
+
+        for (typ, mut expected) in cases {
+            expected["$schema"] = DRAFT_2020_12.into();
+            assert_eq!(export_json(json!({ "type": typ })), expected);
+        }
+    }
+
+    #[test]
+    fn forms() {
+        assert_eq!(
+            export_json(json!({
+                "definitions": {
+                    "a b/c": { "enum": ["X", "Y"] },
+                },
+                "metadata": { "description": "A thing." },
+                "properties": {
+                    "a": { "ref": "a b/c" },
+                    "b": { "elements": {} },
+                },
+                "optionalProperties": {
+                    "c": { "values": { "type": "string" } },
+                },
+            })),
+            json!({
+                "$schema": DRAFT_2020_12,
+                "$defs": {
+                    "a b/c": { "enum": ["X", "Y"] },
+                },
+                "description": "A thing.",
+                "type": "object",
+                "properties": {
+                    "a": { "$ref": "#/$defs/a%20b~1c" },
+                    "b": { "type": "array", "items": {} },
+                    "c": {
+                        "type": "object",
+                        "additionalProperties": { "type": "string" },
+                    },
+                },
+                "required": ["a", "b"],
+                "additionalProperties": false,
+            })
+        );
+
+        assert_eq!(
+            export_json(json!({ "properties": {}, "additionalProperties": true })),
+            json!({ "$schema": DRAFT_2020_12, "type": "object" })
+        );
+    }
+
+    #[test]
+    fn discriminators() {
+        assert_eq!(
+            export_json(json!({
+                "discriminator": {
+                    "tag": "kind",
+                    "mapping": {
+                        "a": { "properties": { "x": { "type": "string" } } },
+                        "b": { "properties": {}, "additionalProperties": true },
+                    },
+                },
+            })),
+            json!({
+                "$schema": DRAFT_2020_12,
+                "type": "object",
+                "properties": { "kind": { "type": "string" } },
+                "required": ["kind"],
+                "oneOf": [
+                    {
+                        "type": "object",
+                        "properties": {
+                            "kind": { "const": "a" },
+                            "x": { "type": "string" },
+                        },
+                        "required": ["kind", "x"],
+                        "additionalProperties": false,
+                    },
+                    {
+                        "type": "object",
+                        "properties": { "kind": { "const": "b" } },
+                        "required": ["kind"],
+                    },
+                ],
+            })
+        );
+
+        assert_eq!(
+            export_json(json!({ "discriminator": { "tag": "kind", "mapping": {} } })),
+            json!({ "$schema": DRAFT_2020_12, "not": {} })
+        );
+    }
+
+    #[test]
+    fn import_exact() {
+        let json_schema = json!({
+            "$schema": DRAFT_2020_12,
+            "$defs": {
+                "a b/c": { "enum": ["X", "Y"] },
+                "id": { "type": "string", "description": "An ID." },
+            },
+            "type": "object",
+            "properties": {
+                "a": { "$ref": "#/$defs/a%20b~1c" },
+                "id": { "$ref": "#/$defs/id" },
+                "at": { "type": "string", "format": "date-time" },
+                "tags": { "type": "array", "items": { "const": "x" } },
+                "counts": { "type": "object", "additionalProperties": { "type": "number" } },
+                "small": { "type": "integer", "minimum": -128, "maximum": 127 },
+                "any": true,
+            },
+            "required": ["a", "id", "extra"],
+            "additionalProperties": false,
+        });
+
+        assert_eq!(
+            import_json(json_schema),
+            (
+                json!({
+                    "definitions": {
+                        "a b/c": { "enum": ["X", "Y"] },
+                        "id": { "type": "string", "metadata": { "description": "An ID." } },
+                    },
+                    "properties": {
+                        "a": { "ref": "a b/c" },
+                        "id": { "ref": "id" },
+                        "extra": {},
+                    },
+                    "optionalProperties": {
+                        "at": { "type": "timestamp" },
+                        "tags": { "elements": { "enum": ["x"] } },
+                        "counts": { "values": { "type": "float64" } },
+                        "small": { "type": "int8" },
+                        "any": {},
+                    },
+                }),
+                vec![]
+            )
+        );
+
+        // Exporting and re-importing is lossless.
+        let schema = json!({
+            "definitions": { "a": { "type": "uint16" } },
+            "discriminator": {
+                "tag": "kind",
+                "mapping": {
+                    "x": { "properties": { "a": { "ref": "a" } } },
+                    "y": { "properties": {}, "additionalProperties": true },
+                },
+            },
+        });
+
+        assert_eq!(import_json(export_json(schema.clone())), (schema, vec![]));
+    }
+
+    #[test]
+    fn import_lossy() {
+        let (schema, issues) = import_json(json!({
+            "type": "object",
+            "properties": {
+                "age": { "type": "integer", "minimum": 0, "maximum": 150 },
+                "big": { "type": "integer" },
+                "name": { "type": "string", "minLength": 1 },
+                "either": { "type": ["string", "null"] },
+                "other": { "$ref": "other.json#/$defs/a" },
+                "map": {
+                    "properties": { "a": {} },
+                    "additionalProperties": { "type": "string" },
+                },
+                "shape": {
+                    "oneOf": [
+                        { "properties": { "kind": { "const": "a" } } },
+                        { "properties": { "kind": { "const": "b" } }, "items": {} },
+                    ],
+                },
+                "union": { "anyOf": [{ "type": "string" }, { "type": "number" }] },
+                "never": false,
+            },
+            "examples": [{}],
+        }));
+
+        assert_eq!(
+            schema,
+            json!({
+                "definitions": {},
+                "optionalProperties": {
+                    "age": { "type": "uint8" },
+                    "big": { "type": "float64" },
+                    "name": { "type": "string" },
+                    "either": {},
+                    "other": {},
+                    "map": {
+                        "optionalProperties": { "a": {} },
+                        "additionalProperties": true,
+                    },
+                    "shape": {
+                        "discriminator": {
+                            "tag": "kind",
+                            "mapping": {
+                                "a": { "properties": {}, "additionalProperties": true },
+                                "b": { "properties": {}, "additionalProperties": true },
+                            },
+                        },
+                    },
+                    "union": {},
+                    "never": {},
+                },
+                "additionalProperties": true,
+            })
+        );
+
+        assert_eq!(
+            issues,
+            vec![
+                (IssueKind::Approximated, "/properties/age".to_owned()),
+                (IssueKind::Approximated, "/properties/big".to_owned()),
+                (
+                    IssueKind::Approximated,
+                    "/properties/either/type".to_owned()
+                ),
+                (
+                    IssueKind::Approximated,
+                    "/properties/map/additionalProperties".to_owned()
+                ),
+                (IssueKind::Dropped, "/properties/name/minLength".to_owned()),
+                (IssueKind::Approximated, "/properties/never".to_owned()),
+                (IssueKind::Approximated, "/properties/other/$ref".to_owned()),
+                (
+                    IssueKind::Dropped,
+                    "/properties/shape/oneOf/1/items".to_owned()
+                ),
+                (
+                    IssueKind::Approximated,
+                    "/properties/union/anyOf".to_owned()
+                ),
+                (IssueKind::Dropped, "/examples".to_owned()),
+            ]
+        );
+    }
+}
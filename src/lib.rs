@@ -65,10 +65,9 @@
 //!     // Each ValidationError holds paths to the bad part of the input, as
 //!     // well as the part of the schema which rejected it.
 //!     //
-//!     // For testing purposes, we'll sort the errors so that their order is
-//!     // predictable.
-//!     let mut validation_errors_bad = validator.validate(&demo_schema, &input_bad)?;
-//!     validation_errors_bad.sort_by_key(|err| err.instance_path().to_string());
+//!     // Errors are returned in a stable order, following the order in which
+//!     // properties were declared in the schema.
+//!     let validation_errors_bad = validator.validate(&demo_schema, &input_bad)?;
 //!     assert_eq!(validation_errors_bad.len(), 3);
 //!
 //!     // "name" is required
@@ -111,12 +110,32 @@
 //!
 //! [jsl-docs]: http://json-schema-language.github.io/docs
 
+#[cfg(test)]
+mod test_util;
 mod vm;
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod binary;
+pub mod bundle;
+pub mod canonical;
+pub mod codegen;
+pub mod compat;
+pub mod diff;
 pub mod errors;
+pub mod gen;
+pub mod json_schema;
+pub mod lint;
+pub mod openapi;
+pub mod reflect;
+pub mod registry;
 pub mod schema;
 pub mod validator;
 
 pub use crate::errors::JddfError;
+pub use crate::reflect::JddfSchema;
 pub use crate::schema::{Form, Schema, Serde as SerdeSchema, Type};
 pub use crate::validator::{Config, ValidationError, Validator};
+
+#[cfg(feature = "derive")]
+pub use jddf_derive::JddfSchema;
@@ -0,0 +1,545 @@
+//! Check schemas for likely mistakes.
+//!
+//! This module contains a *linter*, which inspects a schema and produces
+//! warnings about things that are valid JDDF, but which are probably not what
+//! the schema's author intended.
+//!
+//! See the docs for [`Linter`](struct.Linter.html) for more.
+
+use crate::schema::{Form, Schema, Type};
+use indexmap::IndexMap;
+use json_pointer::JsonPointer;
+use std::collections::HashSet;
+
+/// Checks schemas for likely mistakes.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct Linter {
+    config: Config,
+}
+
+impl Linter {
+    /// Constructs a new linter using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new linter using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Lint a root schema.
+    ///
+    /// Warnings about definitions come first, in the order the definitions
+    /// were declared, followed by warnings about the rest of the schema.
+    pub fn lint(&self, schema: &Schema) -> Vec<Warning> {
+        let empty = IndexMap::new();
+        let defs = schema.definitions().as_ref().unwrap_or(&empty);
+
+        let mut linter = Lint {
+            config: &self.config,
+            tokens: vec![],
+            warnings: vec![],
+        };
+
+        let used = schema.reachable_definitions(defs);
+
+        linter.tokens.push("definitions".to_owned());
+        for (name, sub_schema) in defs {
+            linter.tokens.push(name.clone());
+
+            if !used.contains(name.as_str()) {
+                if sub_schema
+                    .reachable_definitions(defs)
+                    .contains(name.as_str())
+                {
+                    linter.push(
+                        Rule::SelfReferencingDefinition,
+                        format!("definition {:?} is only referred to by itself", name),
+                    );
+                } else {
+                    linter.push(
+                        Rule::UnusedDefinition,
+                        format!("definition {:?} is never used", name),
+                    );
+                }
+            }
+
+            linter.check_description(sub_schema);
+            linter.lint(sub_schema);
+            linter.tokens.pop();
+        }
+        linter.tokens.pop();
+
+        linter.check_description(schema);
+        linter.lint(schema);
+        linter.warnings
+    }
+}
+
+/// Configuration for which lint rules to apply.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct Config {
+    disabled: HashSet<Rule>,
+    naming_convention: Option<NamingConvention>,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    ///
+    /// By default, every rule is enabled. However,
+    /// [`Rule::PropertyNaming`](enum.Rule.html#variant.PropertyNaming) has no
+    /// effect until a naming convention is configured.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables a rule.
+    pub fn enable(&mut self, rule: Rule) -> &mut Self {
+        self.disabled.remove(&rule);
+        self
+    }
+
+    /// Disables a rule.
+    pub fn disable(&mut self, rule: Rule) -> &mut Self {
+        self.disabled.insert(rule);
+        self
+    }
+
+    /// Sets the naming convention that property names are expected to follow.
+    /// `None`, the default value, indicates that property names should not be
+    /// checked.
+    pub fn naming_convention(&mut self, convention: Option<NamingConvention>) -> &mut Self {
+        self.naming_convention = convention;
+        self
+    }
+
+    fn is_enabled(&self, rule: Rule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+}
+
+/// The rules a linter can check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// A definition is never referred to, neither by the root schema nor by
+    /// any definition it uses.
+    UnusedDefinition,
+
+    /// A definition is not used by the root schema, and is referred to only by
+    /// itself (possibly through other unused definitions).
+    SelfReferencingDefinition,
+
+    /// A `values` schema accepts any value. Often, the author meant to be more
+    /// specific.
+    EmptyValues,
+
+    /// An `enum` has only one value. A value which can't vary tells readers
+    /// nothing; if it's there to tell kinds of object apart, a
+    /// `discriminator` says so directly.
+    SingleValueEnum,
+
+    /// A property of type `float32` or `float64` has a name which suggests its
+    /// values are integers, such as `count` or `userId`.
+    FloatForInteger,
+
+    /// The same property appears in more than one mapping of a discriminator,
+    /// with different schemas in each.
+    OverlappingDiscriminatorMappings,
+
+    /// The root schema, a definition, or a property does not have a
+    /// `description` in its `metadata`.
+    MissingDescription,
+
+    /// A property's name doesn't follow the configured naming convention.
+    PropertyNaming,
+}
+
+/// Conventions for how property names are formatted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NamingConvention {
+    /// Names like `fooBar`.
+    CamelCase,
+
+    /// Names like `FooBar`.
+    PascalCase,
+
+    /// Names like `foo_bar`.
+    SnakeCase,
+
+    /// Names like `foo-bar`.
+    KebabCase,
+}
+
+impl NamingConvention {
+    /// Whether a name follows this convention.
+    pub fn matches(self, name: &str) -> bool {
+        let mut chars = name.chars();
+        let first = match chars.next() {
+            Some(c) => c,
+            None => return false,
+        };
+
+        match self {
+            NamingConvention::CamelCase => {
+                first.is_ascii_lowercase() && chars.all(|c| c.is_ascii_alphanumeric())
+            }
+            NamingConvention::PascalCase => {
+                first.is_ascii_uppercase() && chars.all(|c| c.is_ascii_alphanumeric())
+            }
+            NamingConvention::SnakeCase => is_separated(name, '_'),
+            NamingConvention::KebabCase => is_separated(name, '-'),
+        }
+    }
+}
+
+fn is_separated(name: &str, separator: char) -> bool {
+    name.split(separator).all(|word| {
+        word.starts_with(|c: char| c.is_ascii_lowercase())
+            && word
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    })
+}
+
+/// A single problem found by a linter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    rule: Rule,
+    schema_path: JsonPointer<String, Vec<String>>,
+    message: String,
+}
+
+impl Warning {
+    /// The rule which produced this warning.
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// A pointer into the part of the schema the warning is about.
+    pub fn schema_path(&self) -> &JsonPointer<String, Vec<String>> {
+        &self.schema_path
+    }
+
+    /// A human-readable description of the problem.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Words which, at the end of a property name, suggest the property holds an
+/// integer.
+const INTEGER_WORDS: &[&str] = &[
+    "age", "count", "id", "idx", "index", "len", "length", "num", "number", "port", "size",
+    "total", "year",
+];
+
+struct Lint<'a> {
+    config: &'a Config,
+    tokens: Vec<String>,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> Lint<'a> {
+    fn lint(&mut self, schema: &Schema) {
+        match schema.form() {
+            Form::Enum(values) if values.len() == 1 => {
+                self.tokens.push("enum".to_owned());
+                self.push(
+                    Rule::SingleValueEnum,
+                    "enum has only one value; consider removing it, or using a discriminator \
+                     if it tells kinds of object apart"
+                        .to_owned(),
+                );
+                self.tokens.pop();
+            }
+            Form::Elements(sub_schema) => {
+                self.tokens.push("elements".to_owned());
+                self.lint(sub_schema);
+                self.tokens.pop();
+            }
+            Form::Properties {
+                required, optional, ..
+            } => {
+                self.lint_properties("properties", required);
+                self.lint_properties("optionalProperties", optional);
+            }
+            Form::Values(sub_schema) => {
+                self.tokens.push("values".to_owned());
+                if let Form::Empty = sub_schema.form() {
+                    self.push(
+                        Rule::EmptyValues,
+                        "values accepts any value; consider a more specific schema".to_owned(),
+                    );
+                }
+                self.lint(sub_schema);
+                self.tokens.pop();
+            }
+            Form::Discriminator(_, mapping) => {
+                self.tokens.push("discriminator".to_owned());
+                self.tokens.push("mapping".to_owned());
+                self.check_overlap(mapping);
+                for (value, sub_schema) in mapping {
+                    self.tokens.push(value.clone());
+                    self.lint(sub_schema);
+                    self.tokens.pop();
+                }
+                self.tokens.pop();
+                self.tokens.pop();
+            }
+            _ => {}
+        }
+    }
+
+    fn lint_properties(&mut self, keyword: &str, properties: &IndexMap<String, Schema>) {
+        self.tokens.push(keyword.to_owned());
+        for (name, sub_schema) in properties {
+            self.tokens.push(name.clone());
+
+            if let Some(convention) = self.config.naming_convention {
+                if !convention.matches(name) {
+                    self.push(
+                        Rule::PropertyNaming,
+                        format!("property {:?} does not follow {:?}", name, convention),
+                    );
+                }
+            }
+
+            if let Form::Type(Type::Float32) | Form::Type(Type::Float64) = sub_schema.form() {
+                if looks_like_integer(name) {
+                    self.tokens.push("type".to_owned());
+                    self.push(
+                        Rule::FloatForInteger,
+                        format!(
+                            "property {:?} is a float, but its name suggests an integer",
+                            name
+                        ),
+                    );
+                    self.tokens.pop();
+                }
+            }
+
+            self.check_description(sub_schema);
+            self.lint(sub_schema);
+            self.tokens.pop();
+        }
+        self.tokens.pop();
+    }
+
+    fn check_overlap(&mut self, mapping: &IndexMap<String, Schema>) {
+        let mut seen: IndexMap<&str, (&str, &Schema)> = IndexMap::new();
+        for (value, sub_schema) in mapping {
+            if let Form::Properties {
+                required, optional, ..
+            } = sub_schema.form()
+            {
+                for (name, prop_schema) in required.iter().chain(optional) {
+                    match seen.get(name.as_str()) {
+                        Some((other_value, other_schema)) => {
+                            if *other_schema != prop_schema {
+                                self.tokens.push(value.clone());
+                                self.push(
+                                    Rule::OverlappingDiscriminatorMappings,
+                                    format!(
+                                        "property {:?} has a different schema in mapping {:?}",
+                                        name, other_value
+                                    ),
+                                );
+                                self.tokens.pop();
+                            }
+                        }
+                        None => {
+                            seen.insert(name, (value, prop_schema));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_description(&mut self, schema: &Schema) {
+        let has_description = schema
+            .extra()
+            .get("metadata")
+            .and_then(|metadata| metadata.get("description"))
+            .is_some();
+
+        if !has_description {
+            self.push(
+                Rule::MissingDescription,
+                "schema has no metadata.description".to_owned(),
+            );
+        }
+    }
+
+    fn push(&mut self, rule: Rule, message: String) {
+        if self.config.is_enabled(rule) {
+            self.warnings.push(Warning {
+                rule,
+                schema_path: JsonPointer::new(self.tokens.clone()),
+                message,
+            });
+        }
+    }
+}
+
+fn looks_like_integer(name: &str) -> bool {
+    // Find the last word in the name, whether it's camelCase, PascalCase,
+    // snake_case, or kebab-case.
+    let last_word = match name.rfind(|c: char| c.is_ascii_uppercase() || c == '_' || c == '-') {
+        Some(i) => name[i..].trim_start_matches(&['_', '-'][..]),
+        None => name,
+    };
+
+    INTEGER_WORDS.contains(&last_word.to_ascii_lowercase().as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::schema;
+    use serde_json::{json, Value};
+
+    fn lint(config: Config, value: Value) -> Vec<(Rule, String)> {
+        let schema = schema(value);
+        Linter::new_with_config(config)
+            .lint(&schema)
+            .into_iter()
+            .map(|w| (w.rule(), w.schema_path().to_string()))
+            .collect()
+    }
+
+    fn without_descriptions() -> Config {
+        let mut config = Config::new();
+        config.disable(Rule::MissingDescription);
+        config
+    }
+
+    #[test]
+    fn definitions() {
+        assert_eq!(
+            lint(
+                without_descriptions(),
+                json!({
+                    "definitions": {
+                        "used": { "ref": "transitive" },
+                        "transitive": {},
+                        "unused": {},
+                        "loop": { "elements": { "ref": "loop" } },
+                        "recursive": { "elements": { "ref": "recursive" } },
+                    },
+                    "properties": {
+                        "u": { "ref": "used" },
+                        "r": { "ref": "recursive" },
+                    },
+                })
+            ),
+            vec![
+                (
+                    Rule::SelfReferencingDefinition,
+                    "/definitions/loop".to_owned()
+                ),
+                (Rule::UnusedDefinition, "/definitions/unused".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn forms() {
+        assert_eq!(
+            lint(
+                without_descriptions(),
+                json!({
+                    "properties": {
+                        "a": { "values": {} },
+                        "b": { "enum": ["ONLY"] },
+                        "userId": { "type": "float64" },
+                        "item_count": { "type": "float32" },
+                        "price": { "type": "float64" },
+                        "c": {
+                            "discriminator": {
+                                "tag": "kind",
+                                "mapping": {
+                                    "x": { "properties": { "id": { "type": "string" }, "v": { "type": "string" } } },
+                                    "y": { "properties": { "id": { "type": "string" }, "v": { "type": "uint8" } } },
+                                },
+                            },
+                        },
+                    },
+                })
+            ),
+            vec![
+                (Rule::EmptyValues, "/properties/a/values".to_owned()),
+                (Rule::SingleValueEnum, "/properties/b/enum".to_owned()),
+                (
+                    Rule::OverlappingDiscriminatorMappings,
+                    "/properties/c/discriminator/mapping/y".to_owned()
+                ),
+                (
+                    Rule::FloatForInteger,
+                    "/properties/item_count/type".to_owned()
+                ),
+                (Rule::FloatForInteger, "/properties/userId/type".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn descriptions() {
+        assert_eq!(
+            lint(
+                Config::new(),
+                json!({
+                    "definitions": {
+                        "a": { "metadata": { "description": "A" } },
+                        "b": {},
+                    },
+                    "properties": {
+                        "x": { "ref": "a", "metadata": { "description": "X" } },
+                        "y": { "ref": "b" },
+                    },
+                    "metadata": { "description": "Root" },
+                })
+            ),
+            vec![
+                (Rule::MissingDescription, "/definitions/b".to_owned()),
+                (Rule::MissingDescription, "/properties/y".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn naming() {
+        let cases = vec![
+            (NamingConvention::CamelCase, "fooBar2", "foo_bar"),
+            (NamingConvention::PascalCase, "FooBar", "fooBar"),
+            (NamingConvention::SnakeCase, "foo_bar2", "fooBar"),
+            (NamingConvention::KebabCase, "foo-bar", "foo--bar"),
+        ];
+
+        for (convention, good, bad) in cases {
+            let mut config = without_descriptions();
+            config.naming_convention(Some(convention));
+
+            assert_eq!(
+                lint(
+                    config,
+                    json!({ "optionalProperties": { good: {}, bad: {} } })
+                ),
+                vec![(
+                    Rule::PropertyNaming,
+                    JsonPointer::new(vec!["optionalProperties", bad]).to_string()
+                )]
+            );
+        }
+
+        // Without a convention, names aren't checked.
+        assert_eq!(
+            lint(
+                without_descriptions(),
+                json!({ "properties": { "Foo-bar_baz": {} } })
+            ),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,723 @@
+//! Convert schemas to and from OpenAPI components.
+//!
+//! [OpenAPI](https://www.openapis.org) documents describe the data in their
+//! requests and responses with schemas under `components/schemas`. This module
+//! converts the definitions of a JDDF schema into such components, so that
+//! JDDF can stay the source of truth for an API's data, and converts existing
+//! components back into JDDF.
+//!
+//! OpenAPI 3.0 schemas are a dialect of an older JSON Schema draft, while
+//! OpenAPI 3.1 schemas are JSON Schema draft 2020-12. Both are supported; see
+//! [`Version`](enum.Version.html).
+
+use crate::json_schema::{self, Import, Issue, IssueKind};
+use crate::schema::{Form, Schema, Type};
+use indexmap::IndexMap;
+use json_pointer::JsonPointer;
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+
+/// The versions of OpenAPI supported by this module.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Version {
+    /// OpenAPI 3.0.
+    ///
+    /// `$ref`s can't have sibling keywords, and there's no `const` keyword.
+    V3_0,
+
+    /// OpenAPI 3.1.
+    V3_1,
+}
+
+/// Convert the definitions of a root schema into an OpenAPI Components Object.
+///
+/// Each definition becomes an entry in `schemas`, and is converted in much the
+/// same way as [`json_schema::export`](../json_schema/fn.export.html) does.
+/// The root schema itself isn't part of the output.
+///
+/// Component names may only contain letters, digits, `.`, `-`, and `_`. Other
+/// characters in definition names are replaced with `_`.
+///
+/// OpenAPI tools only understand discriminators whose alternatives are
+/// `$ref`s. So each value in a discriminator's `mapping` is exported as a
+/// component of its own, named after the definition, any properties leading to
+/// the discriminator, and the tag value, all joined by `_`. The discriminator
+/// itself becomes a `oneOf` of those components, with a `discriminator`
+/// object mapping each tag value to its component.
+///
+/// JDDF schemas never accept `null`, so `nullable` is never emitted.
+pub fn export(schema: &Schema, version: Version) -> Value {
+    let empty = IndexMap::new();
+    let defs = schema.definitions().as_ref().unwrap_or(&empty);
+
+    let mut exporter = Exporter {
+        version,
+        names: HashMap::new(),
+        taken: HashSet::new(),
+        path: vec![],
+        schemas: Map::new(),
+    };
+
+    for name in defs.keys() {
+        let component = exporter.unique_name(name);
+        exporter.names.insert(name.clone(), component);
+    }
+
+    for (name, sub_schema) in defs {
+        exporter.path.push(exporter.names[name].clone());
+        let out = exporter.export_schema(sub_schema, None);
+        exporter.path.pop();
+
+        let component = exporter.names[name].clone();
+        exporter.schemas.insert(component, Value::Object(out));
+    }
+
+    json!({ "schemas": exporter.schemas })
+}
+
+/// Convert the `components/schemas` of an OpenAPI document into a root
+/// schema.
+///
+/// `document` may be an entire OpenAPI document, or just its Components
+/// Object. Each component becomes a definition of the same name, and the root
+/// schema is empty.
+///
+/// The components are converted as by
+/// [`json_schema::import`](../json_schema/fn.import.html), with the following
+/// additions for OpenAPI:
+///
+/// * A `oneOf` or `anyOf` of `$ref`s with a `discriminator` becomes a JDDF
+///   `discriminator`, with each referenced component inlined into its
+///   `mapping`. The tag value for each component is taken from the
+///   `discriminator`'s `mapping`, or else is the component's name.
+/// * `nullable: true` is reported as approximated, and the schema replaced
+///   with the empty schema.
+/// * OpenAPI 3.0's boolean `exclusiveMinimum` and `exclusiveMaximum` are
+///   understood.
+/// * An `integer` with `format: int32` and no bounds becomes an `int32`, and
+///   a `number` with `format: double` becomes a `float64`.
+///
+/// The paths of issues are pointers into `document`.
+pub fn import(document: &Value) -> Import {
+    let (prefix, schemas) = match document.pointer("/components/schemas") {
+        Some(schemas) => (vec!["components", "schemas"], schemas.as_object()),
+        None => (
+            vec!["schemas"],
+            document.get("schemas").and_then(Value::as_object),
+        ),
+    };
+
+    let empty = Map::new();
+    let schemas = schemas.unwrap_or(&empty);
+
+    let mut rewriter = Rewriter {
+        schemas,
+        tokens: prefix.iter().map(|token| (*token).to_owned()).collect(),
+        issues: vec![],
+    };
+
+    let mut defs = Map::new();
+    for (name, sub_schema) in schemas {
+        rewriter.tokens.push(name.clone());
+        defs.insert(name.clone(), rewriter.rewrite(sub_schema));
+        rewriter.tokens.pop();
+    }
+
+    let mut import = json_schema::import(&json!({ "$defs": defs }));
+
+    // Issues from the JSON Schema importer are relative to the $defs built
+    // above, rather than to the components they came from.
+    for issue in &mut import.issues {
+        let mut tokens = vec![];
+        while let Some(token) = issue.path.pop() {
+            tokens.push(token);
+        }
+
+        tokens.pop();
+        tokens.extend(prefix.iter().rev().map(|token| (*token).to_owned()));
+        tokens.reverse();
+        issue.path = JsonPointer::new(tokens);
+    }
+
+    rewriter.issues.append(&mut import.issues);
+    import.issues = rewriter.issues;
+    import
+}
+
+struct Exporter {
+    version: Version,
+    names: HashMap<String, String>,
+    taken: HashSet<String>,
+    path: Vec<String>,
+    schemas: Map<String, Value>,
+}
+
+impl Exporter {
+    fn export_schema(&mut self, schema: &Schema, tag: Option<(&str, &str)>) -> Map<String, Value> {
+        let mut out = match schema.form() {
+            Form::Empty => Map::new(),
+            Form::Ref(def) => json_schema::object(json!({ "$ref": reference(&self.names[def]) })),
+            Form::Type(typ) => {
+                let mut out = json_schema::export_type(typ);
+                let format = match typ {
+                    Type::Int32 => Some("int32"),
+                    Type::Float32 => Some("float"),
+                    Type::Float64 => Some("double"),
+                    _ => None,
+                };
+
+                if let Some(format) = format {
+                    out.insert("format".to_owned(), format.into());
+                }
+
+                out
+            }
+            Form::Enum(values) => json_schema::object(json!({ "type": "string", "enum": values })),
+            Form::Elements(sub_schema) => {
+                self.path.push("item".to_owned());
+                let items = self.export_schema(sub_schema, None);
+                self.path.pop();
+
+                json_schema::object(json!({ "type": "array", "items": items }))
+            }
+            Form::Properties {
+                required,
+                optional,
+                allow_additional,
+                ..
+            } => {
+                let mut properties = Map::new();
+                let mut required_names = vec![];
+
+                if let Some((tag, value)) = tag {
+                    let tag_schema = match self.version {
+                        Version::V3_0 => json!({ "type": "string", "enum": [value] }),
+                        Version::V3_1 => json!({ "const": value }),
+                    };
+
+                    properties.insert(tag.to_owned(), tag_schema);
+                    required_names.push(tag.to_owned());
+                }
+
+                for (name, sub_schema) in required.iter().chain(optional) {
+                    self.path.push(name.clone());
+                    let sub_schema = self.export_schema(sub_schema, None);
+                    self.path.pop();
+
+                    properties.insert(name.clone(), Value::Object(sub_schema));
+                    if required.contains_key(name) {
+                        required_names.push(name.clone());
+                    }
+                }
+
+                let mut out = json_schema::object(json!({ "type": "object" }));
+                if !properties.is_empty() {
+                    out.insert("properties".to_owned(), Value::Object(properties));
+                }
+
+                if !required_names.is_empty() {
+                    out.insert("required".to_owned(), required_names.into());
+                }
+
+                if !allow_additional {
+                    out.insert("additionalProperties".to_owned(), false.into());
+                }
+
+                out
+            }
+            Form::Values(sub_schema) => {
+                self.path.push("value".to_owned());
+                let values = self.export_schema(sub_schema, None);
+                self.path.pop();
+
+                json_schema::object(json!({ "type": "object", "additionalProperties": values }))
+            }
+            Form::Discriminator(tag, mapping) => self.export_discriminator(tag, mapping),
+        };
+
+        let description = schema
+            .extra()
+            .get("metadata")
+            .and_then(|metadata| metadata.get("description"))
+            .and_then(Value::as_str);
+
+        if let Some(description) = description {
+            // In OpenAPI 3.0, keywords alongside a $ref are ignored.
+            if self.version == Version::V3_0 && out.contains_key("$ref") {
+                out = json_schema::object(json!({ "allOf": [out] }));
+            }
+
+            out.insert("description".to_owned(), description.into());
+        }
+
+        out
+    }
+
+    fn export_discriminator(
+        &mut self,
+        tag: &str,
+        mapping: &IndexMap<String, Schema>,
+    ) -> Map<String, Value> {
+        if mapping.is_empty() {
+            return json_schema::object(json!({ "not": {} }));
+        }
+
+        let mut one_of = vec![];
+        let mut refs = Map::new();
+
+        for (value, sub_schema) in mapping {
+            self.path.push(value.clone());
+            let component = self.unique_name(&self.path.join("_"));
+            let out = self.export_schema(sub_schema, Some((tag, value)));
+            self.path.pop();
+
+            self.schemas.insert(component.clone(), Value::Object(out));
+            one_of.push(json!({ "$ref": reference(&component) }));
+            refs.insert(value.clone(), reference(&component).into());
+        }
+
+        json_schema::object(json!({
+            "type": "object",
+            "oneOf": one_of,
+            "discriminator": { "propertyName": tag, "mapping": refs },
+        }))
+    }
+
+    /// Turn `name` into a valid component name which isn't yet taken.
+    fn unique_name(&mut self, name: &str) -> String {
+        let base: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        let name = if self.taken.contains(&base) {
+            (2..)
+                .map(|n| format!("{}_{}", base, n))
+                .find(|name| !self.taken.contains(name))
+                .unwrap()
+        } else {
+            base
+        };
+
+        self.taken.insert(name.clone());
+        name
+    }
+}
+
+fn reference(component: &str) -> String {
+    format!("#/components/schemas/{}", component)
+}
+
+/// Rewrites OpenAPI schemas into JSON Schema, for use with
+/// `json_schema::import`.
+struct Rewriter<'a> {
+    schemas: &'a Map<String, Value>,
+    tokens: Vec<String>,
+    issues: Vec<Issue>,
+}
+
+impl<'a> Rewriter<'a> {
+    fn rewrite(&mut self, value: &Value) -> Value {
+        let obj = match value {
+            Value::Object(obj) => obj,
+            _ => return value.clone(),
+        };
+
+        if obj.get("nullable") == Some(&Value::Bool(true)) {
+            self.tokens.push("nullable".to_owned());
+            self.issues.push(Issue {
+                kind: IssueKind::Approximated,
+                path: JsonPointer::new(self.tokens.clone()),
+                message: "nullable schemas aren't supported; replaced with the empty schema"
+                    .to_owned(),
+            });
+            self.tokens.pop();
+
+            return Value::Bool(true);
+        }
+
+        // OpenAPI 3.0 ignores keywords alongside a $ref, so a single-element
+        // allOf is the usual way to describe one. Its keywords are merged in.
+        if let Some(Value::Array(all_of)) = obj.get("allOf") {
+            if let [Value::Object(only)] = all_of.as_slice() {
+                if only.keys().all(|keyword| !obj.contains_key(keyword)) {
+                    let mut merged = obj.clone();
+                    merged.remove("allOf");
+                    merged.extend(only.clone());
+                    return self.rewrite(&Value::Object(merged));
+                }
+            }
+        }
+
+        let mut out = obj.clone();
+        out.remove("nullable");
+
+        if let Some(Value::String(rxf)) = obj.get("$ref") {
+            if let Some(name) = rxf.strip_prefix("#/components/schemas/") {
+                out.insert("$ref".to_owned(), format!("#/$defs/{}", name).into());
+            }
+        }
+
+        for (bound, exclusive) in &[
+            ("minimum", "exclusiveMinimum"),
+            ("maximum", "exclusiveMaximum"),
+        ] {
+            if let Some(Value::Bool(is_exclusive)) = obj.get(*exclusive) {
+                out.remove(*exclusive);
+                if *is_exclusive {
+                    if let Some(limit) = out.remove(*bound) {
+                        out.insert((*exclusive).to_owned(), limit);
+                    }
+                }
+            }
+        }
+
+        let has_bounds = ["minimum", "maximum", "exclusiveMinimum", "exclusiveMaximum"]
+            .iter()
+            .any(|bound| out.contains_key(*bound));
+
+        if obj.get("type") == Some(&json!("integer")) && obj.get("format") == Some(&json!("int32"))
+        {
+            out.remove("format");
+            if !has_bounds {
+                out.insert("minimum".to_owned(), i32::MIN.into());
+                out.insert("maximum".to_owned(), i32::MAX.into());
+            }
+        }
+
+        // JDDF numbers are already double-precision.
+        if obj.get("type") == Some(&json!("number")) && obj.get("format") == Some(&json!("double"))
+        {
+            out.remove("format");
+        }
+
+        let discriminator = obj.get("discriminator");
+        let tag = discriminator
+            .and_then(|discriminator| discriminator.get("propertyName"))
+            .and_then(Value::as_str);
+
+        for keyword in &["oneOf", "anyOf", "allOf"] {
+            if let Some(Value::Array(alternatives)) = obj.get(*keyword) {
+                self.tokens.push((*keyword).to_owned());
+
+                let mut rewritten = vec![];
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    self.tokens.push(i.to_string());
+
+                    let variant = match tag {
+                        Some(tag) if *keyword != "allOf" => {
+                            self.inline_variant(alternative, tag, discriminator)
+                        }
+                        _ => None,
+                    };
+
+                    rewritten.push(variant.unwrap_or_else(|| self.rewrite(alternative)));
+                    self.tokens.pop();
+                }
+
+                self.tokens.pop();
+                out.insert((*keyword).to_owned(), rewritten.into());
+            }
+        }
+
+        for keyword in &["items", "additionalProperties", "not"] {
+            if let Some(sub_schema) = obj.get(*keyword) {
+                self.tokens.push((*keyword).to_owned());
+                out.insert((*keyword).to_owned(), self.rewrite(sub_schema));
+                self.tokens.pop();
+            }
+        }
+
+        if let Some(Value::Object(props)) = obj.get("properties") {
+            self.tokens.push("properties".to_owned());
+
+            let mut rewritten = Map::new();
+            for (name, sub_schema) in props {
+                self.tokens.push(name.clone());
+                rewritten.insert(name.clone(), self.rewrite(sub_schema));
+                self.tokens.pop();
+            }
+
+            self.tokens.pop();
+            out.insert("properties".to_owned(), Value::Object(rewritten));
+        }
+
+        Value::Object(out)
+    }
+
+    /// Inline the component an alternative of a discriminated `oneOf` refers
+    /// to, with its tag property constrained to its tag value.
+    fn inline_variant(
+        &mut self,
+        alternative: &Value,
+        tag: &str,
+        discriminator: Option<&Value>,
+    ) -> Option<Value> {
+        let rxf = alternative.get("$ref")?.as_str()?;
+        let name = rxf.strip_prefix("#/components/schemas/")?;
+        let component = self.schemas.get(name)?;
+
+        let mapping = discriminator
+            .and_then(|discriminator| discriminator.get("mapping"))
+            .and_then(Value::as_object);
+        let value = mapping
+            .into_iter()
+            .flatten()
+            .find(|(_, target)| target.as_str() == Some(rxf) || target.as_str() == Some(name))
+            .map_or(name, |(value, _)| value.as_str());
+
+        // Any issues with the component are reported against the component
+        // itself, not here.
+        let issues = std::mem::take(&mut self.issues);
+        let mut variant = self.rewrite(component);
+        self.issues = issues;
+
+        let variant_obj = variant.as_object_mut()?;
+        let props = variant_obj
+            .entry("properties")
+            .or_insert_with(|| json!({}))
+            .as_object_mut()?;
+        props.insert(tag.to_owned(), json!({ "const": value }));
+
+        let required = variant_obj
+            .entry("required")
+            .or_insert_with(|| json!([]))
+            .as_array_mut()?;
+        if !required.contains(&json!(tag)) {
+            required.push(tag.into());
+        }
+
+        Some(variant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::schema;
+
+    #[test]
+    fn export_components() {
+        let schema = schema(json!({
+            "definitions": {
+                "user id": { "type": "int32" },
+                "user": {
+                    "properties": {
+                        "id": { "ref": "user id", "metadata": { "description": "The ID." } },
+                        "tags": { "elements": { "enum": ["A"] } },
+                    },
+                },
+                "event": {
+                    "discriminator": {
+                        "tag": "type",
+                        "mapping": {
+                            "created": { "properties": { "user": { "ref": "user" } } },
+                            "deleted": { "properties": {}, "additionalProperties": true },
+                        },
+                    },
+                },
+            },
+        }));
+
+        assert_eq!(
+            export(&schema, Version::V3_0),
+            json!({
+                "schemas": {
+                    "user_id": {
+                        "type": "integer",
+                        "format": "int32",
+                        "minimum": -2147483648i64,
+                        "maximum": 2147483647,
+                    },
+                    "user": {
+                        "type": "object",
+                        "properties": {
+                            "id": {
+                                "allOf": [{ "$ref": "#/components/schemas/user_id" }],
+                                "description": "The ID.",
+                            },
+                            "tags": {
+                                "type": "array",
+                                "items": { "type": "string", "enum": ["A"] },
+                            },
+                        },
+                        "required": ["id", "tags"],
+                        "additionalProperties": false,
+                    },
+                    "event": {
+                        "type": "object",
+                        "oneOf": [
+                            { "$ref": "#/components/schemas/event_created" },
+                            { "$ref": "#/components/schemas/event_deleted" },
+                        ],
+                        "discriminator": {
+                            "propertyName": "type",
+                            "mapping": {
+                                "created": "#/components/schemas/event_created",
+                                "deleted": "#/components/schemas/event_deleted",
+                            },
+                        },
+                    },
+                    "event_created": {
+                        "type": "object",
+                        "properties": {
+                            "type": { "type": "string", "enum": ["created"] },
+                            "user": { "$ref": "#/components/schemas/user" },
+                        },
+                        "required": ["type", "user"],
+                        "additionalProperties": false,
+                    },
+                    "event_deleted": {
+                        "type": "object",
+                        "properties": {
+                            "type": { "type": "string", "enum": ["deleted"] },
+                        },
+                        "required": ["type"],
+                    },
+                },
+            })
+        );
+
+        let components = export(&schema, Version::V3_1);
+        assert_eq!(
+            components["schemas"]["user"]["properties"]["id"],
+            json!({ "$ref": "#/components/schemas/user_id", "description": "The ID." })
+        );
+        assert_eq!(
+            components["schemas"]["event_deleted"]["properties"]["type"],
+            json!({ "const": "deleted" })
+        );
+
+        // Re-importing the components is lossless, apart from the extra
+        // components made for each discriminator variant.
+        for version in &[Version::V3_0, Version::V3_1] {
+            let import = import(&export(&schema, *version));
+            assert!(import.is_exact(), "{:?}", import.issues());
+
+            let defs = import.schema().definitions().as_ref().unwrap();
+            assert_eq!(
+                defs.keys().collect::<Vec<_>>(),
+                vec!["event", "event_created", "event_deleted", "user", "user_id"]
+            );
+            assert_eq!(
+                serde_json::to_value(defs["user"].clone().into_serde()).unwrap(),
+                json!({
+                    "properties": {
+                        "id": { "ref": "user_id", "metadata": { "description": "The ID." } },
+                        "tags": { "elements": { "enum": ["A"] } },
+                    },
+                })
+            );
+            assert_eq!(
+                serde_json::to_value(defs["event"].clone().into_serde()).unwrap(),
+                json!({
+                    "discriminator": {
+                        "tag": "type",
+                        "mapping": {
+                            "created": { "properties": { "user": { "ref": "user" } } },
+                            "deleted": { "properties": {}, "additionalProperties": true },
+                        },
+                    },
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn import_components() {
+        let import = import(&json!({
+            "openapi": "3.0.3",
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "oneOf": [
+                            { "$ref": "#/components/schemas/Dog" },
+                            { "$ref": "#/components/schemas/Cat" },
+                        ],
+                        "discriminator": {
+                            "propertyName": "petType",
+                            "mapping": { "dog": "#/components/schemas/Dog" },
+                        },
+                    },
+                    "Dog": {
+                        "type": "object",
+                        "properties": {
+                            "petType": { "type": "string" },
+                            "age": {
+                                "type": "integer",
+                                "minimum": 0,
+                                "maximum": 256,
+                                "exclusiveMaximum": true,
+                            },
+                        },
+                        "required": ["petType"],
+                    },
+                    "Cat": {
+                        "type": "object",
+                        "properties": {
+                            "lives": { "type": "integer", "format": "int32" },
+                            "owner": { "type": "string", "nullable": true },
+                        },
+                        "additionalProperties": false,
+                    },
+                },
+            },
+        }));
+
+        let issues: Vec<_> = import
+            .issues()
+            .iter()
+            .map(|issue| (issue.kind(), issue.path().to_string()))
+            .collect();
+
+        assert_eq!(
+            serde_json::to_value(import.into_schema().into_serde()).unwrap(),
+            json!({
+                "definitions": {
+                    "Cat": {
+                        "optionalProperties": {
+                            "lives": { "type": "int32" },
+                            "owner": {},
+                        },
+                    },
+                    "Dog": {
+                        "properties": { "petType": { "type": "string" } },
+                        "optionalProperties": { "age": { "type": "uint8" } },
+                        "additionalProperties": true,
+                    },
+                    "Pet": {
+                        "discriminator": {
+                            "tag": "petType",
+                            "mapping": {
+                                "dog": {
+                                    "optionalProperties": { "age": { "type": "uint8" } },
+                                    "additionalProperties": true,
+                                },
+                                "Cat": {
+                                    "optionalProperties": {
+                                        "lives": { "type": "int32" },
+                                        "owner": {},
+                                    },
+                                },
+                            },
+                        },
+                    },
+                },
+            })
+        );
+
+        assert_eq!(
+            issues,
+            vec![(
+                IssueKind::Approximated,
+                "/components/schemas/Cat/properties/owner/nullable".to_owned()
+            )]
+        );
+    }
+}
@@ -0,0 +1,396 @@
+//! Build schemas from Rust types.
+//!
+//! This is the reverse of [`codegen::rust`](../codegen/rust/index.html):
+//! rather than generating types from a schema, it produces a schema from
+//! types which implement [`JddfSchema`](trait.JddfSchema.html).
+//!
+//! `JddfSchema` is implemented for the primitive types JDDF can express, and
+//! for the standard containers. For your own types, enable this crate's
+//! `derive` feature and use `#[derive(JddfSchema)]`. The derive honors serde's
+//! attributes, so that the schema describes the JSON your type is serialized
+//! to:
+//!
+//! ```ignore
+//! use jddf::reflect::schema_for;
+//! use jddf::JddfSchema;
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize, JddfSchema)]
+//! #[serde(rename_all = "camelCase")]
+//! struct User {
+//!     user_id: String,
+//!     #[serde(skip_serializing_if = "Option::is_none")]
+//!     nickname: Option<String>,
+//! }
+//!
+//! // {"properties":{"userId":{"type":"string"}},
+//! //  "optionalProperties":{"nickname":{"type":"string"}}}
+//! let schema = schema_for::<User>();
+//! ```
+//!
+//! Types which derive `JddfSchema` become definitions, named after the type
+//! or its `#[serde(rename)]`. This is what makes recursive types work, but it
+//! also means two types with the same name in different modules will collide.
+
+use crate::schema::{Form, Schema, Type};
+use indexmap::IndexMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// A type which can describe its JSON representation as a schema.
+pub trait JddfSchema {
+    /// Get the schema for this type.
+    ///
+    /// Types which should be referred to by name add themselves to `defs`,
+    /// and return a `ref` to that definition.
+    fn jddf_schema(defs: &mut IndexMap<String, Schema>) -> Schema;
+}
+
+/// Get the root schema for a type.
+///
+/// The type's own definition, if it has one, becomes the root schema unless
+/// another definition refers back to it. Definitions the root schema doesn't
+/// need are left out.
+pub fn schema_for<T: JddfSchema + ?Sized>() -> Schema {
+    let mut defs = IndexMap::new();
+    let mut root = T::jddf_schema(&mut defs);
+
+    if let Form::Ref(name) = root.form() {
+        let referenced = defs.values().any(|sub_schema| {
+            sub_schema
+                .reachable_definitions(&defs)
+                .contains(name.as_str())
+        });
+
+        if !referenced {
+            let name = name.clone();
+            root = defs.shift_remove(&name).unwrap();
+        }
+    }
+
+    // Flattened types and newtype variants are inlined, so their definitions
+    // may no longer be needed.
+    let reachable: HashSet<String> = root
+        .reachable_definitions(&defs)
+        .into_iter()
+        .map(str::to_owned)
+        .collect();
+    defs.retain(|name, _| reachable.contains(name));
+
+    Schema::from_parts(
+        Some(defs),
+        Box::new(root.form().clone()),
+        root.extra().clone(),
+    )
+}
+
+fn schema(form: Form) -> Schema {
+    Schema::from_parts(None, Box::new(form), IndexMap::new())
+}
+
+macro_rules! impl_type {
+    ($type_:ty, $jddf_type:expr) => {
+        impl JddfSchema for $type_ {
+            fn jddf_schema(_: &mut IndexMap<String, Schema>) -> Schema {
+                schema(Form::Type($jddf_type))
+            }
+        }
+    };
+}
+
+impl_type!(bool, Type::Boolean);
+impl_type!(f32, Type::Float32);
+impl_type!(f64, Type::Float64);
+impl_type!(i8, Type::Int8);
+impl_type!(u8, Type::Uint8);
+impl_type!(i16, Type::Int16);
+impl_type!(u16, Type::Uint16);
+impl_type!(i32, Type::Int32);
+impl_type!(u32, Type::Uint32);
+impl_type!(char, Type::String);
+impl_type!(str, Type::String);
+impl_type!(String, Type::String);
+
+impl<Tz: chrono::TimeZone> JddfSchema for chrono::DateTime<Tz> {
+    fn jddf_schema(_: &mut IndexMap<String, Schema>) -> Schema {
+        schema(Form::Type(Type::Timestamp))
+    }
+}
+
+impl JddfSchema for serde_json::Value {
+    fn jddf_schema(_: &mut IndexMap<String, Schema>) -> Schema {
+        schema(Form::Empty)
+    }
+}
+
+/// `None` is serialized as `null`, which JDDF has no way to express on its
+/// own. So an `Option` is described by the empty schema.
+///
+/// Fields which skip serializing `None`, or which can be omitted thanks to
+/// `#[serde(default)]`, are better described by optional properties. The
+/// derive takes care of this.
+impl<T: JddfSchema> JddfSchema for Option<T> {
+    fn jddf_schema(_: &mut IndexMap<String, Schema>) -> Schema {
+        schema(Form::Empty)
+    }
+}
+
+macro_rules! impl_wrapper {
+    ($($type_:ident),*) => {
+        $(
+            impl<T: JddfSchema + ?Sized> JddfSchema for $type_<T> {
+                fn jddf_schema(defs: &mut IndexMap<String, Schema>) -> Schema {
+                    T::jddf_schema(defs)
+                }
+            }
+        )*
+    };
+}
+
+impl_wrapper!(Box, Rc, Arc);
+
+impl<T: JddfSchema + ?Sized> JddfSchema for &T {
+    fn jddf_schema(defs: &mut IndexMap<String, Schema>) -> Schema {
+        T::jddf_schema(defs)
+    }
+}
+
+macro_rules! impl_elements {
+    ($($type_:ident),*) => {
+        $(
+            impl<T: JddfSchema> JddfSchema for $type_<T> {
+                fn jddf_schema(defs: &mut IndexMap<String, Schema>) -> Schema {
+                    schema(Form::Elements(T::jddf_schema(defs)))
+                }
+            }
+        )*
+    };
+}
+
+impl_elements!(Vec, HashSet, BTreeSet);
+
+impl<T: JddfSchema> JddfSchema for [T] {
+    fn jddf_schema(defs: &mut IndexMap<String, Schema>) -> Schema {
+        schema(Form::Elements(T::jddf_schema(defs)))
+    }
+}
+
+macro_rules! impl_values {
+    ($($type_:ident),*) => {
+        $(
+            impl<T: JddfSchema> JddfSchema for $type_<String, T> {
+                fn jddf_schema(defs: &mut IndexMap<String, Schema>) -> Schema {
+                    schema(Form::Values(T::jddf_schema(defs)))
+                }
+            }
+        )*
+    };
+}
+
+impl_values!(HashMap, BTreeMap, IndexMap);
+
+/// Helpers for code generated by `#[derive(JddfSchema)]`. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    use super::schema;
+    use crate::schema::{Form, Schema};
+    use indexmap::IndexMap;
+
+    pub use indexmap::IndexMap as Definitions;
+
+    /// Add a definition named `name`, built by `f`, if it isn't there already,
+    /// and return a `ref` to it.
+    pub fn define<F>(defs: &mut IndexMap<String, Schema>, name: &str, f: F) -> Schema
+    where
+        F: FnOnce(&mut IndexMap<String, Schema>) -> Schema,
+    {
+        if !defs.contains_key(name) {
+            // A placeholder, so that recursive types refer to the definition
+            // rather than recursing forever.
+            defs.insert(name.to_owned(), schema(Form::Empty));
+
+            let sub_schema = f(defs);
+            defs.insert(name.to_owned(), sub_schema);
+        }
+
+        schema(Form::Ref(name.to_owned()))
+    }
+
+    pub fn empty() -> Schema {
+        schema(Form::Empty)
+    }
+
+    pub fn enumeration(values: &[&str]) -> Schema {
+        schema(Form::Enum(
+            values.iter().map(|value| (*value).to_owned()).collect(),
+        ))
+    }
+
+    pub fn discriminator(tag: &str, mapping: Vec<(&str, Schema)>) -> Schema {
+        schema(Form::Discriminator(
+            tag.to_owned(),
+            mapping
+                .into_iter()
+                .map(|(value, sub_schema)| (value.to_owned(), sub_schema))
+                .collect(),
+        ))
+    }
+
+    /// The properties form of `sub_schema`, following a `ref` if needed.
+    ///
+    /// Anything else is described as an object allowing any properties,
+    /// which is as close as properties can get to it.
+    pub fn properties_of(defs: &IndexMap<String, Schema>, sub_schema: Schema) -> Schema {
+        let resolved = match sub_schema.form() {
+            Form::Ref(name) => defs.get(name).cloned().unwrap_or_else(empty),
+            _ => sub_schema,
+        };
+
+        match resolved.form() {
+            Form::Properties { .. } => resolved,
+            _ => Properties::new(true).into_schema(),
+        }
+    }
+
+    #[derive(Default)]
+    pub struct Properties {
+        required: IndexMap<String, Schema>,
+        optional: IndexMap<String, Schema>,
+        allow_additional: bool,
+    }
+
+    impl Properties {
+        pub fn new(allow_additional: bool) -> Self {
+            Self {
+                allow_additional,
+                ..Self::default()
+            }
+        }
+
+        pub fn required(&mut self, name: &str, sub_schema: Schema) {
+            self.required.insert(name.to_owned(), sub_schema);
+        }
+
+        pub fn optional(&mut self, name: &str, sub_schema: Schema) {
+            self.optional.insert(name.to_owned(), sub_schema);
+        }
+
+        /// Merge the properties of a `#[serde(flatten)]` field.
+        ///
+        /// Flattened maps, or anything else without properties of its own,
+        /// can only be described by allowing additional properties.
+        pub fn flatten(&mut self, defs: &IndexMap<String, Schema>, sub_schema: Schema) {
+            let resolved = match sub_schema.form() {
+                Form::Ref(name) => defs.get(name).cloned().unwrap_or_else(empty),
+                _ => sub_schema,
+            };
+
+            match resolved.form() {
+                Form::Properties {
+                    required,
+                    optional,
+                    allow_additional,
+                    ..
+                } => {
+                    self.required.extend(required.clone());
+                    self.optional.extend(optional.clone());
+                    self.allow_additional |= allow_additional;
+                }
+                _ => self.allow_additional = true,
+            }
+        }
+
+        pub fn into_schema(self) -> Schema {
+            let has_required = !self.required.is_empty() || self.optional.is_empty();
+
+            schema(Form::Properties {
+                required: self.required,
+                optional: self.optional,
+                allow_additional: self.allow_additional,
+                has_required,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::__private::{define, Properties};
+    use super::*;
+    use serde_json::json;
+
+    fn to_json(schema: Schema) -> serde_json::Value {
+        serde_json::to_value(schema.into_serde()).unwrap()
+    }
+
+    #[test]
+    fn standard_types() {
+        assert_eq!(
+            to_json(schema_for::<HashMap<String, Vec<Option<Box<u8>>>>>()),
+            json!({ "definitions": {}, "values": { "elements": {} } })
+        );
+
+        assert_eq!(
+            to_json(schema_for::<Vec<chrono::DateTime<chrono::Utc>>>()),
+            json!({ "definitions": {}, "elements": { "type": "timestamp" } })
+        );
+    }
+
+    struct Node;
+
+    impl JddfSchema for Node {
+        fn jddf_schema(defs: &mut IndexMap<String, Schema>) -> Schema {
+            define(defs, "Node", |defs| {
+                let mut properties = Properties::new(false);
+                properties.required("children", Vec::<Node>::jddf_schema(defs));
+                properties.into_schema()
+            })
+        }
+    }
+
+    struct Tree;
+
+    impl JddfSchema for Tree {
+        fn jddf_schema(defs: &mut IndexMap<String, Schema>) -> Schema {
+            define(defs, "Tree", |defs| {
+                let mut properties = Properties::new(false);
+                properties.required("root", Node::jddf_schema(defs));
+                properties.into_schema()
+            })
+        }
+    }
+
+    #[test]
+    fn root_definition() {
+        assert_eq!(
+            to_json(schema_for::<Tree>()),
+            json!({
+                "definitions": {
+                    "Node": {
+                        "properties": {
+                            "children": { "elements": { "ref": "Node" } },
+                        },
+                    },
+                },
+                "properties": {
+                    "root": { "ref": "Node" },
+                },
+            })
+        );
+
+        assert_eq!(
+            to_json(schema_for::<Node>()),
+            json!({
+                "definitions": {
+                    "Node": {
+                        "properties": {
+                            "children": { "elements": { "ref": "Node" } },
+                        },
+                    },
+                },
+                "ref": "Node",
+            })
+        );
+    }
+}
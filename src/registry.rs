@@ -0,0 +1,496 @@
+//! A local store of versioned schemas.
+//!
+//! This module contains a *registry*, which holds named schemas, each with any
+//! number of [semantic versions](https://semver.org). Schemas are stored as
+//! JSON files on disk, so that a registry can be shared between processes or
+//! checked into source control.
+//!
+//! When a new version of a schema is registered, the registry checks it against
+//! the existing versions using [`compat`](../compat/index.html), and rejects it
+//! if it breaks the registry's compatibility policy. Services can then ask the
+//! registry for a validator for any version of a schema.
+//!
+//! See the docs for [`Registry`](struct.Registry.html) for more.
+
+use crate::compat::{self, Report};
+use crate::errors::JddfError;
+use crate::schema::{Schema, Serde};
+use crate::validator::{self, ValidationError, Validator};
+use failure::{bail, Error};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+pub use semver::Version;
+
+/// Distinguishes the temporary files written by a process.
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Stores versioned schemas in a directory.
+///
+/// Each version of a schema is stored at `<dir>/<name>/<version>.json`.
+/// Registered versions are never modified or removed by the registry, even by
+/// other registries open on the same directory: if one of them has registered
+/// the same version in the meantime, registering fails.
+#[derive(Debug, Clone)]
+pub struct Registry {
+    dir: PathBuf,
+    config: Config,
+    schemas: BTreeMap<String, BTreeMap<Version, Arc<Schema>>>,
+}
+
+impl Registry {
+    /// Opens the registry in `dir` using the default configuration, creating
+    /// the directory if it doesn't exist.
+    pub fn open<P: Into<PathBuf>>(dir: P) -> Result<Self, Error> {
+        Self::open_with_config(dir, Config::default())
+    }
+
+    /// Opens the registry in `dir` using a configuration, creating the
+    /// directory if it doesn't exist.
+    ///
+    /// Every schema in the directory is loaded. Files which don't end in
+    /// `.json` are ignored. Returns an error if a file's name isn't a valid
+    /// version, or if it doesn't contain a valid schema.
+    pub fn open_with_config<P: Into<PathBuf>>(dir: P, config: Config) -> Result<Self, Error> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut schemas = BTreeMap::new();
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            let mut versions = BTreeMap::new();
+            for file in fs::read_dir(entry.path())? {
+                let path = file?.path();
+                if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                    continue;
+                }
+
+                let stem = path.file_stem().and_then(|stem| stem.to_str());
+                let version = Version::parse(stem.unwrap_or_default())?;
+                let serde: Serde = serde_json::from_slice(&fs::read(&path)?)?;
+                versions.insert(version, Arc::new(Schema::from_serde(serde)?));
+            }
+
+            schemas.insert(entry.file_name().to_string_lossy().into_owned(), versions);
+        }
+
+        Ok(Self {
+            dir,
+            config,
+            schemas,
+        })
+    }
+
+    /// Register a new version of a schema, and write it to disk.
+    ///
+    /// The schema is checked against the closest existing versions before and
+    /// after `version`, if there are any, according to the configured
+    /// [`Policy`](enum.Policy.html).
+    ///
+    /// Returns an error if `name` isn't a valid name for a directory, if
+    /// `version` is already registered, if the schema breaks the compatibility
+    /// policy, or if it can't be written to disk.
+    pub fn register(&mut self, name: &str, version: Version, schema: Schema) -> Result<(), Error> {
+        if !is_valid_name(name) {
+            bail!(JddfError::InvalidSchemaName {
+                name: name.to_owned()
+            });
+        }
+
+        if let Some(versions) = self.schemas.get(name) {
+            if versions.contains_key(&version) {
+                bail!(JddfError::DuplicateVersion {
+                    name: name.to_owned(),
+                    version: version.to_string(),
+                });
+            }
+
+            let prev = versions.range(..&version).next_back();
+            let next = versions
+                .range((Bound::Excluded(&version), Bound::Unbounded))
+                .next();
+
+            let conflict = prev
+                .filter(|(_, old)| !self.config.policy.allows(&compat::compare(old, &schema)))
+                .or_else(|| {
+                    next.filter(|(_, new)| {
+                        !self.config.policy.allows(&compat::compare(&schema, new))
+                    })
+                });
+
+            if let Some((other, _)) = conflict {
+                bail!(JddfError::IncompatibleVersion {
+                    name: name.to_owned(),
+                    version: version.to_string(),
+                    other: other.to_string(),
+                });
+            }
+        }
+
+        let dir = self.dir.join(name);
+        fs::create_dir_all(&dir)?;
+
+        // Write to a temporary file first, so that a registry is never left
+        // with a partially-written schema. The name is unique to this write,
+        // so that concurrent writers don't clobber each other's files.
+        let path = dir.join(format!("{}.json", version));
+        let tmp = dir.join(format!(
+            ".{}.json.{}-{}.tmp",
+            version,
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let json = serde_json::to_vec_pretty(&schema.clone().into_serde())?;
+        fs::write(&tmp, json)?;
+
+        // Unlike a rename, a hard link never replaces an existing file. Another
+        // registry open on the same directory may have registered this
+        // version since this one was opened.
+        let linked = fs::hard_link(&tmp, &path);
+        fs::remove_file(&tmp)?;
+        match linked {
+            Err(ref err) if err.kind() == ErrorKind::AlreadyExists => {
+                bail!(JddfError::DuplicateVersion {
+                    name: name.to_owned(),
+                    version: version.to_string(),
+                })
+            }
+            linked => linked?,
+        }
+
+        self.schemas
+            .entry(name.to_owned())
+            .or_default()
+            .insert(version, Arc::new(schema));
+
+        Ok(())
+    }
+
+    /// The directory the registry is stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The names of the registered schemas, in sorted order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.schemas.keys().map(String::as_str)
+    }
+
+    /// The registered versions of a schema, from lowest to highest.
+    pub fn versions(&self, name: &str) -> impl Iterator<Item = &Version> {
+        self.schemas.get(name).into_iter().flat_map(BTreeMap::keys)
+    }
+
+    /// Get a version of a schema.
+    pub fn get(&self, name: &str, version: &Version) -> Option<&Schema> {
+        self.schemas.get(name)?.get(version).map(Arc::as_ref)
+    }
+
+    /// Get the highest registered version of a schema.
+    pub fn latest(&self, name: &str) -> Option<(&Version, &Schema)> {
+        let (version, schema) = self.schemas.get(name)?.iter().next_back()?;
+        Some((version, schema.as_ref()))
+    }
+
+    /// Get a validator for a version of a schema.
+    ///
+    /// The validator shares the registry's copy of the schema, and uses the
+    /// registry's configured [`validator::Config`](../validator/struct.Config.html).
+    pub fn validator(&self, name: &str, version: &Version) -> Option<CompiledValidator> {
+        let schema = self.schemas.get(name)?.get(version)?;
+        Some(CompiledValidator {
+            schema: schema.clone(),
+            validator: Validator::new_with_config(self.config.validator.clone()),
+        })
+    }
+
+    /// Get a validator for the highest registered version of a schema.
+    pub fn latest_validator(&self, name: &str) -> Option<CompiledValidator> {
+        let version = self.latest(name)?.0;
+        self.validator(name, version)
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(&['/', '\\'][..])
+}
+
+/// A validator bundled together with the schema it validates against.
+///
+/// These are handed out by [`Registry::validator`](struct.Registry.html#method.validator).
+/// They're cheap to clone, and don't borrow from the registry.
+#[derive(Debug, Clone)]
+pub struct CompiledValidator {
+    schema: Arc<Schema>,
+    validator: Validator,
+}
+
+impl CompiledValidator {
+    /// The schema instances are validated against.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Validate an instance against the schema.
+    ///
+    /// See [`Validator::validate`](../validator/struct.Validator.html#method.validate).
+    pub fn validate<'a>(&'a self, instance: &'a Value) -> Result<Vec<ValidationError<'a>>, Error> {
+        self.validator.validate(&self.schema, instance)
+    }
+}
+
+/// Configuration for a registry.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct Config {
+    policy: Policy,
+    validator: validator::Config,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the compatibility policy new versions must follow. The default is
+    /// [`Policy::Backward`](enum.Policy.html#variant.Backward).
+    pub fn policy(&mut self, policy: Policy) -> &mut Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the configuration for validators handed out by the registry. The
+    /// default is the default `validator::Config`.
+    pub fn validator(&mut self, config: validator::Config) -> &mut Self {
+        self.validator = config;
+        self
+    }
+}
+
+/// Which changes a registry allows between consecutive versions of a schema.
+///
+/// See [`compat`](../compat/index.html) for what each kind of compatibility
+/// means.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Policy {
+    /// Any change is allowed.
+    None,
+
+    /// Each version must be backward-compatible with the version before it.
+    #[default]
+    Backward,
+
+    /// Each version must be forward-compatible with the version before it.
+    Forward,
+
+    /// Each version must be both backward- and forward-compatible with the
+    /// version before it.
+    Full,
+}
+
+impl Policy {
+    fn allows(self, report: &Report) -> bool {
+        match self {
+            Policy::None => true,
+            Policy::Backward => report.is_backward_compatible(),
+            Policy::Forward => report.is_forward_compatible(),
+            Policy::Full => report.is_fully_compatible(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::schema;
+    use serde_json::json;
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("jddf-registry-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn register_and_reopen() {
+        let dir = temp_dir("reopen");
+        let mut registry = Registry::open(&dir).unwrap();
+
+        let v1 = schema(json!({ "properties": { "id": { "type": "string" } } }));
+        let v2 = schema(json!({
+            "properties": { "id": { "type": "string" } },
+            "optionalProperties": { "name": { "type": "string" } },
+        }));
+
+        registry
+            .register("user", version("1.0.0"), v1.clone())
+            .unwrap();
+        registry
+            .register("user", version("1.1.0"), v2.clone())
+            .unwrap();
+        registry
+            .register("event", version("0.1.0"), schema(json!({})))
+            .unwrap();
+
+        let reopened = Registry::open(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(reopened.names().collect::<Vec<_>>(), vec!["event", "user"]);
+        assert_eq!(
+            reopened.versions("user").collect::<Vec<_>>(),
+            vec![&version("1.0.0"), &version("1.1.0")]
+        );
+        assert_eq!(reopened.get("user", &version("1.0.0")), Some(&v1));
+        assert_eq!(reopened.latest("user"), Some((&version("1.1.0"), &v2)));
+        assert_eq!(reopened.get("user", &version("2.0.0")), None);
+        assert_eq!(reopened.latest("nope"), None);
+
+        let validator = reopened.validator("user", &version("1.0.0")).unwrap();
+        let instance = json!({ "id": 1 });
+        let errors = validator.validate(&instance).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].instance_path().to_string(), "/id");
+
+        let validator = reopened.latest_validator("user").unwrap();
+        assert_eq!(validator.schema(), &v2);
+    }
+
+    #[test]
+    fn register_shared_directory() {
+        let dir = temp_dir("shared");
+        let mut a = Registry::open(&dir).unwrap();
+        let mut b = Registry::open(&dir).unwrap();
+
+        let v1 = schema(json!({ "type": "string" }));
+        a.register("user", version("1.0.0"), v1.clone()).unwrap();
+        let result = b.register("user", version("1.0.0"), schema(json!({ "type": "uint8" })));
+
+        let files: Vec<_> = fs::read_dir(dir.join("user"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        let reopened = Registry::open(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            JddfError::DuplicateVersion {
+                name: "user".to_owned(),
+                version: "1.0.0".to_owned(),
+            }
+            .to_string()
+        );
+        assert_eq!(files, vec!["1.0.0.json"]);
+        assert_eq!(reopened.get("user", &version("1.0.0")), Some(&v1));
+        assert_eq!(b.get("user", &version("1.0.0")), None);
+    }
+
+    #[test]
+    fn compatibility_policy() {
+        let dir = temp_dir("policy");
+        let mut registry = Registry::open(&dir).unwrap();
+
+        let required = |props: Value| schema(json!({ "properties": props }));
+        registry
+            .register("user", version("1.0.0"), required(json!({ "a": {} })))
+            .unwrap();
+
+        // Adding a required property rejects data which used to be valid.
+        let err = registry
+            .register(
+                "user",
+                version("2.0.0"),
+                required(json!({ "a": {}, "b": {} })),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            JddfError::IncompatibleVersion {
+                name: "user".to_owned(),
+                version: "2.0.0".to_owned(),
+                other: "1.0.0".to_owned(),
+            }
+            .to_string()
+        );
+
+        // Adding an optional property is backward-compatible, but a version
+        // inserted before another must also be compatible with that one.
+        let optional = |b: Value| {
+            schema(json!({
+                "properties": { "a": {} },
+                "optionalProperties": { "b": b },
+            }))
+        };
+
+        registry
+            .register(
+                "user",
+                version("2.0.0"),
+                optional(json!({ "type": "string" })),
+            )
+            .unwrap();
+        let err = registry
+            .register("user", version("1.5.0"), optional(json!({})))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            JddfError::IncompatibleVersion {
+                name: "user".to_owned(),
+                version: "1.5.0".to_owned(),
+                other: "2.0.0".to_owned(),
+            }
+            .to_string()
+        );
+
+        let err = registry
+            .register("user", version("2.0.0"), required(json!({})))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            JddfError::DuplicateVersion {
+                name: "user".to_owned(),
+                version: "2.0.0".to_owned(),
+            }
+            .to_string()
+        );
+
+        let err = registry
+            .register("../user", version("1.0.0"), required(json!({})))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            JddfError::InvalidSchemaName {
+                name: "../user".to_owned(),
+            }
+            .to_string()
+        );
+
+        // Rejected versions are never written to disk.
+        let reopened = Registry::open(&dir).unwrap();
+        assert_eq!(reopened.versions("user").count(), 2);
+
+        let mut config = Config::new();
+        config.policy(Policy::Full);
+        let mut registry = Registry::open_with_config(&dir, config).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(registry
+            .register("user", version("3.0.0"), required(json!({ "a": {} })))
+            .is_err());
+    }
+}
@@ -6,20 +6,31 @@
 
 use crate::errors::JddfError;
 use failure::{bail, Error};
+use indexmap::{IndexMap, IndexSet};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
+
+pub mod infer;
+pub mod simplify;
+mod subschema;
+
+pub use self::subschema::Counterexample;
 
 /// An abstract representation of a JDDF schema.
 ///
 /// This struct is meant for use by validators, code generators, or other
 /// high-level processors of schemas. For serialization and deserialization of
 /// schemas, instead use [`Serde`](struct.Serde.html).
+///
+/// Definitions, properties, discriminator mappings, and enum values are all
+/// stored in the order in which they were declared. Iterating over them, or
+/// converting a `Schema` back into a `Serde`, is therefore deterministic.
 #[derive(Clone, PartialEq, Debug)]
 pub struct Schema {
-    defs: Option<HashMap<String, Schema>>,
+    defs: Option<IndexMap<String, Schema>>,
     form: Box<Form>,
-    extra: HashMap<String, Value>,
+    extra: IndexMap<String, Value>,
 }
 
 impl Schema {
@@ -31,16 +42,16 @@ impl Schema {
     /// they can unwrap. Likewise, some tooling will assume that any schema
     /// which has non-`None` definitions are root schemas.
     pub fn from_parts(
-        defs: Option<HashMap<String, Schema>>,
+        defs: Option<IndexMap<String, Schema>>,
         form: Box<Form>,
-        extra: HashMap<String, Value>,
+        extra: IndexMap<String, Value>,
     ) -> Schema {
         Schema { defs, form, extra }
     }
 
     /// Construct a new, root schema from a `Serde`.
     pub fn from_serde(serde_schema: Serde) -> Result<Self, Error> {
-        let schema = Self::_from_serde(serde_schema, true)?;
+        let schema = Self::from_serde_unchecked(serde_schema)?;
 
         Self::check_refs(&schema.defs.as_ref().unwrap(), &schema)?;
         for sub_schema in schema.defs.as_ref().unwrap().values() {
@@ -50,9 +61,15 @@ impl Schema {
         Ok(schema)
     }
 
+    /// Construct a new, root schema from a `Serde`, without checking that its
+    /// `ref`s refer to definitions which exist.
+    pub(crate) fn from_serde_unchecked(serde_schema: Serde) -> Result<Self, Error> {
+        Self::_from_serde(serde_schema, true)
+    }
+
     fn _from_serde(serde_schema: Serde, is_root: bool) -> Result<Self, Error> {
         let defs = if is_root {
-            let mut defs = HashMap::new();
+            let mut defs = IndexMap::new();
             for (name, sub_schema) in serde_schema.defs.unwrap_or_default() {
                 defs.insert(name, Self::_from_serde(sub_schema, false)?);
             }
@@ -97,7 +114,7 @@ impl Schema {
                 bail!(JddfError::InvalidForm);
             }
 
-            let mut values = HashSet::new();
+            let mut values = IndexSet::new();
             for val in enm {
                 if values.contains(&val) {
                     bail!(JddfError::InvalidForm);
@@ -129,12 +146,12 @@ impl Schema {
             let allow_additional = serde_schema.additional_props == Some(true);
             let has_required = serde_schema.props.is_some();
 
-            let mut required = HashMap::new();
+            let mut required = IndexMap::new();
             for (name, sub_schema) in serde_schema.props.unwrap_or_default() {
                 required.insert(name, Self::_from_serde(sub_schema, false)?);
             }
 
-            let mut optional = HashMap::new();
+            let mut optional = IndexMap::new();
             for (name, sub_schema) in serde_schema.opt_props.unwrap_or_default() {
                 if required.contains_key(&name) {
                     bail!(JddfError::AmbiguousProperty { property: name });
@@ -164,7 +181,7 @@ impl Schema {
                 bail!(JddfError::InvalidForm);
             }
 
-            let mut mapping = HashMap::new();
+            let mut mapping = IndexMap::new();
             for (name, sub_schema) in discriminator.mapping {
                 let sub_schema = Self::_from_serde(sub_schema, false)?;
                 match sub_schema.form.as_ref() {
@@ -195,7 +212,7 @@ impl Schema {
         })
     }
 
-    fn check_refs(defs: &HashMap<String, Schema>, schema: &Schema) -> Result<(), Error> {
+    fn check_refs(defs: &IndexMap<String, Schema>, schema: &Schema) -> Result<(), Error> {
         match schema.form() {
             Form::Ref(ref def) => {
                 if !defs.contains_key(def) {
@@ -239,7 +256,7 @@ impl Schema {
         let mut out = Serde::default();
 
         if let Some(defs) = self.defs {
-            let mut out_defs = HashMap::new();
+            let mut out_defs = IndexMap::new();
             for (name, value) in defs {
                 out_defs.insert(name, value.into_serde());
             }
@@ -293,8 +310,12 @@ impl Schema {
                 required,
                 optional,
                 has_required,
-                ..
+                allow_additional,
             } => {
+                if allow_additional {
+                    out.additional_props = Some(true);
+                }
+
                 if has_required || !required.is_empty() {
                     out.props = Some(
                         required
@@ -340,7 +361,7 @@ impl Schema {
     /// Get the definitions associated with this schema.
     ///
     /// If this schema is non-root, this returns None.
-    pub fn definitions(&self) -> &Option<HashMap<String, Schema>> {
+    pub fn definitions(&self) -> &Option<IndexMap<String, Schema>> {
         &self.defs
     }
 
@@ -354,9 +375,37 @@ impl Schema {
     /// Essentially, this function returns a JSON object of properties that
     /// aren't JDDF keywords, but which were included in the schema's JSON. You
     /// might use these nonstandard fields to implement custom behavior.
-    pub fn extra(&self) -> &HashMap<String, Value> {
+    pub fn extra(&self) -> &IndexMap<String, Value> {
         &self.extra
     }
+
+    /// The names of the definitions this schema refers to, directly or
+    /// indirectly through other definitions in `defs`.
+    pub(crate) fn reachable_definitions<'a>(
+        &'a self,
+        defs: &'a IndexMap<String, Schema>,
+    ) -> HashSet<&'a str> {
+        let mut out = HashSet::new();
+        let mut stack = vec![self];
+
+        while let Some(schema) = stack.pop() {
+            match schema.form() {
+                Form::Ref(def) if out.insert(def.as_str()) => {
+                    if let Some(sub_schema) = defs.get(def) {
+                        stack.push(sub_schema);
+                    }
+                }
+                Form::Elements(sub_schema) | Form::Values(sub_schema) => stack.push(sub_schema),
+                Form::Properties {
+                    required, optional, ..
+                } => stack.extend(required.values().chain(optional.values())),
+                Form::Discriminator(_, mapping) => stack.extend(mapping.values()),
+                _ => {}
+            }
+        }
+
+        out
+    }
 }
 
 /// The various forms which a schema may take on, and their respective data.
@@ -383,7 +432,7 @@ pub enum Form {
     ///
     /// This schema asserts that the data is a string, and that it is one of a
     /// set of values.
-    Enum(HashSet<String>),
+    Enum(IndexSet<String>),
 
     /// The elements form.
     ///
@@ -406,8 +455,8 @@ pub enum Form {
     /// field from an omitted one. This is necessary for tooling which wants to
     /// link to a particular part of a schema in JSON form.
     Properties {
-        required: HashMap<String, Schema>,
-        optional: HashMap<String, Schema>,
+        required: IndexMap<String, Schema>,
+        optional: IndexMap<String, Schema>,
         allow_additional: bool,
         has_required: bool,
     },
@@ -427,7 +476,7 @@ pub enum Form {
     ///
     /// The first parameter is the name of the tag property. The second
     /// parameter is the mapping from tag values to their corresponding schemas.
-    Discriminator(String, HashMap<String, Schema>),
+    Discriminator(String, IndexMap<String, Schema>),
 }
 
 /// The values that the "type" keyword may check for.
@@ -473,6 +522,36 @@ pub enum Type {
     Timestamp,
 }
 
+impl Type {
+    /// Whether every value accepted by `other` is also accepted by `self`.
+    pub(crate) fn includes(&self, other: &Type) -> bool {
+        if self == other {
+            return true;
+        }
+
+        if let (Some(outer), Some(inner)) = (self.number_range(), other.number_range()) {
+            return outer.0 <= inner.0 && inner.1 <= outer.1 && (outer.2 || !inner.2);
+        }
+
+        *self == Type::String && *other == Type::Timestamp
+    }
+
+    /// The range of numbers this type accepts, and whether it accepts
+    /// fractions.
+    fn number_range(&self) -> Option<(f64, f64, bool)> {
+        match self {
+            Type::Float32 | Type::Float64 => Some((f64::NEG_INFINITY, f64::INFINITY, true)),
+            Type::Int8 => Some((-128.0, 127.0, false)),
+            Type::Uint8 => Some((0.0, 255.0, false)),
+            Type::Int16 => Some((-32768.0, 32767.0, false)),
+            Type::Uint16 => Some((0.0, 65535.0, false)),
+            Type::Int32 => Some((-2147483648.0, 2147483647.0, false)),
+            Type::Uint32 => Some((0.0, 4294967295.0, false)),
+            _ => None,
+        }
+    }
+}
+
 /// A serialization/deserialization-friendly representation of a JDDF schema.
 ///
 /// This struct is meant for use with the `serde` crate. It is excellent for
@@ -480,11 +559,14 @@ pub enum Type {
 /// rules about how schemas must be formed. For that, consider converting
 /// instances of `Serde` into [`Schema`](struct.Schema.html) using
 /// [`Schema::from_serde`](struct.Schema.html#method.from_serde).
+///
+/// Maps in this struct preserve the order of keys as they appear in the parsed
+/// data, and are serialized back out in that same order.
 #[derive(Debug, PartialEq, Deserialize, Serialize, Default, Clone)]
 pub struct Serde {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "definitions")]
-    pub defs: Option<HashMap<String, Serde>>,
+    pub defs: Option<IndexMap<String, Serde>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "additionalProperties")]
@@ -508,11 +590,11 @@ pub struct Serde {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "properties")]
-    pub props: Option<HashMap<String, Serde>>,
+    pub props: Option<IndexMap<String, Serde>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "optionalProperties")]
-    pub opt_props: Option<HashMap<String, Serde>>,
+    pub opt_props: Option<IndexMap<String, Serde>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub values: Option<Box<Serde>>,
@@ -520,9 +602,9 @@ pub struct Serde {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub discriminator: Option<SerdeDiscriminator>,
 
-    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
     #[serde(flatten)]
-    pub extra: HashMap<String, Value>,
+    pub extra: IndexMap<String, Value>,
 }
 
 /// A serialization/deserialization-friendly representation of a JDDF
@@ -534,7 +616,7 @@ pub struct Serde {
 pub struct SerdeDiscriminator {
     #[serde(rename = "tag")]
     pub tag: String,
-    pub mapping: HashMap<String, Serde>,
+    pub mapping: IndexMap<String, Serde>,
 }
 
 #[cfg(test)]
@@ -618,6 +700,52 @@ mod tests {
         assert_eq!(round_trip, data);
     }
 
+    #[test]
+    fn roundtrip_preserves_order() {
+        let data = r#"{
+  "definitions": {
+    "zeta": {},
+    "alpha": {}
+  },
+  "properties": {
+    "b": {
+      "enum": [
+        "Z",
+        "A",
+        "M"
+      ]
+    },
+    "a": {}
+  },
+  "optionalProperties": {
+    "d": {},
+    "c": {
+      "discriminator": {
+        "tag": "kind",
+        "mapping": {
+          "y": {
+            "properties": {}
+          },
+          "x": {
+            "properties": {}
+          }
+        }
+      }
+    }
+  }
+}"#;
+
+        let parsed: Serde = serde_json::from_str(data).expect("failed to parse json");
+        let schema = Schema::from_serde(parsed).expect("failed to parse schema");
+
+        let defs: Vec<_> = schema.definitions().as_ref().unwrap().keys().collect();
+        assert_eq!(defs, vec!["zeta", "alpha"]);
+
+        let round_trip =
+            serde_json::to_string_pretty(&schema.into_serde()).expect("failed to serialize json");
+        assert_eq!(round_trip, data);
+    }
+
     #[test]
     fn from_serde_root() {
         assert_eq!(
@@ -637,7 +765,7 @@ mod tests {
                         Schema {
                             defs: None,
                             form: Box::new(Form::Type(Type::Boolean)),
-                            extra: HashMap::new(),
+                            extra: IndexMap::new(),
                         },
                     )]
                     .iter()
@@ -645,7 +773,7 @@ mod tests {
                     .collect()
                 ),
                 form: Box::new(Form::Empty),
-                extra: HashMap::new(),
+                extra: IndexMap::new(),
             }
         );
     }
@@ -655,9 +783,9 @@ mod tests {
         assert_eq!(
             Schema::from_serde(serde_json::from_value(json!({})).unwrap()).unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(IndexMap::new()),
                 form: Box::new(Form::Empty),
-                extra: HashMap::new(),
+                extra: IndexMap::new(),
             }
         );
     }
@@ -667,7 +795,7 @@ mod tests {
         assert_eq!(
             Schema::from_serde(serde_json::from_value(json!({ "foo": "bar" })).unwrap()).unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(IndexMap::new()),
                 form: Box::new(Form::Empty),
                 extra: serde_json::from_value(json!({ "foo": "bar" })).unwrap(),
             }
@@ -694,7 +822,7 @@ mod tests {
                         Schema {
                             defs: None,
                             form: Box::new(Form::Type(Type::Boolean)),
-                            extra: HashMap::new(),
+                            extra: IndexMap::new(),
                         },
                     )]
                     .iter()
@@ -702,7 +830,7 @@ mod tests {
                     .collect()
                 ),
                 form: Box::new(Form::Ref("a".to_owned())),
-                extra: HashMap::new(),
+                extra: IndexMap::new(),
             }
         );
 
@@ -729,9 +857,9 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(IndexMap::new()),
                 form: Box::new(Form::Type(Type::Boolean)),
-                extra: HashMap::new(),
+                extra: IndexMap::new(),
             },
         );
 
@@ -744,9 +872,9 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(IndexMap::new()),
                 form: Box::new(Form::Type(Type::Float64)),
-                extra: HashMap::new(),
+                extra: IndexMap::new(),
             },
         );
 
@@ -759,9 +887,9 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(IndexMap::new()),
                 form: Box::new(Form::Type(Type::String)),
-                extra: HashMap::new(),
+                extra: IndexMap::new(),
             },
         );
 
@@ -774,9 +902,9 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(IndexMap::new()),
                 form: Box::new(Form::Type(Type::Timestamp)),
-                extra: HashMap::new(),
+                extra: IndexMap::new(),
             },
         );
 
@@ -800,14 +928,14 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(IndexMap::new()),
                 form: Box::new(Form::Enum(
-                    vec!["FOO".to_owned(), "BAR".to_owned()]
+                    ["FOO".to_owned(), "BAR".to_owned()]
                         .iter()
                         .cloned()
                         .collect()
                 )),
-                extra: HashMap::new(),
+                extra: IndexMap::new(),
             },
         );
 
@@ -841,13 +969,13 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(IndexMap::new()),
                 form: Box::new(Form::Elements(Schema {
                     defs: None,
                     form: Box::new(Form::Type(Type::Boolean)),
-                    extra: HashMap::new(),
+                    extra: IndexMap::new(),
                 })),
-                extra: HashMap::new(),
+                extra: IndexMap::new(),
             }
         );
     }
@@ -869,14 +997,14 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(IndexMap::new()),
                 form: Box::new(Form::Properties {
                     required: [(
                         "a".to_owned(),
                         Schema {
                             defs: None,
                             form: Box::new(Form::Type(Type::Boolean)),
-                            extra: HashMap::new(),
+                            extra: IndexMap::new(),
                         }
                     )]
                     .iter()
@@ -887,7 +1015,7 @@ mod tests {
                         Schema {
                             defs: None,
                             form: Box::new(Form::Type(Type::Boolean)),
-                            extra: HashMap::new(),
+                            extra: IndexMap::new(),
                         }
                     )]
                     .iter()
@@ -896,7 +1024,7 @@ mod tests {
                     has_required: true,
                     allow_additional: true,
                 }),
-                extra: HashMap::new(),
+                extra: IndexMap::new(),
             }
         );
 
@@ -911,15 +1039,15 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(IndexMap::new()),
                 form: Box::new(Form::Properties {
-                    required: HashMap::new(),
+                    required: IndexMap::new(),
                     optional: [(
                         "b".to_owned(),
                         Schema {
                             defs: None,
                             form: Box::new(Form::Type(Type::Boolean)),
-                            extra: HashMap::new(),
+                            extra: IndexMap::new(),
                         }
                     )]
                     .iter()
@@ -928,7 +1056,7 @@ mod tests {
                     has_required: false,
                     allow_additional: false,
                 }),
-                extra: HashMap::new(),
+                extra: IndexMap::new(),
             }
         );
 
@@ -959,13 +1087,13 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(IndexMap::new()),
                 form: Box::new(Form::Values(Schema {
                     defs: None,
                     form: Box::new(Form::Type(Type::Boolean)),
-                    extra: HashMap::new(),
+                    extra: IndexMap::new(),
                 })),
-                extra: HashMap::new(),
+                extra: IndexMap::new(),
             }
         );
     }
@@ -987,7 +1115,7 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(IndexMap::new()),
                 form: Box::new(Form::Discriminator(
                     "foo".to_owned(),
                     [
@@ -996,12 +1124,12 @@ mod tests {
                             Schema {
                                 defs: None,
                                 form: Box::new(Form::Properties {
-                                    required: HashMap::new(),
-                                    optional: HashMap::new(),
+                                    required: IndexMap::new(),
+                                    optional: IndexMap::new(),
                                     has_required: true,
                                     allow_additional: false,
                                 }),
-                                extra: HashMap::new(),
+                                extra: IndexMap::new(),
                             }
                         ),
                         (
@@ -1009,12 +1137,12 @@ mod tests {
                             Schema {
                                 defs: None,
                                 form: Box::new(Form::Properties {
-                                    required: HashMap::new(),
-                                    optional: HashMap::new(),
+                                    required: IndexMap::new(),
+                                    optional: IndexMap::new(),
                                     has_required: true,
                                     allow_additional: false,
                                 }),
-                                extra: HashMap::new(),
+                                extra: IndexMap::new(),
                             }
                         )
                     ]
@@ -1022,7 +1150,7 @@ mod tests {
                     .cloned()
                     .collect(),
                 )),
-                extra: HashMap::new(),
+                extra: IndexMap::new(),
             }
         );
 
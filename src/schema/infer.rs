@@ -0,0 +1,524 @@
+//! Infer schemas from example data.
+//!
+//! This module takes a set of example instances (called "samples"), and
+//! produces the tightest schema it can which accepts all of them. It's meant
+//! to give you a starting point when writing a schema for an existing source of
+//! data, not to replace writing a schema by hand.
+//!
+//! See the docs for [`Inferrer`](struct.Inferrer.html) for more.
+
+use crate::schema::{Form, Schema, Type};
+use chrono::DateTime;
+use indexmap::{IndexMap, IndexSet};
+use serde_json::Value;
+
+/// Infer a schema from a sequence of samples, using the default configuration.
+///
+/// This is a convenience wrapper around [`Inferrer`](struct.Inferrer.html).
+pub fn infer<'a, I: IntoIterator<Item = &'a Value>>(samples: I) -> Schema {
+    let mut inferrer = Inferrer::new();
+    for sample in samples {
+        inferrer.add(sample);
+    }
+
+    inferrer.into_schema()
+}
+
+/// Incrementally infers a schema from samples.
+///
+/// Samples are fed in one at a time with [`add`](#method.add), so that large
+/// streams of data don't need to be held in memory. Once all samples have been
+/// added, [`into_schema`](#method.into_schema) produces the inferred schema.
+///
+/// The inferred schema has these properties:
+///
+/// * Numbers become the narrowest integer `Type` that covers every sample, or
+///   `float64` if some sample isn't an integer or is out of range of `uint32`
+///   and `int32`.
+/// * Strings become `timestamp` if every sample is an RFC3339 timestamp, an
+///   `enum` if there are few enough distinct values (see
+///   [`Config::enum_max_values`](struct.Config.html#method.enum_max_values)),
+///   and `string` otherwise.
+/// * Objects become `properties` schemas. Properties present in every sample
+///   are required, and the rest are optional. Objects that look like
+///   dictionaries rather than records become `values` schemas instead (see
+///   [`Config::values_min_keys`](struct.Config.html#method.values_min_keys)).
+/// * Arrays become `elements` schemas.
+/// * Samples which disagree on their type, as well as `null`s, become the
+///   empty schema.
+#[derive(Debug, Clone)]
+pub struct Inferrer {
+    config: Config,
+    hint: Hint,
+}
+
+impl Inferrer {
+    /// Constructs a new inferrer using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new inferrer using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self {
+            config,
+            hint: Hint::Unknown,
+        }
+    }
+
+    /// Add a sample to the inferrer.
+    pub fn add(&mut self, sample: &Value) {
+        let hint = std::mem::replace(&mut self.hint, Hint::Unknown);
+        self.hint = hint.merge(Hint::from_value(&self.config, sample), &self.config);
+    }
+
+    /// Produce a root schema that accepts every sample added so far.
+    ///
+    /// If no samples were added, this returns the empty schema.
+    pub fn into_schema(self) -> Schema {
+        let mut schema = self.hint.into_schema(&self.config);
+        schema.defs = Some(IndexMap::new());
+        schema
+    }
+}
+
+impl Default for Inferrer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Configuration for how inference should proceed.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    enum_max_values: usize,
+    values_min_keys: usize,
+    detect_timestamps: bool,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of distinct values a string may take on for it
+    /// to be inferred as an `enum`. 0, the default value, indicates that enums
+    /// should never be inferred.
+    pub fn enum_max_values(&mut self, enum_max_values: usize) -> &mut Self {
+        self.enum_max_values = enum_max_values;
+        self
+    }
+
+    /// Sets the number of distinct keys at which an object is inferred as a
+    /// `values` schema instead of a `properties` one. 0 indicates that `values`
+    /// should never be inferred on the basis of key count. The default value is
+    /// 64.
+    ///
+    /// Regardless of this setting, objects are also inferred as `values` if
+    /// more than one non-empty object was sampled, and no key was seen in more
+    /// than one of them. That's typical of objects keyed by IDs.
+    pub fn values_min_keys(&mut self, values_min_keys: usize) -> &mut Self {
+        self.values_min_keys = values_min_keys;
+        self
+    }
+
+    /// Sets whether strings may be inferred as `timestamp`. The default value
+    /// is `true`.
+    pub fn detect_timestamps(&mut self, detect_timestamps: bool) -> &mut Self {
+        self.detect_timestamps = detect_timestamps;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enum_max_values: 0,
+            values_min_keys: 64,
+            detect_timestamps: true,
+        }
+    }
+}
+
+/// What's been learned so far about a part of the samples.
+#[derive(Debug, Clone)]
+enum Hint {
+    /// Nothing has been seen yet.
+    Unknown,
+
+    /// Conflicting types, or a null, were seen.
+    Any,
+
+    Boolean,
+
+    Number {
+        min: f64,
+        max: f64,
+        integer: bool,
+    },
+
+    String {
+        /// The distinct values seen, or `None` if there were too many to make
+        /// for an enum.
+        values: Option<IndexSet<String>>,
+        timestamp: bool,
+    },
+
+    Array(Box<Hint>),
+
+    Object {
+        /// For each property, its hint and the number of objects it was in.
+        properties: IndexMap<String, (Hint, usize)>,
+        count: usize,
+        nonempty: usize,
+    },
+}
+
+impl Hint {
+    fn from_value(config: &Config, value: &Value) -> Self {
+        match value {
+            Value::Null => Hint::Any,
+            Value::Bool(_) => Hint::Boolean,
+            Value::Number(n) => {
+                let f = n.as_f64().unwrap_or(f64::NAN);
+                Hint::Number {
+                    min: f,
+                    max: f,
+                    integer: f.fract() == 0.0,
+                }
+            }
+            Value::String(s) => {
+                let values = if config.enum_max_values == 0 {
+                    None
+                } else {
+                    Some(vec![s.clone()].into_iter().collect())
+                };
+
+                Hint::String {
+                    values,
+                    timestamp: config.detect_timestamps && DateTime::parse_from_rfc3339(s).is_ok(),
+                }
+            }
+            Value::Array(elems) => {
+                Hint::Array(Box::new(elems.iter().fold(Hint::Unknown, |hint, elem| {
+                    hint.merge(Hint::from_value(config, elem), config)
+                })))
+            }
+            Value::Object(obj) => Hint::Object {
+                properties: obj
+                    .iter()
+                    .map(|(k, v)| (k.clone(), (Hint::from_value(config, v), 1)))
+                    .collect(),
+                count: 1,
+                nonempty: if obj.is_empty() { 0 } else { 1 },
+            },
+        }
+    }
+
+    fn merge(self, other: Hint, config: &Config) -> Hint {
+        match (self, other) {
+            (Hint::Unknown, hint) | (hint, Hint::Unknown) => hint,
+            (Hint::Boolean, Hint::Boolean) => Hint::Boolean,
+            (
+                Hint::Number {
+                    min: min_a,
+                    max: max_a,
+                    integer: integer_a,
+                },
+                Hint::Number {
+                    min: min_b,
+                    max: max_b,
+                    integer: integer_b,
+                },
+            ) => Hint::Number {
+                min: min_a.min(min_b),
+                max: max_a.max(max_b),
+                integer: integer_a && integer_b,
+            },
+            (
+                Hint::String {
+                    values: values_a,
+                    timestamp: timestamp_a,
+                },
+                Hint::String {
+                    values: values_b,
+                    timestamp: timestamp_b,
+                },
+            ) => {
+                let values = match (values_a, values_b) {
+                    (Some(mut a), Some(b)) => {
+                        a.extend(b);
+                        if a.len() > config.enum_max_values {
+                            None
+                        } else {
+                            Some(a)
+                        }
+                    }
+                    _ => None,
+                };
+
+                Hint::String {
+                    values,
+                    timestamp: timestamp_a && timestamp_b,
+                }
+            }
+            (Hint::Array(a), Hint::Array(b)) => Hint::Array(Box::new(a.merge(*b, config))),
+            (
+                Hint::Object {
+                    properties: mut properties_a,
+                    count: count_a,
+                    nonempty: nonempty_a,
+                },
+                Hint::Object {
+                    properties: properties_b,
+                    count: count_b,
+                    nonempty: nonempty_b,
+                },
+            ) => {
+                for (name, (hint_b, seen_b)) in properties_b {
+                    let merged = match properties_a.remove(&name) {
+                        Some((hint_a, seen_a)) => (hint_a.merge(hint_b, config), seen_a + seen_b),
+                        None => (hint_b, seen_b),
+                    };
+
+                    properties_a.insert(name, merged);
+                }
+
+                Hint::Object {
+                    properties: properties_a,
+                    count: count_a + count_b,
+                    nonempty: nonempty_a + nonempty_b,
+                }
+            }
+            _ => Hint::Any,
+        }
+    }
+
+    fn into_schema(self, config: &Config) -> Schema {
+        let form = match self {
+            Hint::Unknown | Hint::Any => Form::Empty,
+            Hint::Boolean => Form::Type(Type::Boolean),
+            Hint::Number { min, max, integer } => Form::Type(number_type(min, max, integer)),
+            Hint::String { values, timestamp } => {
+                if timestamp {
+                    Form::Type(Type::Timestamp)
+                } else if let Some(values) = values {
+                    Form::Enum(values)
+                } else {
+                    Form::Type(Type::String)
+                }
+            }
+            Hint::Array(hint) => Form::Elements(hint.into_schema(config)),
+            Hint::Object {
+                properties,
+                count,
+                nonempty,
+            } => {
+                let many_keys =
+                    config.values_min_keys != 0 && properties.len() >= config.values_min_keys;
+                let disjoint_keys = nonempty > 1 && properties.values().all(|(_, seen)| *seen == 1);
+
+                if many_keys || disjoint_keys {
+                    let hint = properties
+                        .into_iter()
+                        .fold(Hint::Unknown, |hint, (_, (prop_hint, _))| {
+                            hint.merge(prop_hint, config)
+                        });
+
+                    Form::Values(hint.into_schema(config))
+                } else {
+                    let mut required = IndexMap::new();
+                    let mut optional = IndexMap::new();
+                    for (name, (hint, seen)) in properties {
+                        if seen == count {
+                            required.insert(name, hint.into_schema(config));
+                        } else {
+                            optional.insert(name, hint.into_schema(config));
+                        }
+                    }
+
+                    Form::Properties {
+                        has_required: !required.is_empty() || optional.is_empty(),
+                        required,
+                        optional,
+                        allow_additional: false,
+                    }
+                }
+            }
+        };
+
+        Schema::from_parts(None, Box::new(form), IndexMap::new())
+    }
+}
+
+fn number_type(min: f64, max: f64, integer: bool) -> Type {
+    if !integer {
+        return Type::Float64;
+    }
+
+    let candidates = [
+        (Type::Uint8, 0.0, 255.0),
+        (Type::Int8, -128.0, 127.0),
+        (Type::Uint16, 0.0, 65535.0),
+        (Type::Int16, -32768.0, 32767.0),
+        (Type::Uint32, 0.0, 4294967295.0),
+        (Type::Int32, -2147483648.0, 2147483647.0),
+    ];
+
+    candidates
+        .iter()
+        .find(|(_, lo, hi)| min >= *lo && max <= *hi)
+        .map(|(typ, _, _)| typ.clone())
+        .unwrap_or(Type::Float64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator::Validator;
+    use serde_json::json;
+
+    fn infer_json(config: Config, samples: Vec<Value>) -> Value {
+        let mut inferrer = Inferrer::new_with_config(config);
+        for sample in &samples {
+            inferrer.add(sample);
+        }
+
+        let schema = inferrer.into_schema();
+        for sample in &samples {
+            assert!(Validator::new()
+                .validate(&schema, sample)
+                .unwrap()
+                .is_empty());
+        }
+
+        serde_json::to_value(schema.into_serde()).unwrap()
+    }
+
+    #[test]
+    fn infer_primitives() {
+        assert_eq!(
+            infer_json(Config::new(), vec![]),
+            json!({ "definitions": {} })
+        );
+
+        assert_eq!(
+            infer_json(Config::new(), vec![json!(true), json!(false)]),
+            json!({ "definitions": {}, "type": "boolean" })
+        );
+
+        assert_eq!(
+            infer_json(Config::new(), vec![json!(true), json!("a")]),
+            json!({ "definitions": {} })
+        );
+
+        assert_eq!(
+            infer_json(Config::new(), vec![json!(null)]),
+            json!({ "definitions": {} })
+        );
+
+        assert_eq!(
+            infer_json(
+                Config::new(),
+                vec![
+                    json!("2019-08-01T12:00:00Z"),
+                    json!("2020-01-01T00:00:00+01:00")
+                ]
+            ),
+            json!({ "definitions": {}, "type": "timestamp" })
+        );
+
+        assert_eq!(
+            infer_json(
+                Config::new().detect_timestamps(false).clone(),
+                vec![json!("2019-08-01T12:00:00Z")]
+            ),
+            json!({ "definitions": {}, "type": "string" })
+        );
+    }
+
+    #[test]
+    fn infer_numbers() {
+        let cases = vec![
+            (vec![json!(0), json!(255)], "uint8"),
+            (vec![json!(-1), json!(127)], "int8"),
+            (vec![json!(0), json!(256)], "uint16"),
+            (vec![json!(-129), json!(0)], "int16"),
+            (vec![json!(65536)], "uint32"),
+            (vec![json!(-32769)], "int32"),
+            (vec![json!(4294967296u64)], "float64"),
+            (vec![json!(-1), json!(4294967295u64)], "float64"),
+            (vec![json!(1), json!(1.5)], "float64"),
+        ];
+
+        for (samples, typ) in cases {
+            assert_eq!(
+                infer_json(Config::new(), samples),
+                json!({ "definitions": {}, "type": typ })
+            );
+        }
+    }
+
+    #[test]
+    fn infer_enums() {
+        let mut config = Config::new();
+        config.enum_max_values(2);
+
+        assert_eq!(
+            infer_json(config.clone(), vec![json!("b"), json!("a"), json!("b")]),
+            json!({ "definitions": {}, "enum": ["b", "a"] })
+        );
+
+        assert_eq!(
+            infer_json(config.clone(), vec![json!("a"), json!("b"), json!("c")]),
+            json!({ "definitions": {}, "type": "string" })
+        );
+    }
+
+    #[test]
+    fn infer_objects() {
+        assert_eq!(
+            infer_json(
+                Config::new(),
+                vec![
+                    json!({ "a": 1, "b": [true], "c": "x" }),
+                    json!({ "a": 2, "b": [], "d": {} }),
+                ]
+            ),
+            json!({
+                "definitions": {},
+                "properties": {
+                    "a": { "type": "uint8" },
+                    "b": { "elements": { "type": "boolean" } },
+                },
+                "optionalProperties": {
+                    "c": { "type": "string" },
+                    "d": { "properties": {} },
+                },
+            })
+        );
+
+        assert_eq!(
+            infer_json(
+                Config::new(),
+                vec![json!({ "a": 1 }), json!({}), json!({ "b": 2 })]
+            ),
+            json!({
+                "definitions": {},
+                "values": { "type": "uint8" },
+            })
+        );
+
+        assert_eq!(
+            infer_json(
+                Config::new().values_min_keys(3).clone(),
+                vec![json!({ "a": 1, "b": 2, "c": "x" })]
+            ),
+            json!({
+                "definitions": {},
+                "values": {},
+            })
+        );
+    }
+}
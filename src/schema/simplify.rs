@@ -0,0 +1,640 @@
+//! Simplify schemas by rearranging their definitions.
+//!
+//! Each function in this module takes a root schema and returns a new root
+//! schema which accepts exactly the same instances. They differ in how they
+//! trade off `ref`s against repetition:
+//!
+//! * [`drop_unreachable`](fn.drop_unreachable.html) removes definitions which
+//!   nothing refers to.
+//! * [`inline_single_use`](fn.inline_single_use.html) replaces `ref`s to
+//!   definitions used only once with the definition itself.
+//! * [`inline_refs`](fn.inline_refs.html) replaces every `ref` it can with the
+//!   definition it refers to.
+//! * [`extract_duplicates`](fn.extract_duplicates.html) moves schemas which
+//!   appear more than once into definitions of their own.
+//!
+//! Moving a schema into or out of a definition changes the schema path of
+//! validation errors it produces. For example, once `{ "ref": "a" }` at
+//! `/properties/foo` is inlined, errors which used to have a schema path
+//! beginning with `/definitions/a` will instead begin with `/properties/foo`.
+//! Each function documents whether it preserves schema paths.
+
+use crate::schema::{Form, Schema};
+use indexmap::IndexMap;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+/// Remove definitions which can't be reached from the root of the schema.
+///
+/// A definition is reachable if the root schema refers to it, or if a
+/// reachable definition refers to it. Definitions which only refer to
+/// themselves are therefore removed.
+///
+/// This preserves the schema paths of all validation errors.
+pub fn drop_unreachable(mut schema: Schema) -> Schema {
+    let defs = match schema.defs.take() {
+        Some(defs) => defs,
+        None => return schema,
+    };
+
+    let reachable: HashSet<String> = schema
+        .reachable_definitions(&defs)
+        .into_iter()
+        .map(str::to_owned)
+        .collect();
+
+    schema.defs = Some(
+        defs.into_iter()
+            .filter(|(name, _)| reachable.contains(name))
+            .collect(),
+    );
+
+    schema
+}
+
+/// Replace every `ref` to a non-recursive definition with the definition
+/// itself.
+///
+/// A definition is recursive if it refers back to itself, directly or through
+/// other definitions. `ref`s to recursive definitions are left in place, since
+/// inlining them would never terminate.
+///
+/// Non-keyword data on the `ref` schema, such as `metadata`, is merged into
+/// the inlined copy of the definition, taking precedence over the
+/// definition's own data under the same key.
+///
+/// Definitions are kept, even if nothing refers to them anymore; use
+/// [`drop_unreachable`](fn.drop_unreachable.html) afterwards to remove them.
+/// Validation errors from inlined definitions are reported at the place the
+/// `ref` used to be, rather than under `/definitions`.
+pub fn inline_refs(schema: Schema) -> Schema {
+    let defs = schema.defs.clone().unwrap_or_default();
+    let inlinable = non_recursive(&defs);
+
+    inline_all(schema, &defs, &inlinable)
+}
+
+/// Inline definitions which are referred to exactly once, and remove them.
+///
+/// `ref`s are counted across the root schema and all of the definitions,
+/// including unreachable ones. Recursive definitions are never inlined. See
+/// [`inline_refs`](fn.inline_refs.html) for how non-keyword data and schema
+/// paths are treated.
+pub fn inline_single_use(schema: Schema) -> Schema {
+    let defs = schema.defs.clone().unwrap_or_default();
+
+    let mut counts = HashMap::new();
+    count_refs(&schema, &mut counts);
+    for sub_schema in defs.values() {
+        count_refs(sub_schema, &mut counts);
+    }
+
+    let inlinable: HashSet<String> = non_recursive(&defs)
+        .into_iter()
+        .filter(|name| counts.get(name.as_str()) == Some(&1))
+        .collect();
+
+    let mut schema = inline_all(schema, &defs, &inlinable);
+    if let Some(defs) = schema.defs.as_mut() {
+        defs.retain(|name, _| !inlinable.contains(name));
+    }
+
+    schema
+}
+
+/// Move subschemas which appear more than once into definitions, and replace
+/// each appearance with a `ref`.
+///
+/// Only schemas of the `enum`, `elements`, `properties`, `values`, and
+/// `discriminator` forms are extracted; replacing an empty, `type`, or `ref`
+/// schema with a `ref` wouldn't make the schema any simpler. Two subschemas
+/// are considered identical only if they would serialize identically,
+/// including any non-keyword data and the order of their properties.
+///
+/// Where an existing definition is identical to a repeated subschema, it is
+/// reused. Otherwise, a new definition is added, named after the property or
+/// discriminator value at which the subschema first appears. Larger
+/// subschemas are extracted before the subschemas they contain.
+///
+/// The values of a discriminator's `mapping` are never replaced, since they
+/// must be of the `properties` form, though their contents may be. Validation
+/// errors from extracted subschemas are reported under `/definitions`, rather
+/// than at the place the subschema used to be.
+pub fn extract_duplicates(mut schema: Schema) -> Schema {
+    let mut defs = schema.defs.take().unwrap_or_default();
+
+    loop {
+        let mut candidates: IndexMap<String, Candidate> = IndexMap::new();
+        let mut tokens = vec![];
+        collect_candidates(&schema, &mut tokens, &mut candidates);
+        for (name, sub_schema) in &defs {
+            tokens.push(name.clone());
+            collect_candidates(sub_schema, &mut tokens, &mut candidates);
+            tokens.pop();
+        }
+
+        let existing: HashMap<String, &String> =
+            defs.iter().map(|(name, body)| (key(body), name)).collect();
+
+        let best = candidates
+            .into_iter()
+            .filter(|(key, candidate)| candidate.count > 1 || existing.contains_key(key))
+            .fold(
+                None,
+                |best: Option<(String, Candidate)>, (key, candidate)| match best {
+                    Some(best) if best.0.len() >= key.len() => Some(best),
+                    _ => Some((key, candidate)),
+                },
+            );
+
+        let (key, candidate) = match best {
+            Some(best) => best,
+            None => break,
+        };
+
+        let name = match existing.get(&key) {
+            Some(name) => (*name).clone(),
+            None => {
+                let name = unused_name(&defs, &candidate.tokens);
+                defs.insert(name.clone(), candidate.schema);
+                name
+            }
+        };
+
+        replace(&mut schema, &key, &name);
+        for sub_schema in defs.values_mut() {
+            replace(sub_schema, &key, &name);
+        }
+    }
+
+    schema.defs = Some(defs);
+    schema
+}
+
+/// The names of definitions which don't refer back to themselves.
+fn non_recursive(defs: &IndexMap<String, Schema>) -> HashSet<String> {
+    defs.iter()
+        .filter(|(name, sub_schema)| {
+            !sub_schema
+                .reachable_definitions(defs)
+                .contains(name.as_str())
+        })
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+fn inline_all(
+    mut schema: Schema,
+    defs: &IndexMap<String, Schema>,
+    names: &HashSet<String>,
+) -> Schema {
+    // The root may itself be a ref, in which case it's replaced wholesale.
+    // Its definitions are set aside so they survive that.
+    let root_defs = schema.defs.take();
+
+    inline(&mut schema, defs, names);
+    schema.defs = root_defs.map(|mut root_defs| {
+        for sub_schema in root_defs.values_mut() {
+            inline(sub_schema, defs, names);
+        }
+
+        root_defs
+    });
+
+    schema
+}
+
+fn inline(schema: &mut Schema, defs: &IndexMap<String, Schema>, names: &HashSet<String>) {
+    if let Form::Ref(ref def) = *schema.form {
+        if names.contains(def) {
+            if let Some(body) = defs.get(def) {
+                let mut body = body.clone();
+                body.extra
+                    .extend(mem::replace(&mut schema.extra, IndexMap::new()));
+                *schema = body;
+
+                // The definition may itself be a ref, or contain some.
+                inline(schema, defs, names);
+                return;
+            }
+        }
+    }
+
+    for_each_child(schema, &mut |sub_schema, _| inline(sub_schema, defs, names));
+}
+
+fn count_refs<'a>(schema: &'a Schema, counts: &mut HashMap<&'a str, usize>) {
+    match *schema.form {
+        Form::Ref(ref def) => *counts.entry(def.as_str()).or_insert(0) += 1,
+        Form::Elements(ref sub_schema) | Form::Values(ref sub_schema) => {
+            count_refs(sub_schema, counts)
+        }
+        Form::Properties {
+            ref required,
+            ref optional,
+            ..
+        } => {
+            for sub_schema in required.values().chain(optional.values()) {
+                count_refs(sub_schema, counts);
+            }
+        }
+        Form::Discriminator(_, ref mapping) => {
+            for sub_schema in mapping.values() {
+                count_refs(sub_schema, counts);
+            }
+        }
+        _ => {}
+    }
+}
+
+struct Candidate {
+    count: usize,
+    schema: Schema,
+    tokens: Vec<String>,
+}
+
+/// Tally up the subschemas of `schema` which may be extracted, keyed by their
+/// serialized form. `schema` itself is not included.
+fn collect_candidates(
+    schema: &Schema,
+    tokens: &mut Vec<String>,
+    candidates: &mut IndexMap<String, Candidate>,
+) {
+    let mut visit = |keyword: &str, name: Option<&str>, sub_schema: &Schema, replaceable: bool| {
+        tokens.push(keyword.to_owned());
+        if let Some(name) = name {
+            tokens.push(name.to_owned());
+        }
+
+        if replaceable && is_extractable(sub_schema) {
+            let candidate = candidates
+                .entry(key(sub_schema))
+                .or_insert_with(|| Candidate {
+                    count: 0,
+                    schema: sub_schema.clone(),
+                    tokens: tokens.clone(),
+                });
+
+            candidate.count += 1;
+        }
+
+        collect_candidates(sub_schema, tokens, candidates);
+
+        tokens.pop();
+        if name.is_some() {
+            tokens.pop();
+        }
+    };
+
+    match *schema.form {
+        Form::Elements(ref sub_schema) => visit("elements", None, sub_schema, true),
+        Form::Values(ref sub_schema) => visit("values", None, sub_schema, true),
+        Form::Properties {
+            ref required,
+            ref optional,
+            ..
+        } => {
+            for (name, sub_schema) in required {
+                visit("properties", Some(name), sub_schema, true);
+            }
+
+            for (name, sub_schema) in optional {
+                visit("optionalProperties", Some(name), sub_schema, true);
+            }
+        }
+        Form::Discriminator(_, ref mapping) => {
+            for (name, sub_schema) in mapping {
+                visit("mapping", Some(name), sub_schema, false);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_extractable(schema: &Schema) -> bool {
+    !matches!(*schema.form, Form::Empty | Form::Ref(_) | Form::Type(_))
+}
+
+/// Replace every replaceable subschema of `schema` whose key is `target` with
+/// a `ref` to `name`.
+fn replace(schema: &mut Schema, target: &str, name: &str) {
+    for_each_child(schema, &mut |sub_schema, replaceable| {
+        if replaceable && key(sub_schema) == target {
+            *sub_schema =
+                Schema::from_parts(None, Box::new(Form::Ref(name.to_owned())), IndexMap::new());
+        } else {
+            replace(sub_schema, target, name);
+        }
+    });
+}
+
+/// Call `f` on each immediate subschema of `schema`, along with whether that
+/// subschema may be replaced by a `ref`.
+fn for_each_child<F: FnMut(&mut Schema, bool)>(schema: &mut Schema, f: &mut F) {
+    match *schema.form {
+        Form::Elements(ref mut sub_schema) | Form::Values(ref mut sub_schema) => {
+            f(sub_schema, true)
+        }
+        Form::Properties {
+            ref mut required,
+            ref mut optional,
+            ..
+        } => {
+            for sub_schema in required.values_mut().chain(optional.values_mut()) {
+                f(sub_schema, true);
+            }
+        }
+        Form::Discriminator(_, ref mut mapping) => {
+            for sub_schema in mapping.values_mut() {
+                f(sub_schema, false);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub(crate) fn key(schema: &Schema) -> String {
+    serde_json::to_string(&schema.clone().into_serde())
+        .expect("unreachable: schemas always serialize to JSON")
+}
+
+/// Pick a name for a new definition, based on the last property name or
+/// discriminator value in `tokens`.
+///
+/// Any `#` is left out of the name, because a `ref` containing a `#` refers to
+/// another document in a [`Bundle`](../../bundle/struct.Bundle.html).
+fn unused_name(defs: &IndexMap<String, Schema>, tokens: &[String]) -> String {
+    let base = tokens
+        .windows(2)
+        .rev()
+        .find(|pair| {
+            pair[0] == "properties" || pair[0] == "optionalProperties" || pair[0] == "mapping"
+        })
+        .map(|pair| pair[1].replace('#', ""))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "definition".to_owned());
+
+    if !defs.contains_key(&base) {
+        return base;
+    }
+
+    (2..)
+        .map(|n| format!("{}{}", base, n))
+        .find(|name| !defs.contains_key(name))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bundle::{Bundle, MemoryResolver};
+    use crate::test_util::schema;
+    use crate::validator::Validator;
+    use serde_json::{json, Value};
+
+    fn to_json(schema: Schema) -> Value {
+        serde_json::to_value(schema.into_serde()).unwrap()
+    }
+
+    /// Checks that both schemas produce errors at the same instance paths.
+    fn assert_equivalent(a: &Schema, b: &Schema, instances: &[Value]) {
+        let validator = Validator::new();
+        for instance in instances {
+            let errors = |schema| -> Vec<String> {
+                validator
+                    .validate(schema, instance)
+                    .unwrap()
+                    .iter()
+                    .map(|error| error.instance_path().to_string())
+                    .collect()
+            };
+
+            assert_eq!(errors(a), errors(b), "instance: {}", instance);
+        }
+    }
+
+    #[test]
+    fn drop_unreachable_definitions() {
+        let schema = schema(json!({
+            "definitions": {
+                "a": { "ref": "b" },
+                "b": { "type": "string" },
+                "c": { "type": "string" },
+                "d": { "elements": { "ref": "d" } },
+            },
+            "ref": "a",
+        }));
+
+        assert_eq!(
+            to_json(drop_unreachable(schema)),
+            json!({
+                "definitions": {
+                    "a": { "ref": "b" },
+                    "b": { "type": "string" },
+                },
+                "ref": "a",
+            })
+        );
+    }
+
+    #[test]
+    fn inline_non_recursive_refs() {
+        let original = schema(json!({
+            "definitions": {
+                "a": { "ref": "b", "metadata": { "description": "a" } },
+                "b": { "type": "string", "metadata": { "description": "b" }, "x": 1 },
+                "node": {
+                    "properties": {
+                        "value": { "ref": "a" },
+                        "children": { "elements": { "ref": "node" } },
+                    },
+                },
+            },
+            "ref": "a",
+        }));
+
+        let inlined = inline_refs(original.clone());
+        assert_eq!(
+            to_json(inlined.clone()),
+            json!({
+                "definitions": {
+                    "a": { "type": "string", "metadata": { "description": "a" }, "x": 1 },
+                    "b": { "type": "string", "metadata": { "description": "b" }, "x": 1 },
+                    "node": {
+                        "properties": {
+                            "value": { "type": "string", "metadata": { "description": "a" }, "x": 1 },
+                            "children": { "elements": { "ref": "node" } },
+                        },
+                    },
+                },
+                "type": "string",
+                "metadata": { "description": "a" },
+                "x": 1,
+            })
+        );
+
+        assert_equivalent(&original, &inlined, &[json!("foo"), json!(1), json!(null)]);
+        assert_eq!(
+            to_json(drop_unreachable(inlined)),
+            json!({
+                "definitions": {},
+                "type": "string",
+                "metadata": { "description": "a" },
+                "x": 1,
+            })
+        );
+    }
+
+    #[test]
+    fn inline_definitions_used_once() {
+        let original = schema(json!({
+            "definitions": {
+                "once": { "elements": { "ref": "nested" } },
+                "nested": { "type": "string" },
+                "twice": { "type": "uint8" },
+                "recursive": { "values": { "ref": "recursive" } },
+            },
+            "properties": {
+                "a": { "ref": "once" },
+                "b": { "ref": "twice" },
+                "c": { "ref": "twice" },
+                "d": { "ref": "recursive" },
+            },
+        }));
+
+        let inlined = inline_single_use(original.clone());
+        assert_eq!(
+            to_json(inlined.clone()),
+            json!({
+                "definitions": {
+                    "twice": { "type": "uint8" },
+                    "recursive": { "values": { "ref": "recursive" } },
+                },
+                "properties": {
+                    "a": { "elements": { "type": "string" } },
+                    "b": { "ref": "twice" },
+                    "c": { "ref": "twice" },
+                    "d": { "ref": "recursive" },
+                },
+            })
+        );
+
+        assert_equivalent(
+            &original,
+            &inlined,
+            &[
+                json!({ "a": ["x"], "b": 1, "c": 2, "d": {} }),
+                json!({ "a": [1], "b": -1, "c": "x", "d": { "x": { "y": 1 } } }),
+                json!([]),
+            ],
+        );
+    }
+
+    #[test]
+    fn extract_repeated_subschemas() {
+        let original = schema(json!({
+            "definitions": {
+                "tags": { "elements": { "type": "string" } },
+            },
+            "properties": {
+                "home": {
+                    "properties": {
+                        "street": { "type": "string" },
+                        "labels": { "elements": { "type": "string" } },
+                    },
+                },
+                "work": {
+                    "properties": {
+                        "street": { "type": "string" },
+                        "labels": { "elements": { "type": "string" } },
+                    },
+                },
+                "event": {
+                    "discriminator": {
+                        "tag": "type",
+                        "mapping": {
+                            "a": { "properties": { "level": { "enum": ["LOW", "HIGH"] } } },
+                            "b": { "properties": { "level": { "enum": ["LOW", "HIGH"] } } },
+                        },
+                    },
+                },
+            },
+        }));
+
+        let extracted = extract_duplicates(original.clone());
+        assert_eq!(
+            to_json(extracted.clone()),
+            json!({
+                "definitions": {
+                    "tags": { "elements": { "type": "string" } },
+                    "home": {
+                        "properties": {
+                            "street": { "type": "string" },
+                            "labels": { "ref": "tags" },
+                        },
+                    },
+                    "level": { "enum": ["LOW", "HIGH"] },
+                },
+                "properties": {
+                    "home": { "ref": "home" },
+                    "work": { "ref": "home" },
+                    "event": {
+                        "discriminator": {
+                            "tag": "type",
+                            "mapping": {
+                                "a": { "properties": { "level": { "ref": "level" } } },
+                                "b": { "properties": { "level": { "ref": "level" } } },
+                            },
+                        },
+                    },
+                },
+            })
+        );
+
+        assert_equivalent(
+            &original,
+            &extracted,
+            &[
+                json!({
+                    "home": { "street": "x", "labels": ["a"] },
+                    "work": { "street": 1, "labels": [1] },
+                    "event": { "type": "a", "level": "MEDIUM" },
+                }),
+                json!({ "home": null, "work": {}, "event": { "type": "b", "level": "LOW" } }),
+            ],
+        );
+
+        // Extracting again changes nothing.
+        assert_eq!(extract_duplicates(extracted.clone()), extracted);
+    }
+
+    #[test]
+    fn extract_names_bundle() {
+        let original = schema(json!({
+            "properties": {
+                "a#b": { "properties": { "x": { "type": "string" } } },
+                "c": { "properties": { "x": { "type": "string" } } },
+                "#": { "elements": { "type": "uint8" } },
+                "d": { "elements": { "type": "uint8" } },
+            },
+        }));
+
+        let extracted = extract_duplicates(original);
+        assert_eq!(
+            extracted
+                .definitions()
+                .as_ref()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["ab", "definition"]
+        );
+
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("main.json", extracted.clone().into_serde());
+
+        let mut bundle = Bundle::new();
+        bundle.load(&resolver, "main.json").unwrap();
+        assert_eq!(bundle.bundle("main.json").unwrap(), extracted);
+    }
+}
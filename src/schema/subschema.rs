@@ -0,0 +1,737 @@
+//! Deciding whether one schema accepts everything another does.
+
+use crate::schema::{Form, Schema, Type};
+use chrono::DateTime;
+use indexmap::{IndexMap, IndexSet};
+use json_pointer::JsonPointer;
+use std::collections::HashSet;
+
+/// Why a schema is not a subschema of another.
+///
+/// A `Counterexample` identifies a part of the first schema that accepts some
+/// data which the corresponding part of the second schema rejects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Counterexample {
+    schema_path: JsonPointer<String, Vec<String>>,
+    other_schema_path: JsonPointer<String, Vec<String>>,
+}
+
+impl Counterexample {
+    /// A pointer into the part of the schema which accepts too much.
+    pub fn schema_path(&self) -> &JsonPointer<String, Vec<String>> {
+        &self.schema_path
+    }
+
+    /// A pointer into the part of the other schema which rejects what
+    /// [`schema_path`](#method.schema_path) accepts.
+    pub fn other_schema_path(&self) -> &JsonPointer<String, Vec<String>> {
+        &self.other_schema_path
+    }
+}
+
+impl Schema {
+    /// Check whether every instance this schema accepts is accepted by `other`.
+    ///
+    /// Both `self` and `other` must be root schemas. If some instance is
+    /// accepted by `self` but rejected by `other`, this returns a
+    /// [`Counterexample`](struct.Counterexample.html) pointing to where in each
+    /// schema they disagree.
+    ///
+    /// Recursive schemas are handled coinductively: when the same pair of
+    /// schemas is reached twice through `ref`s, the second comparison is
+    /// assumed to succeed.
+    ///
+    /// This check is sound, but not complete: if it succeeds, `self` truly is a
+    /// subschema of `other`. In a few contrived cases, such as a `properties`
+    /// schema which requires a discriminator's tag to be one of its mapping
+    /// keys, it may report a counterexample even though none exists.
+    pub fn is_subschema_of(&self, other: &Schema) -> Result<(), Counterexample> {
+        let mut checker = Checker {
+            a_root: self,
+            b_root: other,
+            a_tokens: vec![],
+            b_tokens: vec![],
+            assumed: HashSet::new(),
+        };
+
+        checker.check(self, other, None)
+    }
+}
+
+struct Checker<'a> {
+    a_root: &'a Schema,
+    b_root: &'a Schema,
+    a_tokens: Vec<String>,
+    b_tokens: Vec<String>,
+    assumed: HashSet<(String, String)>,
+}
+
+impl<'a> Checker<'a> {
+    fn check(&mut self, a: &Schema, b: &Schema, tag: Option<&str>) -> Result<(), Counterexample> {
+        match (a.form(), b.form()) {
+            (_, Form::Empty) => Ok(()),
+            (Form::Ref(a_def), Form::Ref(b_def)) => {
+                if !self.assumed.insert((a_def.clone(), b_def.clone())) {
+                    return Ok(());
+                }
+
+                let a_tokens = self.enter(true, a_def);
+                let b_tokens = self.enter(false, b_def);
+                self.check(self.a_def(a_def), self.b_def(b_def), None)?;
+                self.a_tokens = a_tokens;
+                self.b_tokens = b_tokens;
+                Ok(())
+            }
+            (Form::Ref(_), _) => {
+                // A schema which is nothing but a cycle of refs accepts nothing,
+                // so it's a subschema of anything.
+                let (a_def, a) = match resolve(self.a_root, a) {
+                    Some(resolved) => resolved,
+                    None => return Ok(()),
+                };
+
+                let a_tokens = self.enter(true, a_def);
+                self.check(a, b, tag)?;
+                self.a_tokens = a_tokens;
+                Ok(())
+            }
+            (_, Form::Ref(_)) => {
+                let (b_def, b) = match resolve(self.b_root, b) {
+                    Some(resolved) => resolved,
+                    None => return self.fail(),
+                };
+
+                let b_tokens = self.enter(false, b_def);
+                self.check(a, b, tag)?;
+                self.b_tokens = b_tokens;
+                Ok(())
+            }
+            _ => self.check_forms(a, b, tag),
+        }
+    }
+
+    fn check_forms(
+        &mut self,
+        a: &Schema,
+        b: &Schema,
+        tag: Option<&str>,
+    ) -> Result<(), Counterexample> {
+        match (a.form(), b.form()) {
+            (Form::Type(a_type), Form::Type(b_type)) => {
+                if b_type.includes(a_type) {
+                    Ok(())
+                } else {
+                    self.fail_at("type", "type")
+                }
+            }
+            (Form::Enum(a_values), Form::Enum(b_values)) => {
+                if a_values.is_subset(b_values) {
+                    Ok(())
+                } else {
+                    self.fail_at("enum", "enum")
+                }
+            }
+            (Form::Enum(a_values), Form::Type(b_type)) => {
+                let ok = match b_type {
+                    Type::String => true,
+                    Type::Timestamp => a_values
+                        .iter()
+                        .all(|v| DateTime::parse_from_rfc3339(v).is_ok()),
+                    _ => false,
+                };
+
+                if ok {
+                    Ok(())
+                } else {
+                    self.fail_at("enum", "type")
+                }
+            }
+            (Form::Elements(a_sub), Form::Elements(b_sub)) => {
+                self.a_tokens.push("elements".to_owned());
+                self.b_tokens.push("elements".to_owned());
+                self.check(a_sub, b_sub, None)?;
+                self.a_tokens.pop();
+                self.b_tokens.pop();
+                Ok(())
+            }
+            (Form::Values(a_sub), Form::Values(b_sub)) => {
+                self.a_tokens.push("values".to_owned());
+                self.b_tokens.push("values".to_owned());
+                self.check(a_sub, b_sub, None)?;
+                self.a_tokens.pop();
+                self.b_tokens.pop();
+                Ok(())
+            }
+            (
+                Form::Values(a_sub),
+                Form::Properties {
+                    required: b_required,
+                    optional: b_optional,
+                    allow_additional: b_additional,
+                    ..
+                },
+            ) => {
+                // A values schema accepts the empty object, as well as objects
+                // with any keys at all.
+                if !b_required.is_empty() || !b_additional {
+                    return self.fail();
+                }
+
+                self.a_tokens.push("values".to_owned());
+                self.b_tokens.push("optionalProperties".to_owned());
+                for (name, b_sub) in b_optional {
+                    self.b_tokens.push(name.clone());
+                    self.check(a_sub, b_sub, None)?;
+                    self.b_tokens.pop();
+                }
+                self.a_tokens.pop();
+                self.b_tokens.pop();
+                Ok(())
+            }
+            (
+                Form::Properties {
+                    required: a_required,
+                    optional: a_optional,
+                    allow_additional: a_additional,
+                    ..
+                },
+                Form::Values(b_sub),
+            ) => {
+                if *a_additional {
+                    return self.fail();
+                }
+
+                self.b_tokens.push("values".to_owned());
+                for (keyword, props) in &[
+                    ("properties", a_required),
+                    ("optionalProperties", a_optional),
+                ] {
+                    self.a_tokens.push((*keyword).to_owned());
+                    for (name, a_sub) in props.iter() {
+                        if tag == Some(name.as_str()) {
+                            continue;
+                        }
+
+                        self.a_tokens.push(name.clone());
+                        self.check(a_sub, b_sub, None)?;
+                        self.a_tokens.pop();
+                    }
+                    self.a_tokens.pop();
+                }
+                self.b_tokens.pop();
+                Ok(())
+            }
+            (
+                Form::Properties {
+                    required: a_required,
+                    optional: a_optional,
+                    allow_additional: a_additional,
+                    ..
+                },
+                Form::Properties {
+                    required: b_required,
+                    optional: b_optional,
+                    allow_additional: b_additional,
+                    ..
+                },
+            ) => self.check_properties(
+                (a_required, a_optional, *a_additional),
+                (b_required, b_optional, *b_additional),
+                tag,
+            ),
+            (Form::Discriminator(a_tag, a_mapping), Form::Discriminator(b_tag, b_mapping)) => {
+                if a_tag != b_tag {
+                    return self.fail_at("discriminator", "discriminator");
+                }
+
+                self.a_tokens.push("discriminator".to_owned());
+                self.a_tokens.push("mapping".to_owned());
+                self.b_tokens.push("discriminator".to_owned());
+                self.b_tokens.push("mapping".to_owned());
+                for (value, a_sub) in a_mapping {
+                    self.a_tokens.push(value.clone());
+                    match b_mapping.get(value) {
+                        Some(b_sub) => {
+                            self.b_tokens.push(value.clone());
+                            self.check(a_sub, b_sub, Some(a_tag))?;
+                            self.b_tokens.pop();
+                        }
+                        None => return self.fail(),
+                    }
+                    self.a_tokens.pop();
+                }
+                self.a_tokens.pop();
+                self.a_tokens.pop();
+                self.b_tokens.pop();
+                self.b_tokens.pop();
+                Ok(())
+            }
+            (Form::Discriminator(a_tag, a_mapping), Form::Properties { .. })
+            | (Form::Discriminator(a_tag, a_mapping), Form::Values(_)) => {
+                // Each variant of the discriminator is equivalent to its
+                // mapping schema, with the tag as an additional required
+                // property of a single possible value.
+                self.a_tokens.push("discriminator".to_owned());
+                self.a_tokens.push("mapping".to_owned());
+                for (value, a_sub) in a_mapping {
+                    self.a_tokens.push(value.clone());
+                    let variant = with_tag(a_sub, a_tag, value);
+                    self.check(&variant, b, None)?;
+                    self.a_tokens.pop();
+                }
+                self.a_tokens.pop();
+                self.a_tokens.pop();
+                Ok(())
+            }
+            _ => self.fail(),
+        }
+    }
+
+    fn check_properties(
+        &mut self,
+        a: (&IndexMap<String, Schema>, &IndexMap<String, Schema>, bool),
+        b: (&IndexMap<String, Schema>, &IndexMap<String, Schema>, bool),
+        tag: Option<&str>,
+    ) -> Result<(), Counterexample> {
+        let (a_required, a_optional, a_additional) = a;
+        let (b_required, b_optional, b_additional) = b;
+
+        // Everything b requires, a must require too.
+        for name in b_required.keys() {
+            if !a_required.contains_key(name) {
+                self.b_tokens.push("properties".to_owned());
+                self.b_tokens.push(name.clone());
+                let result = self.fail();
+                self.b_tokens.pop();
+                self.b_tokens.pop();
+                return result;
+            }
+        }
+
+        // Everything a accepts as a property, b must accept.
+        for (a_keyword, props) in &[
+            ("properties", a_required),
+            ("optionalProperties", a_optional),
+        ] {
+            for (name, a_sub) in props.iter() {
+                if tag == Some(name.as_str()) {
+                    continue;
+                }
+
+                self.a_tokens.push((*a_keyword).to_owned());
+                self.a_tokens.push(name.clone());
+
+                let b_sub = b_required
+                    .get(name)
+                    .map(|s| ("properties", s))
+                    .or_else(|| b_optional.get(name).map(|s| ("optionalProperties", s)));
+
+                match b_sub {
+                    Some((b_keyword, b_sub)) => {
+                        self.b_tokens.push(b_keyword.to_owned());
+                        self.b_tokens.push(name.clone());
+                        self.check(a_sub, b_sub, None)?;
+                        self.b_tokens.pop();
+                        self.b_tokens.pop();
+                    }
+                    None => {
+                        if !b_additional {
+                            return self.fail();
+                        }
+                    }
+                }
+
+                self.a_tokens.pop();
+                self.a_tokens.pop();
+            }
+        }
+
+        // If a accepts additional properties, then b must too, and anything b
+        // declares that a doesn't must accept any value.
+        if a_additional {
+            if !b_additional {
+                return self.fail();
+            }
+
+            let empty = Schema::from_parts(None, Box::new(Form::Empty), IndexMap::new());
+            for (b_keyword, props) in &[
+                ("properties", b_required),
+                ("optionalProperties", b_optional),
+            ] {
+                for (name, b_sub) in props.iter() {
+                    if a_required.contains_key(name) || a_optional.contains_key(name) {
+                        continue;
+                    }
+
+                    self.b_tokens.push((*b_keyword).to_owned());
+                    self.b_tokens.push(name.clone());
+                    self.check(&empty, b_sub, None)?;
+                    self.b_tokens.pop();
+                    self.b_tokens.pop();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn enter(&mut self, a_side: bool, def: &str) -> Vec<String> {
+        let tokens = if a_side {
+            &mut self.a_tokens
+        } else {
+            &mut self.b_tokens
+        };
+
+        std::mem::replace(tokens, vec!["definitions".to_owned(), def.to_owned()])
+    }
+
+    fn a_def(&self, name: &str) -> &'a Schema {
+        &self.a_root.definitions().as_ref().unwrap()[name]
+    }
+
+    fn b_def(&self, name: &str) -> &'a Schema {
+        &self.b_root.definitions().as_ref().unwrap()[name]
+    }
+
+    fn fail(&self) -> Result<(), Counterexample> {
+        Err(Counterexample {
+            schema_path: JsonPointer::new(self.a_tokens.clone()),
+            other_schema_path: JsonPointer::new(self.b_tokens.clone()),
+        })
+    }
+
+    fn fail_at(&mut self, a_token: &str, b_token: &str) -> Result<(), Counterexample> {
+        self.a_tokens.push(a_token.to_owned());
+        self.b_tokens.push(b_token.to_owned());
+        let result = self.fail();
+        self.a_tokens.pop();
+        self.b_tokens.pop();
+        result
+    }
+}
+
+/// Follow a chain of refs until reaching a schema which isn't a ref. Returns
+/// that schema, and the name of the definition it came from.
+///
+/// Returns `None` if the chain is a cycle.
+fn resolve<'a>(root: &'a Schema, mut schema: &'a Schema) -> Option<(&'a str, &'a Schema)> {
+    let defs = root.definitions().as_ref().unwrap();
+    let mut seen = HashSet::new();
+    let mut name = "";
+
+    while let Form::Ref(def) = schema.form() {
+        if !seen.insert(def) {
+            return None;
+        }
+
+        name = def;
+        schema = &defs[def];
+    }
+
+    Some((name, schema))
+}
+
+/// A discriminator mapping schema, with its tag made into an explicit property.
+fn with_tag(schema: &Schema, tag: &str, value: &str) -> Schema {
+    let mut schema = schema.clone();
+    if let Form::Properties { required, .. } = schema.form.as_mut() {
+        let values: IndexSet<_> = vec![value.to_owned()].into_iter().collect();
+        required.insert(
+            tag.to_owned(),
+            Schema::from_parts(None, Box::new(Form::Enum(values)), IndexMap::new()),
+        );
+    }
+
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::schema;
+    use serde_json::{json, Value};
+
+    fn check(a: Value, b: Value) -> Result<(), (String, String)> {
+        let a = schema(a);
+        let b = schema(b);
+
+        a.is_subschema_of(&b).map_err(|c| {
+            (
+                c.schema_path().to_string(),
+                c.other_schema_path().to_string(),
+            )
+        })
+    }
+
+    fn fail(a: &str, b: &str) -> Result<(), (String, String)> {
+        Err((a.to_owned(), b.to_owned()))
+    }
+
+    #[test]
+    fn empty_and_types() {
+        assert_eq!(check(json!({ "type": "string" }), json!({})), Ok(()));
+        assert_eq!(check(json!({}), json!({ "type": "string" })), fail("", ""));
+
+        let ints = ["int8", "uint8", "int16", "uint16", "int32", "uint32"];
+        for (i, a) in ints.iter().enumerate() {
+            assert_eq!(
+                check(json!({ "type": a }), json!({ "type": "float32" })),
+                Ok(())
+            );
+            assert_eq!(
+                check(json!({ "type": "float64" }), json!({ "type": a })),
+                fail("/type", "/type")
+            );
+
+            // Every even-indexed (signed) type is contained in every wider
+            // signed type, and every unsigned type is contained in every wider
+            // type at all.
+            for (j, b) in ints.iter().enumerate() {
+                let expected = i == j || (j > i && (i % 2 == 1 || j % 2 == 0));
+                assert_eq!(
+                    check(json!({ "type": a }), json!({ "type": b })).is_ok(),
+                    expected,
+                    "{} <= {}",
+                    a,
+                    b
+                );
+            }
+        }
+
+        assert_eq!(
+            check(json!({ "type": "timestamp" }), json!({ "type": "string" })),
+            Ok(())
+        );
+        assert_eq!(
+            check(json!({ "type": "string" }), json!({ "type": "timestamp" })),
+            fail("/type", "/type")
+        );
+    }
+
+    #[test]
+    fn enums() {
+        assert_eq!(
+            check(json!({ "enum": ["A"] }), json!({ "enum": ["A", "B"] })),
+            Ok(())
+        );
+        assert_eq!(
+            check(json!({ "enum": ["A", "C"] }), json!({ "enum": ["A", "B"] })),
+            fail("/enum", "/enum")
+        );
+        assert_eq!(
+            check(json!({ "enum": ["A"] }), json!({ "type": "string" })),
+            Ok(())
+        );
+        assert_eq!(
+            check(
+                json!({ "enum": ["2020-01-01T00:00:00Z"] }),
+                json!({ "type": "timestamp" })
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check(json!({ "enum": ["A"] }), json!({ "type": "timestamp" })),
+            fail("/enum", "/type")
+        );
+        assert_eq!(
+            check(json!({ "type": "string" }), json!({ "enum": ["A"] })),
+            fail("", "")
+        );
+    }
+
+    #[test]
+    fn properties() {
+        assert_eq!(
+            check(
+                json!({
+                    "properties": { "a": { "type": "uint8" }, "b": {} },
+                    "optionalProperties": { "c": {} },
+                }),
+                json!({
+                    "properties": { "a": { "type": "uint16" } },
+                    "optionalProperties": { "b": {}, "c": {} },
+                }),
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            check(
+                json!({ "optionalProperties": { "a": {} } }),
+                json!({ "properties": { "a": {} } }),
+            ),
+            fail("", "/properties/a")
+        );
+
+        assert_eq!(
+            check(
+                json!({ "properties": { "a": { "type": "uint16" } } }),
+                json!({ "properties": { "a": { "type": "uint8" } } }),
+            ),
+            fail("/properties/a/type", "/properties/a/type")
+        );
+
+        assert_eq!(
+            check(
+                json!({ "properties": { "a": {} } }),
+                json!({ "properties": {} }),
+            ),
+            fail("/properties/a", "")
+        );
+
+        assert_eq!(
+            check(
+                json!({ "properties": { "a": {} } }),
+                json!({ "properties": {}, "additionalProperties": true }),
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            check(
+                json!({ "properties": {}, "additionalProperties": true }),
+                json!({
+                    "optionalProperties": { "a": { "type": "string" } },
+                    "additionalProperties": true,
+                }),
+            ),
+            fail("", "/optionalProperties/a")
+        );
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(
+            check(
+                json!({ "values": { "type": "uint8" } }),
+                json!({ "values": { "type": "float64" } }),
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            check(
+                json!({ "optionalProperties": { "a": { "type": "uint8" } } }),
+                json!({ "values": { "type": "float64" } }),
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            check(
+                json!({ "values": { "type": "uint8" } }),
+                json!({
+                    "optionalProperties": { "a": { "type": "float64" } },
+                    "additionalProperties": true,
+                }),
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            check(
+                json!({ "values": {} }),
+                json!({ "optionalProperties": { "a": {} } }),
+            ),
+            fail("", "")
+        );
+    }
+
+    #[test]
+    fn discriminators() {
+        let schema = json!({
+            "discriminator": {
+                "tag": "kind",
+                "mapping": {
+                    "a": { "properties": { "x": { "type": "uint8" } } },
+                    "b": { "properties": {} },
+                },
+            },
+        });
+
+        assert_eq!(check(schema.clone(), schema.clone()), Ok(()));
+
+        assert_eq!(
+            check(
+                schema.clone(),
+                json!({
+                    "discriminator": {
+                        "tag": "kind",
+                        "mapping": {
+                            "a": { "properties": { "x": { "type": "uint8" } } },
+                        },
+                    },
+                }),
+            ),
+            fail("/discriminator/mapping/b", "/discriminator/mapping")
+        );
+
+        assert_eq!(
+            check(
+                schema.clone(),
+                json!({
+                    "properties": { "kind": { "enum": ["a", "b"] } },
+                    "optionalProperties": { "x": { "type": "uint16" } },
+                }),
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            check(
+                schema.clone(),
+                json!({
+                    "properties": { "kind": { "enum": ["a"] } },
+                    "optionalProperties": { "x": { "type": "uint16" } },
+                }),
+            ),
+            fail(
+                "/discriminator/mapping/b/properties/kind/enum",
+                "/properties/kind/enum"
+            )
+        );
+    }
+
+    #[test]
+    fn recursive_refs() {
+        let list = |typ: &str| {
+            json!({
+                "definitions": {
+                    "node": {
+                        "properties": { "value": { "type": typ } },
+                        "optionalProperties": { "next": { "ref": "node" } },
+                    },
+                },
+                "ref": "node",
+            })
+        };
+
+        assert_eq!(check(list("uint8"), list("uint16")), Ok(()));
+        assert_eq!(
+            check(list("uint16"), list("uint8")),
+            fail(
+                "/definitions/node/properties/value/type",
+                "/definitions/node/properties/value/type"
+            )
+        );
+
+        // An unrolled version of the list is a subschema of the recursive one,
+        // and vice versa.
+        let unrolled = json!({
+            "definitions": {
+                "node": {
+                    "properties": { "value": { "type": "uint8" } },
+                    "optionalProperties": {
+                        "next": {
+                            "properties": { "value": { "type": "uint8" } },
+                            "optionalProperties": { "next": { "ref": "node" } },
+                        },
+                    },
+                },
+            },
+            "ref": "node",
+        });
+
+        assert_eq!(check(unrolled.clone(), list("uint8")), Ok(()));
+        assert_eq!(check(list("uint8"), unrolled), Ok(()));
+    }
+}
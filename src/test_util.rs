@@ -0,0 +1,151 @@
+//! Helpers shared by the tests of several modules.
+
+use crate::schema::{Schema, Serde};
+use serde_json::{json, Value};
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+/// Parse a schema from JSON, panicking if it's not a valid schema.
+pub fn schema(schema: Value) -> Schema {
+    let serde: Serde = serde_json::from_value(schema).unwrap();
+    Schema::from_serde(serde).unwrap()
+}
+
+/// Assert that `source` parses, by piping it to `command`, which should exit
+/// with an error if it doesn't.
+///
+/// This does nothing if `command` isn't installed, so that the tests don't
+/// depend on the toolchain of every language there's a code generator for.
+pub fn assert_parses(source: &str, command: &[&str]) {
+    let mut child = match Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(ref err) if err.kind() == ErrorKind::NotFound => return,
+        Err(err) => panic!("couldn't run {}: {}", command[0], err),
+    };
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}\n{}",
+        source,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Definitions which are recursive in each of the ways a schema can be: a
+/// `node` with required, optional, and repeated references to itself, and a
+/// `list` which recurses through a discriminator.
+///
+/// These are meant for tests which generate instances, where recursion is the
+/// easiest thing to get wrong.
+pub fn recursive_definitions() -> Value {
+    json!({
+        "node": {
+            "properties": {
+                "value": { "type": "int8" },
+                "children": { "elements": { "ref": "node" } },
+            },
+            "optionalProperties": {
+                "next": { "ref": "node" },
+            },
+        },
+        "list": {
+            "discriminator": {
+                "tag": "kind",
+                "mapping": {
+                    "cons": {
+                        "properties": {
+                            "head": {},
+                            "tail": { "ref": "list" },
+                        },
+                    },
+                    "nil": {
+                        "properties": {},
+                        "additionalProperties": true,
+                    },
+                },
+            },
+        },
+    })
+}
+
+/// A user, with a property of each form and some names which are keywords in
+/// one language or another.
+///
+/// These are meant for tests of code generators.
+pub fn user() -> Schema {
+    schema(json!({
+        "metadata": { "description": "A user." },
+        "properties": {
+            "id": { "type": "string" },
+            "createdAt": { "type": "timestamp" },
+            "role": { "enum": ["admin", "read-only"] },
+            "class": { "type": "uint8" },
+        },
+        "optionalProperties": {
+            "address": {
+                "properties": {
+                    "street": {
+                        "type": "string",
+                        "metadata": { "description": "The street." },
+                    },
+                },
+            },
+            "labels": { "values": { "type": "string" } },
+            "friends": { "elements": {} },
+        },
+    }))
+}
+
+/// A `shape` which is a discriminator, whose variants refer to other
+/// definitions and, through `shapes`, back to `shape`.
+///
+/// These are meant for tests of code generators, which have to name and order
+/// the types of the variants.
+pub fn shapes() -> Schema {
+    schema(json!({
+        "definitions": {
+            "node": {
+                "properties": {
+                    "value": { "type": "float64" },
+                },
+                "optionalProperties": {
+                    "next": { "ref": "node" },
+                },
+            },
+            "shape": {
+                "discriminator": {
+                    "tag": "kind",
+                    "mapping": {
+                        "list": {
+                            "properties": {
+                                "head": { "ref": "node" },
+                            },
+                        },
+                        "group": {
+                            "properties": {
+                                "shapes": { "ref": "shapes" },
+                            },
+                        },
+                        "empty": { "properties": {} },
+                    },
+                },
+            },
+            "shapes": { "elements": { "ref": "shape" } },
+        },
+        "ref": "shape",
+    }))
+}
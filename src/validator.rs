@@ -182,4 +182,32 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn errors_in_declaration_order() -> Result<(), Error> {
+        let schema: crate::schema::Serde = serde_json::from_str(
+            r#"{
+                "properties": {
+                    "z": { "type": "string" },
+                    "a": { "type": "string" },
+                    "m": { "type": "string" }
+                }
+            }"#,
+        )?;
+
+        let schema = Schema::from_serde(schema)?;
+        let instance = json!({});
+        let errors: Vec<_> = Validator::new()
+            .validate(&schema, &instance)?
+            .into_iter()
+            .map(|err| err.schema_path().to_string())
+            .collect();
+
+        assert_eq!(
+            errors,
+            vec!["/properties/z", "/properties/a", "/properties/m"]
+        );
+
+        Ok(())
+    }
 }
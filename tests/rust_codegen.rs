@@ -0,0 +1,68 @@
+//! Checks that generated Rust code compiles and deserializes instances.
+//!
+//! `rust_codegen/generated.rs` is the output of the Rust generator for
+//! `schema()`, checked in so that it can be compiled as part of this test.
+//! `generated_is_current` fails if the two drift apart.
+
+use jddf::codegen::rust::Generator;
+use jddf::{Schema, SerdeSchema};
+use serde_json::{json, Value};
+
+#[allow(dead_code)]
+mod generated {
+    include!("rust_codegen/generated.rs");
+}
+
+fn schema() -> Value {
+    json!({
+        "definitions": {
+            "string": { "type": "string" },
+            "option": {
+                "properties": {
+                    "name": { "ref": "string" },
+                },
+                "optionalProperties": {
+                    "note": { "type": "string" },
+                },
+            },
+            "vec": { "elements": { "ref": "option" } },
+            "box": {
+                "properties": {
+                    "value": { "type": "int32" },
+                },
+                "optionalProperties": {
+                    "next": { "ref": "box" },
+                },
+            },
+        },
+        "properties": {
+            "options": { "ref": "vec" },
+            "list": { "ref": "box" },
+            "seen": { "values": { "type": "timestamp" } },
+        },
+    })
+}
+
+#[test]
+fn generated_is_current() {
+    let serde: SerdeSchema = serde_json::from_value(schema()).unwrap();
+    let schema = Schema::from_serde(serde).unwrap();
+
+    assert_eq!(
+        Generator::new().generate(&schema),
+        include_str!("rust_codegen/generated.rs")
+    );
+}
+
+#[test]
+fn generated_round_trips() {
+    let instance = json!({
+        "options": [{ "name": "a" }, { "name": "b", "note": "c" }],
+        "list": { "value": 1, "next": { "value": 2 } },
+        "seen": { "a": "2020-01-01T00:00:00+01:00" },
+    });
+
+    let root: generated::Root = serde_json::from_value(instance.clone()).unwrap();
+    assert_eq!(root.list.next.as_ref().unwrap().value, 2);
+    assert_eq!(serde_json::to_value(&root).unwrap(), instance);
+}
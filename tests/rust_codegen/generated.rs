@@ -0,0 +1,26 @@
+// This file was generated by jddf. Do not edit it by hand.
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Root {
+    pub list: Box<Box2>,
+    pub options: Vec2,
+    pub seen: std::collections::HashMap<String, chrono::DateTime<chrono::FixedOffset>>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Box2 {
+    pub value: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<Box<Box2>>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Option2 {
+    pub name: String2,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+pub type String2 = String;
+
+pub type Vec2 = Vec<Option2>;